@@ -0,0 +1,87 @@
+//! Full-screen rasterization with and without the glyph cache.
+//!
+//! Run with `cargo bench --bench glyph_cache`. The uncached case expands
+//! every glyph into a fresh buffer, as the renderer did per cell before the
+//! cache (it also crossed into JS once per cell for `putImageData`, which
+//! this native benchmark cannot measure). The true color case draws more
+//! distinct glyphs than the cache holds, so nearly every lookup misses and
+//! evicts.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use webterm_dos_ansi::font::Font;
+use webterm_dos_ansi::raster::{cell_size, expand_glyph, Framebuffer, GlyphCache, Rgb};
+
+const COLUMNS: usize = 80;
+const ROWS: usize = 25;
+
+/// VGA colors used by the sample screen.
+const COLORS: [Rgb; 8] = [
+    (0x00, 0x00, 0x00),
+    (0xAA, 0x00, 0x00),
+    (0x00, 0xAA, 0x00),
+    (0xAA, 0x55, 0x00),
+    (0x00, 0x00, 0xAA),
+    (0xAA, 0x00, 0xAA),
+    (0x00, 0xAA, 0xAA),
+    (0xAA, 0xAA, 0xAA),
+];
+
+/// A screen of shading blocks and text in a handful of colors, like typical ANSI art.
+fn sample_screen() -> Vec<(u8, Rgb, Rgb)> {
+    const CHARS: &[u8] = b"\xB0\xB1\xB2\xDB\xDC\xDF ANSI art by BBS.land ";
+    (0..COLUMNS * ROWS)
+        .map(|i| {
+            let ch = CHARS[i % CHARS.len()];
+            let fg = COLORS[(i / 7) % COLORS.len()];
+            let bg = COLORS[(i / 80) % 3];
+            (ch, fg, bg)
+        })
+        .collect()
+}
+
+/// A screen where every cell has its own 24-bit foreground color.
+fn true_color_screen() -> Vec<(u8, Rgb, Rgb)> {
+    (0..COLUMNS * ROWS)
+        .map(|i| (0xDB, (i as u8, (i >> 8) as u8, 0x55), COLORS[0]))
+        .collect()
+}
+
+fn bench_full_screen(c: &mut Criterion) {
+    let cells = sample_screen();
+    let font = Font::ega();
+    let mut framebuffer = Framebuffer::new(COLUMNS, ROWS, cell_size(&font));
+
+    c.bench_function("full screen, uncached", |b| {
+        b.iter(|| {
+            for (i, &(ch, fg, bg)) in cells.iter().enumerate() {
+                let mut glyph = Vec::new();
+                expand_glyph(&font, ch, fg, bg, &mut glyph);
+                framebuffer.draw_glyph(i % COLUMNS, i / COLUMNS, &glyph);
+            }
+            black_box(framebuffer.take_dirty())
+        })
+    });
+
+    let mut cache = GlyphCache::default();
+    c.bench_function("full screen, glyph cache", |b| {
+        b.iter(|| {
+            for (i, &(ch, fg, bg)) in cells.iter().enumerate() {
+                framebuffer.draw_glyph(i % COLUMNS, i / COLUMNS, cache.glyph(ch, fg, bg));
+            }
+            black_box(framebuffer.take_dirty())
+        })
+    });
+
+    let true_color = true_color_screen();
+    c.bench_function("full screen, true color", |b| {
+        b.iter(|| {
+            for (i, &(ch, fg, bg)) in true_color.iter().enumerate() {
+                framebuffer.draw_glyph(i % COLUMNS, i / COLUMNS, cache.glyph(ch, fg, bg));
+            }
+            black_box(framebuffer.take_dirty())
+        })
+    });
+}
+
+criterion_group!(benches, bench_full_screen);
+criterion_main!(benches);
@@ -0,0 +1,363 @@
+//! Binary art file formats: format detection and loaders.
+//!
+//! BIN, ADF, IDF, and XBin files store the image as DOS text mode
+//! character/attribute pairs, the same layout the scrollback buffer keeps.
+//! Loaders decode them into an `Image`, which is shown through the same
+//! terminal and renderers as ANSI. See `xbin` for the XBin decoder.
+
+use crate::font::{Font, GLYPH_COUNT};
+use crate::palette::{Palette, PALETTE_SIZE};
+use crate::sauce::{self, DATA_TYPE_BINARY_TEXT, DATA_TYPE_XBIN};
+use crate::screen::{Cell, Screen, MAX_COLUMNS, MAX_DOCUMENT_ROWS};
+use crate::xbin;
+
+/// BIN width when SAUCE doesn't give one.
+const DEFAULT_BIN_COLUMNS: usize = 160;
+
+/// ADF and IDF width and font size.
+const ADF_COLUMNS: usize = 80;
+const FONT_HEIGHT: usize = 16;
+const FONT_SIZE: usize = GLYPH_COUNT * FONT_HEIGHT;
+
+/// ADF: version byte, then the 64-color EGA palette.
+const ADF_PALETTE_SIZE: usize = 64 * 3;
+/// EGA palette entries used for the 16 text mode colors (in DOS order).
+const ADF_COLOR_ENTRIES: [usize; PALETTE_SIZE] = [0, 1, 2, 3, 4, 5, 20, 7, 56, 57, 58, 59, 60, 61, 62, 63];
+
+/// IDF signature ("\x041.4") and header size (signature plus the window
+/// corners x1, y1, x2, y2).
+const IDF_MAGIC: &[u8] = b"\x041.4";
+const IDF_HEADER_SIZE: usize = 12;
+/// IDF run marker: character 1 with attribute 0, then a count and the pair.
+const IDF_RUN: [u8; 2] = [0x01, 0x00];
+
+/// Binary art file format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Raw character/attribute pairs
+    Bin,
+    /// XBin
+    XBin,
+    /// Artworx ADF
+    Adf,
+    /// iCE Draw IDF
+    Idf,
+}
+
+impl Format {
+    /// Format from a file name's extension (case-insensitive).
+    pub fn from_extension(name: &str) -> Option<Self> {
+        let (_, extension) = name.rsplit_once('.')?;
+        match extension.to_lowercase().as_str() {
+            "bin" => Some(Format::Bin),
+            "xb" => Some(Format::XBin),
+            "adf" => Some(Format::Adf),
+            "idf" => Some(Format::Idf),
+            _ => None,
+        }
+    }
+}
+
+/// Detect a binary art format from the file name, a signature, or the SAUCE
+/// DataType. `None` means the content is text (ANSI, ASCII, ...).
+pub fn detect(content: &[u8], name: Option<&str>) -> Option<Format> {
+    if let Some(format) = name.and_then(Format::from_extension) {
+        return Some(format);
+    }
+    if xbin::is_xbin(content) {
+        return Some(Format::XBin);
+    }
+    if content.starts_with(IDF_MAGIC) {
+        return Some(Format::Idf);
+    }
+    match sauce::parse(content)?.data_type {
+        DATA_TYPE_BINARY_TEXT => Some(Format::Bin),
+        DATA_TYPE_XBIN => Some(Format::XBin),
+        _ => None,
+    }
+}
+
+/// A decoded art file.
+pub struct Image {
+    /// Width in characters
+    pub width: usize,
+    /// Height in characters
+    pub height: usize,
+    /// Character/attribute pairs, row by row (colors in DOS order); cells
+    /// past the end are blank
+    pub data: Vec<u8>,
+    /// Whether attribute bit 7 selects a bright background instead of blink
+    pub ice_colors: bool,
+    /// Embedded palette
+    pub palette: Option<Palette>,
+    /// Embedded font
+    pub font: Option<Font>,
+}
+
+impl Image {
+    /// The image as a screen sized to it (the width is limited to
+    /// `MAX_COLUMNS`). Data past `height` rows is ignored.
+    pub fn to_screen(&self, ice_colors: bool) -> Screen {
+        let mut screen = Screen::with_size(self.width, self.height);
+        screen.set_ice_colors(ice_colors);
+        for (i, pair) in self.data.chunks_exact(2).take(self.width * self.height).enumerate() {
+            let cell = Cell::from_attribute(pair[0], pair[1], ice_colors);
+            screen.set_cell(i % self.width, i / self.width, cell);
+        }
+        screen
+    }
+}
+
+/// Decode a binary art file.
+pub fn decode(format: Format, content: &[u8]) -> Result<Image, String> {
+    match format {
+        Format::Bin => decode_bin(content),
+        Format::XBin => xbin::decode(content),
+        Format::Adf => decode_adf(content),
+        Format::Idf => decode_idf(content),
+    }
+}
+
+/// BIN: character/attribute pairs, with the width and iCE colors from SAUCE.
+///
+/// Widths over `MAX_COLUMNS` are rejected.
+fn decode_bin(content: &[u8]) -> Result<Image, String> {
+    let sauce = sauce::parse(content);
+    let width = sauce
+        .as_ref()
+        .and_then(|sauce| sauce.width())
+        .map_or(DEFAULT_BIN_COLUMNS, usize::from);
+    if width > MAX_COLUMNS {
+        return Err(format!("BIN image is too wide ({} columns)", width));
+    }
+    let data = sauce::strip(content).to_vec();
+    Ok(Image {
+        width,
+        height: rows(data.len() / 2, width)?,
+        data,
+        ice_colors: sauce.is_some_and(|sauce| sauce.ice_colors()),
+        palette: None,
+        font: None,
+    })
+}
+
+/// ADF: version byte, 64-color EGA palette, 8x16 font, then 80-column
+/// character/attribute pairs. Always uses iCE colors.
+fn decode_adf(content: &[u8]) -> Result<Image, String> {
+    let content = sauce::strip(content);
+    let rest = content.get(1..).ok_or("ADF file is empty")?;
+    if rest.len() < ADF_PALETTE_SIZE + FONT_SIZE {
+        return Err("ADF file is truncated".to_string());
+    }
+    let (ega, rest) = rest.split_at(ADF_PALETTE_SIZE);
+    let (font, data) = rest.split_at(FONT_SIZE);
+
+    let mut dac = [0; PALETTE_SIZE * 3];
+    for (color, &entry) in dac.chunks_exact_mut(3).zip(ADF_COLOR_ENTRIES.iter()) {
+        color.copy_from_slice(&ega[entry * 3..entry * 3 + 3]);
+    }
+    Ok(Image {
+        width: ADF_COLUMNS,
+        height: rows(data.len() / 2, ADF_COLUMNS)?,
+        data: data.to_vec(),
+        ice_colors: true,
+        palette: Some(Palette::from_dac(&dac)),
+        font: Some(Font::from_raw("ADF", font)?),
+    })
+}
+
+/// IDF: header with the window corners, RLE-compressed image, then an 8x16
+/// font and a 16-color palette. Always uses iCE colors.
+///
+/// Windows wider than `MAX_COLUMNS` are rejected, and the image is expanded
+/// to at most `MAX_DOCUMENT_ROWS` rows.
+fn decode_idf(content: &[u8]) -> Result<Image, String> {
+    let content = sauce::strip(content);
+    if !content.starts_with(IDF_MAGIC) {
+        return Err("Not an IDF file".to_string());
+    }
+    let trailer_size = FONT_SIZE + PALETTE_SIZE * 3;
+    if content.len() < IDF_HEADER_SIZE + trailer_size {
+        return Err("IDF file is truncated".to_string());
+    }
+    let x1 = u16::from_le_bytes([content[4], content[5]]) as usize;
+    let x2 = u16::from_le_bytes([content[8], content[9]]) as usize;
+    let width = match x2.checked_sub(x1) {
+        Some(columns) if columns < MAX_COLUMNS => columns + 1,
+        _ => return Err(format!("IDF window is invalid (columns {} to {})", x1, x2)),
+    };
+    let (image, trailer) = content[IDF_HEADER_SIZE..].split_at(content.len() - IDF_HEADER_SIZE - trailer_size);
+    let (font, dac) = trailer.split_at(FONT_SIZE);
+
+    let data = expand_idf(image, width * MAX_DOCUMENT_ROWS);
+    Ok(Image {
+        width,
+        height: rows(data.len() / 2, width)?,
+        data,
+        ice_colors: true,
+        palette: Some(Palette::from_dac(dac.try_into().expect("palette size"))),
+        font: Some(Font::from_raw("IDF", font)?),
+    })
+}
+
+/// Expand IDF image data into up to `cells` character/attribute pairs, where
+/// the pair 1/0 starts a run of a 16-bit count and the pair to repeat.
+fn expand_idf(mut image: &[u8], cells: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(image.len().min(cells * 2));
+    while image.len() >= 2 && data.len() < cells * 2 {
+        if image[..2] == IDF_RUN && image.len() >= 6 {
+            let count = u16::from_le_bytes([image[2], image[3]]) as usize;
+            data.extend(image[4..6].repeat(count.min(cells - data.len() / 2)));
+            image = &image[6..];
+        } else {
+            data.extend_from_slice(&image[..2]);
+            image = &image[2..];
+        }
+    }
+    data
+}
+
+/// Rows needed for `cells` cells at `width` columns, up to
+/// `MAX_DOCUMENT_ROWS`.
+fn rows(cells: usize, width: usize) -> Result<usize, String> {
+    match cells.div_ceil(width) {
+        0 => Err("Art file is empty".to_string()),
+        rows => Ok(rows.min(MAX_DOCUMENT_ROWS)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ansi_color_to_rgb_tuple;
+
+    /// 4-column BIN (from SAUCE) with a partial last row.
+    const BIN: &[u8] = include_bytes!("../fixtures/plain.bin");
+    /// 80x2 ADF with the standard EGA palette.
+    const ADF: &[u8] = include_bytes!("../fixtures/art.adf");
+    /// 10x2 IDF with runs and an orange DOS color 1, plus SAUCE.
+    const IDF: &[u8] = include_bytes!("../fixtures/art.idf");
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(BIN, None), Some(Format::Bin));
+        assert_eq!(detect(IDF, None), Some(Format::Idf));
+        assert_eq!(detect(ADF, None), None);
+        assert_eq!(detect(ADF, Some("ART.ADF")), Some(Format::Adf));
+        assert_eq!(detect(b"XBIN\x1a", None), Some(Format::XBin));
+        assert_eq!(detect(b"\x1b[0mHi", Some("hi.ans")), None);
+        assert_eq!(detect(b"\x1b[0mHi", Some("hi.bin")), Some(Format::Bin));
+    }
+
+    #[test]
+    fn test_bin() {
+        let image = decode(Format::Bin, BIN).unwrap();
+        assert_eq!((image.width, image.height), (4, 3));
+        assert!(!image.ice_colors);
+
+        let screen = image.to_screen(image.ice_colors);
+        assert_eq!(screen.row_text(0), "ABCD");
+        assert_eq!(screen.row_text(1), "EFGH");
+        assert_eq!(screen.row_text(2), "IJ  ");
+        let cell = screen.get_cell(0, 1).unwrap();
+        assert_eq!((cell.fg, cell.bg, cell.blink), (9, 0, true));
+
+        // iCE colors override: bit 7 is a bright background
+        let cell = *image.to_screen(true).get_cell(0, 1).unwrap();
+        assert_eq!((cell.fg, cell.bg, cell.blink), (9, 8, false));
+
+        // No SAUCE: 160 columns
+        let image = decode(Format::Bin, &[b'x', 0x07].repeat(161)).unwrap();
+        assert_eq!((image.width, image.height), (160, 2));
+    }
+
+    #[test]
+    fn test_bin_too_wide() {
+        // SAUCE FileType 128 and 200: 256 and 400 columns
+        let mut bin = BIN.to_vec();
+        let file_type = bin.len() - 128 + 95;
+        bin[file_type] = 128;
+        assert!(decode(Format::Bin, &bin).is_err());
+        bin[file_type] = 200;
+        assert!(decode(Format::Bin, &bin).is_err());
+
+        // 254 columns still fit
+        bin[file_type] = 127;
+        assert_eq!(decode(Format::Bin, &bin).unwrap().width, 254);
+    }
+
+    #[test]
+    fn test_adf() {
+        let image = decode(Format::Adf, ADF).unwrap();
+        assert_eq!((image.width, image.height), (80, 2));
+        assert!(image.ice_colors);
+
+        let screen = image.to_screen(true);
+        assert!(screen.row_text(0).starts_with("ADF "));
+        // 0xE6: brown on bright yellow
+        let cell = screen.get_cell(0, 1).unwrap();
+        assert_eq!((cell.ch, cell.fg, cell.bg), (0xDB, 3, 11));
+
+        // Brown comes from EGA palette entry 20
+        let palette = image.palette.unwrap();
+        assert_eq!(ansi_color_to_rgb_tuple(3, palette), (0xAA, 0x55, 0x00));
+        assert_eq!(ansi_color_to_rgb_tuple(15, palette), (0xFF, 0xFF, 0xFF));
+
+        let font = image.font.unwrap();
+        assert_eq!((font.width(), font.height()), (8, 16));
+        assert!(font.is_set(b'A', 0, 0));
+    }
+
+    #[test]
+    fn test_idf() {
+        let image = decode(Format::Idf, IDF).unwrap();
+        assert_eq!((image.width, image.height), (10, 2));
+        assert!(image.ice_colors);
+
+        let screen = image.to_screen(true);
+        assert_eq!(screen.row_text(0), "IDF       ");
+        assert_eq!(screen.row_text(1), "\u{DB}".repeat(10));
+        // 0x9C: light red on bright blue
+        let cell = screen.get_cell(9, 1).unwrap();
+        assert_eq!((cell.fg, cell.bg), (9, 12));
+
+        // The IDF palette makes DOS blue orange; ANSI color 4 is DOS blue
+        let palette = image.palette.unwrap();
+        assert_eq!(ansi_color_to_rgb_tuple(4, palette), (0xFF, 0x82, 0x00));
+        assert!(image.font.unwrap().is_set(b'A', 7, 0));
+    }
+
+    /// IDF with the window columns `x1` to `x2` and the given image data.
+    fn idf(x1: u16, x2: u16, image: &[u8]) -> Vec<u8> {
+        let mut idf = IDF_MAGIC.to_vec();
+        for corner in [x1, 0, x2, 0] {
+            idf.extend(corner.to_le_bytes());
+        }
+        idf.extend(image);
+        idf.extend([0; FONT_SIZE + PALETTE_SIZE * 3]);
+        idf
+    }
+
+    #[test]
+    fn test_idf_limits() {
+        // Window from column 10 to 19
+        let image = decode(Format::Idf, &idf(10, 19, &b"x\x07".repeat(15))).unwrap();
+        assert_eq!((image.width, image.height), (10, 2));
+
+        // Runs of 65535 cells stop at the document row limit
+        let image = decode(Format::Idf, &idf(0, 79, &b"\x01\x00\xff\xff\xdb\x07".repeat(20))).unwrap();
+        assert_eq!((image.width, image.height), (80, MAX_DOCUMENT_ROWS));
+        assert_eq!(image.data.len(), 80 * MAX_DOCUMENT_ROWS * 2);
+
+        assert!(decode(Format::Idf, &idf(0, 0xFFFF, b"x\x07")).is_err());
+        assert!(decode(Format::Idf, &idf(20, 10, b"x\x07")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        assert!(decode(Format::Bin, b"").is_err());
+        assert!(decode(Format::Adf, &ADF[..1000]).is_err());
+        assert!(decode(Format::Idf, &IDF[..100]).is_err());
+        assert!(decode(Format::Idf, ADF).is_err());
+    }
+}
@@ -0,0 +1,521 @@
+//! WebSocket connection for `data-term-url` terminals.
+//!
+//! Shows a pre-connect splash, opens a binary WebSocket when the user presses
+//! Enter (or clicks), feeds received bytes through the telnet filter into the
+//! terminal, and offers to reconnect when the connection drops.
+
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{BinaryType, CloseEvent, HtmlCanvasElement, HtmlElement, MessageEvent, MouseEvent, WebSocket};
+
+use crate::cp437;
+use crate::events::FrameScheduler;
+use crate::keyboard::OutputSink;
+use crate::telnet::TelnetFilter;
+use crate::terminal::Terminal;
+
+/// WebSocket event handler closure.
+type SocketHandler = Closure<dyn FnMut(JsValue)>;
+
+/// Connection lifecycle state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Showing the pre-connect screen, waiting for the user
+    Idle,
+    /// WebSocket opened, waiting for the handshake
+    Connecting,
+    /// Bridged to the host
+    Connected,
+    /// Connection closed or failed, waiting for the user to reconnect
+    Disconnected,
+}
+
+/// A terminal bound to a WebSocket URL.
+///
+/// The connection is also the terminal's output sink: while connected,
+/// keystrokes go to the socket; otherwise Enter starts a (re)connect.
+pub struct Connection {
+    url: String,
+    terminal: Rc<RefCell<Terminal>>,
+    frame: Rc<FrameScheduler>,
+    /// Telnet negotiation (None when `data-term-telnet="false"`)
+    telnet: Option<RefCell<TelnetFilter>>,
+    /// Custom pre-connect screen (CP437 ANSI)
+    preconnect_screen: Option<Vec<u8>>,
+    /// Optional connect button overlay
+    connect_button: Option<HtmlElement>,
+    state: Cell<ConnectionState>,
+    socket: RefCell<Option<WebSocket>>,
+    /// Socket event handlers, kept alive while the socket is open
+    socket_handlers: RefCell<Vec<SocketHandler>>,
+    me: Weak<Connection>,
+}
+
+/// Options read from the container's data attributes.
+pub struct ConnectionOptions {
+    pub url: String,
+    pub telnet: bool,
+    pub preconnect_screen: Option<String>,
+    pub connect_button: Option<String>,
+}
+
+impl Connection {
+    /// Create a connection for a terminal and show the pre-connect screen.
+    ///
+    /// The connection lives for the lifetime of the page.
+    pub fn start(
+        options: ConnectionOptions,
+        terminal: Rc<RefCell<Terminal>>,
+        frame: Rc<FrameScheduler>,
+        canvas: &HtmlCanvasElement,
+    ) -> Result<Rc<Connection>, JsValue> {
+        let connect_button = match &options.connect_button {
+            Some(src) => Some(create_connect_button(canvas, src)?),
+            None => None,
+        };
+
+        let connection = Rc::new_cyclic(|me| Connection {
+            url: options.url,
+            terminal: terminal.clone(),
+            frame,
+            telnet: options.telnet.then(|| RefCell::new(telnet_filter(&terminal))),
+            preconnect_screen: options.preconnect_screen.as_deref().map(text_to_cp437),
+            connect_button,
+            state: Cell::new(ConnectionState::Idle),
+            socket: RefCell::new(None),
+            socket_handlers: RefCell::new(Vec::new()),
+            me: me.clone(),
+        });
+
+        // Keystrokes are routed through the connection
+        connection
+            .terminal
+            .borrow_mut()
+            .set_output_sink(connection.clone());
+
+        // Clicking the canvas (or the button) connects when idle
+        {
+            let weak = Rc::downgrade(&connection);
+            let closure = Closure::<dyn Fn(MouseEvent)>::new(move |_event: MouseEvent| {
+                if let Some(connection) = weak.upgrade() {
+                    if connection.state.get() != ConnectionState::Connected
+                        && connection.state.get() != ConnectionState::Connecting
+                    {
+                        connection.connect();
+                    }
+                }
+            });
+            canvas.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;
+            if let Some(button) = &connection.connect_button {
+                button.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;
+            }
+            closure.forget();
+        }
+
+        connection.show_preconnect_screen();
+        Ok(connection)
+    }
+
+    /// Open the WebSocket (no-op if already connecting or connected).
+    pub fn connect(&self) {
+        if matches!(self.state.get(), ConnectionState::Connecting | ConnectionState::Connected) {
+            return;
+        }
+
+        self.set_state(ConnectionState::Connecting);
+        if let Some(telnet) = &self.telnet {
+            *telnet.borrow_mut() = telnet_filter(&self.terminal);
+        }
+        {
+            let mut term = self.terminal.borrow_mut();
+            term.reset();
+            term.process_bytes(&status_screen(b"\x1b[1;33m", &format!("Connecting to {}...", self.url)));
+        }
+        self.frame.request();
+
+        if let Err(e) = self.open_socket() {
+            web_sys::console::error_1(&format!("WebTerm: Failed to open WebSocket: {:?}", e).into());
+            self.on_close();
+        }
+    }
+
+    fn open_socket(&self) -> Result<(), JsValue> {
+        let socket = WebSocket::new(&self.url)?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let mut handlers = Vec::new();
+
+        let weak = self.me.clone();
+        let on_open = SocketHandler::new(move |_: JsValue| {
+            if let Some(connection) = weak.upgrade() {
+                connection.on_open();
+            }
+        });
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        handlers.push(on_open);
+
+        let weak = self.me.clone();
+        let on_message = SocketHandler::new(move |event: JsValue| {
+            if let (Some(connection), Ok(event)) = (weak.upgrade(), event.dyn_into::<MessageEvent>()) {
+                if let Ok(buffer) = event.data().dyn_into::<js_sys::ArrayBuffer>() {
+                    connection.on_data(&js_sys::Uint8Array::new(&buffer).to_vec());
+                } else if let Some(text) = event.data().as_string() {
+                    connection.on_data(&text_to_cp437(&text));
+                }
+            }
+        });
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        handlers.push(on_message);
+
+        let weak = self.me.clone();
+        let on_close = SocketHandler::new(move |event: JsValue| {
+            if let Some(connection) = weak.upgrade() {
+                if let Ok(event) = event.dyn_into::<CloseEvent>() {
+                    web_sys::console::log_1(&format!(
+                        "WebTerm: Connection closed (code {}, clean: {})",
+                        event.code(),
+                        event.was_clean()
+                    ).into());
+                }
+                connection.on_close();
+            }
+        });
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        handlers.push(on_close);
+
+        *self.socket.borrow_mut() = Some(socket);
+        *self.socket_handlers.borrow_mut() = handlers;
+        Ok(())
+    }
+
+    fn on_open(&self) {
+        web_sys::console::log_1(&format!("WebTerm: Connected to {}", self.url).into());
+        self.set_state(ConnectionState::Connected);
+        {
+            let mut term = self.terminal.borrow_mut();
+            term.reset();
+        }
+        self.frame.request();
+    }
+
+    /// Feed bytes received from the host into the terminal.
+    fn on_data(&self, bytes: &[u8]) {
+        let reply = match &self.telnet {
+            Some(telnet) => {
+                let output = telnet.borrow_mut().process(bytes);
+                self.terminal.borrow_mut().process_bytes(&output.data);
+                output.reply
+            }
+            None => {
+                self.terminal.borrow_mut().process_bytes(bytes);
+                Vec::new()
+            }
+        };
+
+        if !reply.is_empty() {
+            self.send_raw(&reply);
+        }
+        self.frame.request();
+    }
+
+    fn on_close(&self) {
+        let was_connected = self.state.get() == ConnectionState::Connected;
+        self.set_state(ConnectionState::Disconnected);
+
+        if let Some(socket) = self.socket.borrow_mut().take() {
+            socket.set_onopen(None);
+            socket.set_onmessage(None);
+            socket.set_onclose(None);
+            let _ = socket.close();
+        }
+        // Drop handlers after the current event finishes dispatching
+        let handlers = std::mem::take(&mut *self.socket_handlers.borrow_mut());
+        drop_later(handlers);
+
+        let message = if was_connected {
+            "Connection lost."
+        } else {
+            "Unable to connect."
+        };
+        {
+            let mut term = self.terminal.borrow_mut();
+            let mut notice = b"\x1b[0m\r\n\r\n\x1b[1;31m".to_vec();
+            notice.extend_from_slice(message.as_bytes());
+            notice.extend_from_slice(b"\x1b[0;37m Press \x1b[1;37mEnter\x1b[0;37m to reconnect.\x1b[0m");
+            term.process_bytes(&notice);
+        }
+        self.frame.request();
+    }
+
+    fn show_preconnect_screen(&self) {
+        {
+            let mut term = self.terminal.borrow_mut();
+            term.reset();
+            match &self.preconnect_screen {
+                Some(screen) => term.process_bytes(screen),
+                None => {
+                    let (columns, rows) = term.screen.dimensions();
+                    term.process_bytes(&splash_screen(&self.url, columns, rows));
+                }
+            }
+        }
+        self.frame.request();
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        self.state.set(state);
+        if let Some(button) = &self.connect_button {
+            let display = match state {
+                ConnectionState::Idle | ConnectionState::Disconnected => "block",
+                ConnectionState::Connecting | ConnectionState::Connected => "none",
+            };
+            let _ = button.style().set_property("display", display);
+        }
+    }
+
+    /// Send bytes to the socket without telnet escaping.
+    fn send_raw(&self, bytes: &[u8]) {
+        if let Some(socket) = self.socket.borrow().as_ref() {
+            OutputSink::send(socket, bytes);
+        }
+    }
+}
+
+impl OutputSink for Connection {
+    fn send(&self, bytes: &[u8]) {
+        match self.state.get() {
+            ConnectionState::Connected => match &self.telnet {
+                Some(telnet) => self.send_raw(&telnet.borrow().escape_outbound(bytes)),
+                None => self.send_raw(bytes),
+            },
+            ConnectionState::Idle | ConnectionState::Disconnected => {
+                if bytes == b"\r" {
+                    self.connect();
+                }
+            }
+            ConnectionState::Connecting => {}
+        }
+    }
+}
+
+/// Create a telnet filter that reports the terminal's screen size via NAWS.
+fn telnet_filter(terminal: &RefCell<Terminal>) -> TelnetFilter {
+    let (columns, rows) = terminal.borrow().screen.dimensions();
+    let mut filter = TelnetFilter::new();
+    filter.set_window_size(columns as u16, rows as u16);
+    filter
+}
+
+/// Release closures on the next tick (they may still be on the call stack).
+fn drop_later(handlers: Vec<SocketHandler>) {
+    if handlers.is_empty() {
+        return;
+    }
+    let handlers = RefCell::new(Some(handlers));
+    let closure = Closure::once_into_js(move || {
+        handlers.borrow_mut().take();
+    });
+    if let Some(window) = web_sys::window() {
+        let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(closure.unchecked_ref(), 0);
+    }
+}
+
+/// Convert a JavaScript string to CP437 bytes.
+///
+/// Characters without a CP437 equivalent become '?'. Control characters
+/// (ESC, CR, LF) pass through unchanged.
+pub fn text_to_cp437(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|ch| match ch {
+            '\0'..='\x1f' | '\x7f' => ch as u8,
+            _ => cp437::from_unicode(ch).unwrap_or(b'?'),
+        })
+        .collect()
+}
+
+/// Build the default "Press Enter to connect" splash screen, centered on a
+/// screen of the given size. Screens too small for the box get the text lines
+/// alone.
+fn splash_screen(url: &str, columns: usize, rows: usize) -> Vec<u8> {
+    const WIDTH: usize = 50;
+    const HEIGHT: usize = 9;
+    let boxed = columns >= WIDTH && rows >= HEIGHT;
+    let (width, height) = if boxed { (WIDTH, HEIGHT) } else { (columns, rows.min(5)) };
+    let left = columns.saturating_sub(width) / 2;
+    // Rows are 0-based here; CSI positions are 1-based
+    let top = rows.saturating_sub(height) / 2;
+
+    let mut out = b"\x1b[0m\x1b[2J".to_vec();
+    let margin = if boxed { 4 } else { 2 };
+    let center = |out: &mut Vec<u8>, row: usize, attr: &[u8], text: &[u8]| {
+        let (text, visible) = truncate_visible(text, width.saturating_sub(margin));
+        let col = left + (width - visible) / 2;
+        out.extend_from_slice(format!("\x1b[{};{}H", row + 1, col + 1).as_bytes());
+        out.extend_from_slice(attr);
+        out.extend_from_slice(&text);
+    };
+
+    let text_rows = if boxed {
+        // Double-line box (CP437 box drawing characters)
+        let bottom = top + HEIGHT - 1;
+        out.extend_from_slice(b"\x1b[1;34;40m");
+        for row in top..=bottom {
+            out.extend_from_slice(format!("\x1b[{};{}H", row + 1, left + 1).as_bytes());
+            let (left, fill, right) = if row == top {
+                (0xC9, 0xCD, 0xBB)
+            } else if row == bottom {
+                (0xC8, 0xCD, 0xBC)
+            } else {
+                (0xBA, b' ', 0xBA)
+            };
+            out.push(left);
+            out.extend(std::iter::repeat_n(fill, WIDTH - 2));
+            out.push(right);
+        }
+        [top + 2, top + 4, top + 6]
+    } else if height >= 5 {
+        [top, top + 2, top + 4]
+    } else {
+        [top, top + 1, top + 2]
+    };
+
+    let lines: [(&[u8], Vec<u8>); 3] = [
+        (b"\x1b[1;37m", b"BBS.land WebTerm".to_vec()),
+        (b"\x1b[0;36m", text_to_cp437(url)),
+        (b"\x1b[0;37m", b"Press \x1b[1;37mEnter\x1b[0;37m to connect".to_vec()),
+    ];
+    for (row, (attr, text)) in text_rows.into_iter().zip(lines) {
+        if row < rows {
+            center(&mut out, row, attr, &text);
+        }
+    }
+    out.extend_from_slice(format!("\x1b[0m\x1b[{};1H", rows).as_bytes());
+    out
+}
+
+/// Cut text to `max` visible characters, keeping embedded CSI sequences
+/// (ESC [ ... final byte) whole. Returns the bytes and their visible length.
+fn truncate_visible(text: &[u8], max: usize) -> (Vec<u8>, usize) {
+    let mut out = Vec::with_capacity(text.len());
+    let mut visible = 0;
+    let mut rest = text;
+    while let Some(&byte) = rest.first() {
+        if rest.starts_with(b"\x1b[") {
+            // An unterminated sequence is dropped
+            let Some(end) = rest[2..].iter().position(|b| (0x40..=0x7E).contains(b)) else { break };
+            out.extend_from_slice(&rest[..end + 3]);
+            rest = &rest[end + 3..];
+        } else if visible < max {
+            out.push(byte);
+            visible += 1;
+            rest = &rest[1..];
+        } else {
+            break;
+        }
+    }
+    (out, visible)
+}
+
+/// Build a cleared screen with a single status line.
+fn status_screen(attr: &[u8], text: &str) -> Vec<u8> {
+    let mut out = b"\x1b[0m\x1b[2J".to_vec();
+    out.extend_from_slice(attr);
+    out.extend_from_slice(&text_to_cp437(text));
+    out.extend_from_slice(b"\x1b[0m\r\n");
+    out
+}
+
+/// Create the connect button overlay centered over the canvas.
+fn create_connect_button(canvas: &HtmlCanvasElement, src: &str) -> Result<HtmlElement, JsValue> {
+    let doc = crate::dom::document()?;
+    let button = doc.create_element("img")?.dyn_into::<HtmlElement>()?;
+    button.set_attribute("src", src)?;
+    button.set_attribute("alt", "Connect")?;
+
+    let style = button.style();
+    style.set_property("position", "absolute")?;
+    style.set_property("left", "50%")?;
+    style.set_property("top", "50%")?;
+    style.set_property("transform", "translate(-50%, -50%)")?;
+    style.set_property("cursor", "pointer")?;
+    style.set_property("max-width", "50%")?;
+
+    if let Some(parent) = canvas.parent_element() {
+        if let Some(parent) = parent.dyn_ref::<HtmlElement>() {
+            parent.style().set_property("position", "relative")?;
+        }
+        parent.append_child(&button)?;
+    }
+    Ok(button)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_to_cp437() {
+        assert_eq!(text_to_cp437("Hi\x1b[1m\r\n"), b"Hi\x1b[1m\r\n".to_vec());
+        assert_eq!(text_to_cp437("░█"), vec![0xB0, 0xDB]);
+        assert_eq!(text_to_cp437("€"), vec![b'?']);
+    }
+
+    #[test]
+    fn test_splash_screen_renders() {
+        let mut term = Terminal::new();
+        term.process_bytes(&splash_screen("wss://bbs.example.com/ws", 80, 25));
+        // 8 rows above the 9-row box and 8 below
+        let row: Vec<u8> = (0..80).map(|x| term.screen.get_cell(x, 8).unwrap().ch).collect();
+        assert_eq!(row[15], 0xC9);
+        assert_eq!(row[64], 0xBB);
+        assert_eq!(term.screen.get_cell(15, 16).unwrap().ch, 0xC8);
+        assert!(term.screen.row_text(14).contains("Press Enter to connect"));
+    }
+
+    #[test]
+    fn test_splash_screen_on_small_screen() {
+        let mut term = Terminal::with_size(40, 8, 0);
+        term.process_bytes(&splash_screen("wss://bbs.example.com/ws", 40, 8));
+        // No box: the text lines alone, centered, and nothing scrolled
+        assert_eq!(term.scrollback.history_len(), 0);
+        assert!((0..8).all(|y| !term.screen.row_text(y).contains('\u{C9}')));
+        assert_eq!(term.screen.row_text(1).trim(), "BBS.land WebTerm");
+        assert_eq!(term.screen.row_text(3).trim(), "wss://bbs.example.com/ws");
+        assert_eq!(term.screen.row_text(5).trim(), "Press Enter to connect");
+        assert_eq!(term.screen.row_text(5).find('P'), Some(9));
+
+        let mut term = Terminal::with_size(20, 3, 0);
+        term.process_bytes(&splash_screen("wss://bbs.example.com/ws", 20, 3));
+        assert_eq!(term.scrollback.history_len(), 0);
+        assert_eq!(term.screen.row_text(0).trim(), "BBS.land WebTerm");
+        assert_eq!(term.screen.row_text(2).trim(), "Press Enter to con");
+    }
+
+    #[test]
+    fn test_splash_text_is_centered() {
+        let mut term = Terminal::new();
+        term.process_bytes(&splash_screen("wss://bbs.example.com/ws", 80, 25));
+        let row: Vec<u8> = (0..80).map(|x| term.screen.get_cell(x, 14).unwrap().ch).collect();
+        // 22 characters inside the 50-column box at columns 15-64
+        let start = row.windows(22).position(|w| w == b"Press Enter to connect");
+        assert_eq!(start, Some(29));
+    }
+
+    #[test]
+    fn test_truncate_visible() {
+        let text = b"Press \x1b[1;37mEnter\x1b[0;37m to connect";
+        assert_eq!(truncate_visible(text, 50), (text.to_vec(), 22));
+        assert_eq!(truncate_visible(text, 8), (b"Press \x1b[1;37mEn".to_vec(), 8));
+        assert_eq!(truncate_visible(b"ab\x1b[1;3", 5), (b"ab".to_vec(), 2));
+    }
+
+    #[test]
+    fn test_splash_screen_centers_on_wide_screen() {
+        let mut term = Terminal::with_size(132, 50, 0);
+        term.process_bytes(&splash_screen("wss://bbs.example.com/ws", 132, 50));
+        assert_eq!(term.screen.get_cell(41, 20).unwrap().ch, 0xC9);
+        assert_eq!(term.screen.get_cell(90, 28).unwrap().ch, 0xBC);
+    }
+}
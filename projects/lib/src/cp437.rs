@@ -0,0 +1,99 @@
+//! CP437 (DOS codepage 437) character mapping.
+//!
+//! Maps between CP437 byte values and Unicode characters, including the
+//! graphical glyphs DOS displays for control codes.
+
+/// Unicode equivalents for CP437 bytes 0x00-0x1F (DOS displays these as glyphs).
+const CP437_LOW: [char; 32] = [
+    '\u{0000}', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+];
+
+/// Unicode equivalents for CP437 bytes 0x80-0xFF.
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{00A0}',
+];
+
+/// Convert a CP437 byte to the Unicode character DOS displays for it.
+pub fn to_unicode(byte: u8) -> char {
+    match byte {
+        0x00..=0x1F => CP437_LOW[byte as usize],
+        0x7F => '⌂',
+        0x80..=0xFF => CP437_HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+/// Convert a Unicode character to its CP437 byte, if it has one.
+///
+/// Printable ASCII maps to itself; control-code glyphs (☺, ♥, ...) are not
+/// mapped since those bytes would be interpreted as control characters.
+pub fn from_unicode(ch: char) -> Option<u8> {
+    if (' '..='~').contains(&ch) {
+        return Some(ch as u8);
+    }
+    CP437_HIGH
+        .iter()
+        .position(|&c| c == ch)
+        .map(|i| 0x80 + i as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_unicode() {
+        assert_eq!(to_unicode(b'A'), 'A');
+        assert_eq!(to_unicode(0x01), '☺');
+        assert_eq!(to_unicode(0x7F), '⌂');
+        assert_eq!(to_unicode(0xB0), '░');
+        assert_eq!(to_unicode(0xFF), '\u{00A0}');
+    }
+
+    #[test]
+    fn test_roundtrip_high() {
+        for byte in 0x80..=0xFFu8 {
+            assert_eq!(from_unicode(to_unicode(byte)), Some(byte));
+        }
+    }
+
+    #[test]
+    fn test_from_unicode_ascii() {
+        assert_eq!(from_unicode('A'), Some(b'A'));
+        assert_eq!(from_unicode(' '), Some(b' '));
+        assert_eq!(from_unicode('~'), Some(b'~'));
+    }
+
+    #[test]
+    fn test_from_unicode_high() {
+        assert_eq!(from_unicode('Ç'), Some(0x80));
+        assert_eq!(from_unicode('░'), Some(0xB0));
+        assert_eq!(from_unicode('█'), Some(0xDB));
+        assert_eq!(from_unicode('═'), Some(0xCD));
+        assert_eq!(from_unicode('\u{00A0}'), Some(0xFF));
+    }
+
+    #[test]
+    fn test_from_unicode_unmapped() {
+        assert_eq!(from_unicode('€'), None);
+        assert_eq!(from_unicode('☺'), None);
+        assert_eq!(from_unicode('\n'), None);
+    }
+
+    #[test]
+    fn test_tables_have_no_duplicates() {
+        for (i, a) in CP437_HIGH.iter().enumerate() {
+            for b in &CP437_HIGH[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
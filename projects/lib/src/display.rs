@@ -0,0 +1,177 @@
+//! Render pipelines from terminal state to the display canvas.
+//!
+//! - `Canvas`: cells are rasterized in Rust onto an offscreen 2D canvas, which
+//!   is uploaded to WebGL for post-processing every frame
+//! - `WebGl`: the cell grid is uploaded as a small texture and glyphs are
+//!   drawn by a fragment shader, then post-processed
+//! - `Direct`: fallback without WebGL; cells are rasterized straight onto a 2D
+//!   display canvas, with an optional CSS-filter blur
+
+use std::rc::Rc;
+use wasm_bindgen::JsValue;
+use web_sys::HtmlCanvasElement;
+
+use crate::dom;
+use crate::font::Font;
+use crate::gl_renderer::GlRenderer;
+use crate::postprocess::PostProcessor;
+use crate::palette::Palette;
+use crate::renderer::{canvas_size, Renderer};
+use crate::screen::Screen;
+use crate::scrollback::ScrollbackBuffer;
+use crate::webterm::TerminalConfig;
+
+/// CSS blur radius per pixel of post-processing blur spread (the canvas is
+/// usually shown at about half its size).
+const CSS_BLUR_PER_PIXEL: f32 = 0.5;
+
+/// Which text renderer to use.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RendererKind {
+    /// 2D canvas (default)
+    #[default]
+    Canvas,
+    /// Fragment shader glyphs
+    WebGl,
+}
+
+impl RendererKind {
+    /// Parse renderer kind from string (case-insensitive)
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "webgl" => RendererKind::WebGl,
+            _ => RendererKind::Canvas,
+        }
+    }
+}
+
+/// A text renderer plus post-processing for one display canvas.
+pub enum Display {
+    Canvas {
+        renderer: Renderer,
+        offscreen_canvas: HtmlCanvasElement,
+        post_processor: PostProcessor,
+    },
+    WebGl {
+        renderer: GlRenderer,
+        post_processor: PostProcessor,
+    },
+    Direct {
+        renderer: Renderer,
+    },
+}
+
+impl Display {
+    /// Create a display canvas sized to the screen and font, and its pipeline.
+    ///
+    /// Falls back to `Direct` rendering when the WebGL context can't be
+    /// created or a shader fails to build.
+    pub fn create(
+        columns: usize,
+        rows: usize,
+        config: &TerminalConfig,
+    ) -> Result<(HtmlCanvasElement, Display), JsValue> {
+        let font = config.cell_font();
+        let (width, height) = canvas_size(columns, rows, &font);
+        let canvas = dom::create_canvas(width, height)?;
+        let (canvas, display) = match Display::new(&canvas, config, font.clone()) {
+            Ok(display) => (canvas, display),
+            Err(e) => {
+                web_sys::console::warn_1(&format!(
+                    "WebTerm: WebGL unavailable ({:?}), falling back to 2D canvas rendering",
+                    e
+                ).into());
+                // A canvas that created a WebGL context can't switch to 2D
+                let canvas = dom::create_canvas(width, height)?;
+                let display = Display::direct(&canvas, config, font)?;
+                (canvas, display)
+            }
+        };
+        // Only for the canvas that is kept
+        dom::add_fullscreen_css(&canvas)?;
+        Ok((canvas, display))
+    }
+
+    /// Create the WebGL pipeline for a display canvas (already sized to the screen).
+    pub fn new(canvas: &HtmlCanvasElement, config: &TerminalConfig, font: Rc<Font>) -> Result<Self, JsValue> {
+        let post_processor = PostProcessor::new(canvas, config.effects)?;
+        match config.renderer {
+            RendererKind::Canvas => {
+                let offscreen_canvas = dom::create_offscreen_canvas(canvas.width(), canvas.height())?;
+                let renderer = Renderer::new(&offscreen_canvas, config.palette, font)?;
+                Ok(Display::Canvas {
+                    renderer,
+                    offscreen_canvas,
+                    post_processor,
+                })
+            }
+            RendererKind::WebGl => Ok(Display::WebGl {
+                renderer: GlRenderer::new(post_processor.gl(), config.palette, &font)?,
+                post_processor,
+            }),
+        }
+    }
+
+    /// Create the 2D fallback pipeline, approximating the blur with a CSS filter.
+    pub fn direct(canvas: &HtmlCanvasElement, config: &TerminalConfig, font: Rc<Font>) -> Result<Self, JsValue> {
+        let blur = config.effects.clamped().blur;
+        if blur > 0.0 {
+            canvas
+                .style()
+                .set_property("filter", &format!("blur({}px)", blur * CSS_BLUR_PER_PIXEL))?;
+        }
+        Ok(Display::Direct {
+            renderer: Renderer::new(canvas, config.palette, font)?,
+        })
+    }
+
+    /// Name of the rendering path: "webgl", "canvas", or "2d" (no WebGL).
+    pub fn path(&self) -> &'static str {
+        match self {
+            Display::Canvas { .. } => "canvas",
+            Display::WebGl { .. } => "webgl",
+            Display::Direct { .. } => "2d",
+        }
+    }
+
+    /// Render the current view (scrollback position or live screen).
+    pub fn render(&self, screen: &Screen, scrollback: &ScrollbackBuffer) -> Result<(), JsValue> {
+        match self {
+            Display::Canvas {
+                renderer,
+                offscreen_canvas,
+                post_processor,
+            } => {
+                renderer.render_with_scrollback(screen, scrollback)?;
+                post_processor.process(offscreen_canvas)
+            }
+            Display::WebGl {
+                renderer,
+                post_processor,
+            } => {
+                post_processor.bind_source();
+                renderer.render(screen, scrollback)?;
+                post_processor.apply()
+            }
+            Display::Direct { renderer } => renderer.render_with_scrollback(screen, scrollback),
+        }
+    }
+
+    /// Change the color palette (takes effect on the next render).
+    pub fn set_palette(&self, palette: Palette) {
+        match self {
+            Display::Canvas { renderer, .. } => renderer.set_palette(palette),
+            Display::WebGl { renderer, .. } => renderer.set_palette(palette),
+            Display::Direct { renderer } => renderer.set_palette(palette),
+        }
+    }
+
+    /// Advance the blink phase (takes effect on the next render).
+    pub fn toggle_blink(&self) {
+        match self {
+            Display::Canvas { renderer, .. } => renderer.toggle_blink(),
+            Display::WebGl { renderer, .. } => renderer.toggle_blink(),
+            Display::Direct { renderer } => renderer.toggle_blink(),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-/// DOM manipulation utilities for creating and managing terminal elements.
+//! DOM manipulation utilities for creating and managing terminal elements.
 
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
@@ -17,6 +17,9 @@ pub fn document() -> Result<Document, JsValue> {
 }
 
 /// Create a canvas element with the specified dimensions and display styles.
+///
+/// The fullscreen rules are added separately (`add_fullscreen_css`), once the
+/// canvas is known to be kept.
 pub fn create_canvas(width: u32, height: u32) -> Result<HtmlCanvasElement, JsValue> {
     let doc = document()?;
     let canvas = doc
@@ -30,9 +33,6 @@ pub fn create_canvas(width: u32, height: u32) -> Result<HtmlCanvasElement, JsVal
     let class_name = format!("webterm-canvas-{}", js_sys::Math::random().to_bits());
     canvas.set_class_name(&class_name);
 
-    // Inject fullscreen CSS rules for this canvas
-    inject_fullscreen_css(&doc, &class_name, width, height)?;
-
     // Apply CSS for responsive scaling
     let style = canvas.style();
     style.set_property("max-width", "100%")?;
@@ -45,6 +45,11 @@ pub fn create_canvas(width: u32, height: u32) -> Result<HtmlCanvasElement, JsVal
     Ok(canvas)
 }
 
+/// Add the fullscreen CSS rules for a canvas from `create_canvas`.
+pub fn add_fullscreen_css(canvas: &HtmlCanvasElement) -> Result<(), JsValue> {
+    inject_fullscreen_css(&document()?, &canvas.class_name(), canvas.width(), canvas.height())
+}
+
 /// Inject CSS rules for fullscreen mode.
 ///
 /// In fullscreen, the canvas should:
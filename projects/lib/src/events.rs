@@ -0,0 +1,381 @@
+//! Browser event wiring for a terminal canvas: scrollback input (wheel,
+//! keys, clicks), render scheduling per animation frame, and the blink timer.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlCanvasElement, KeyboardEvent, MouseEvent, WheelEvent};
+
+use crate::display::Display;
+use crate::keyboard::Modifiers;
+use crate::terminal::{KeyInput, Terminal};
+
+/// Event listeners attached to a terminal canvas.
+///
+/// Listeners stay attached while this value is alive. Call `remove()` to
+/// detach them.
+pub struct EventListeners {
+    canvas: HtmlCanvasElement,
+    listeners: Vec<(&'static str, js_sys::Function, Box<dyn std::any::Any>)>,
+}
+
+impl EventListeners {
+    fn new(canvas: &HtmlCanvasElement) -> Self {
+        EventListeners {
+            canvas: canvas.clone(),
+            listeners: Vec::new(),
+        }
+    }
+
+    /// Keep a closure alive for as long as its listener is attached.
+    fn push<T: ?Sized + 'static>(&mut self, event: &'static str, closure: Closure<T>) {
+        let function: js_sys::Function = closure.as_ref().clone().unchecked_into();
+        self.listeners.push((event, function, Box::new(closure)));
+    }
+
+    /// Detach all listeners from the canvas and release their closures.
+    pub fn remove(self) {
+        for (event, function, _closure) in &self.listeners {
+            let _ = self.canvas.remove_event_listener_with_callback(event, function);
+        }
+    }
+}
+
+/// Set up event listeners for scrollback on a canvas element.
+///
+/// This attaches wheel, keyboard, and click events to the canvas container.
+pub fn setup_scrollback_events(
+    canvas: &HtmlCanvasElement,
+    terminal: Rc<RefCell<Terminal>>,
+    display: Rc<Display>,
+) -> Result<EventListeners, JsValue> {
+    let mut listeners = EventListeners::new(canvas);
+
+    // Make canvas focusable for keyboard events
+    canvas.set_tab_index(0);
+
+    // Create options for passive: false (required to preventDefault on wheel)
+    let wheel_options = web_sys::AddEventListenerOptions::new();
+    wheel_options.set_passive(false);
+
+    // Set up wheel event listener with passive: false
+    {
+        let terminal = terminal.clone();
+        let display = display.clone();
+
+        let closure = Closure::<dyn Fn(WheelEvent)>::new(move |event: WheelEvent| {
+            // Always capture wheel events on canvas to prevent page scrolling
+            event.prevent_default();
+            event.stop_propagation();
+
+            let mut term = terminal.borrow_mut();
+            let was_animating = term.scrollback.is_animating_exit();
+            if term.handle_wheel(event.delta_y()) {
+                // Re-render with scrollback
+                let _ = display.render(&term.screen, &term.scrollback);
+
+                // Start animation only if it just started (wasn't already running)
+                if !was_animating && term.scrollback.is_animating_exit() {
+                    drop(term); // Release borrow before starting animation
+                    start_exit_animation(terminal.clone(), display.clone());
+                }
+            }
+        });
+
+        canvas.add_event_listener_with_callback_and_add_event_listener_options(
+            "wheel",
+            closure.as_ref().unchecked_ref(),
+            &wheel_options,
+        )?;
+        listeners.push("wheel", closure); // Keep the closure alive
+    }
+
+    // Set up keyboard event listener on the canvas itself (requires focus)
+    {
+        let terminal = terminal.clone();
+        let display = display.clone();
+        let canvas_for_fullscreen = canvas.clone();
+
+        let closure = Closure::<dyn Fn(KeyboardEvent)>::new(move |event: KeyboardEvent| {
+            // Handle Alt+Enter for fullscreen toggle
+            if event.key() == "Enter" && event.alt_key() {
+                event.prevent_default();
+                event.stop_propagation();
+                toggle_fullscreen(&canvas_for_fullscreen);
+                return;
+            }
+
+            let was_animating = terminal.borrow().scrollback.is_animating_exit();
+            let modifiers = Modifiers {
+                ctrl: event.ctrl_key(),
+                alt: event.alt_key(),
+                shift: event.shift_key(),
+            };
+            match Terminal::key_down(&terminal, &event.key(), modifiers) {
+                KeyInput::Local => {
+                    event.prevent_default();
+                    event.stop_propagation();
+
+                    // Re-render with scrollback
+                    let term = terminal.borrow();
+                    let _ = display.render(&term.screen, &term.scrollback);
+
+                    // Start animation only if it just started (wasn't already running)
+                    if !was_animating && term.scrollback.is_animating_exit() {
+                        drop(term); // Release borrow before starting animation
+                        start_exit_animation(terminal.clone(), display.clone());
+                    }
+                }
+                KeyInput::Sent => {
+                    event.prevent_default();
+                    event.stop_propagation();
+                }
+                KeyInput::Ignored => {}
+            }
+        });
+
+        // Add to canvas directly for keyboard events (canvas is now focusable)
+        canvas.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref())?;
+        listeners.push("keydown", closure);
+    }
+
+    // Set up click event listener for exiting scrollback and focusing canvas
+    {
+        let terminal = terminal.clone();
+        let display = display.clone();
+        let canvas_clone = canvas.clone();
+
+        let closure = Closure::<dyn Fn(MouseEvent)>::new(move |event: MouseEvent| {
+            // Focus the canvas on click so it receives keyboard events
+            let _ = canvas_clone.focus();
+
+            let mut term = terminal.borrow_mut();
+            let was_animating = term.scrollback.is_animating_exit();
+            if term.handle_click() {
+                event.prevent_default();
+                event.stop_propagation();
+
+                // Re-render with scrollback
+                let _ = display.render(&term.screen, &term.scrollback);
+
+                // Start animation only if it just started (wasn't already running)
+                if !was_animating && term.scrollback.is_animating_exit() {
+                    drop(term); // Release borrow before starting animation
+                    start_exit_animation(terminal.clone(), display.clone());
+                }
+            }
+        });
+
+        canvas.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;
+        listeners.push("click", closure);
+    }
+
+    // Set up mousedown event listener to prevent bubbling (especially for middle-click scroll)
+    {
+        let closure = Closure::<dyn Fn(MouseEvent)>::new(move |event: MouseEvent| {
+            // Prevent default for middle mouse button (scroll wheel click)
+            // This stops the auto-scroll behavior in browsers
+            if event.button() == 1 {
+                event.prevent_default();
+                event.stop_propagation();
+            }
+        });
+
+        canvas.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref())?;
+        listeners.push("mousedown", closure);
+    }
+
+    Ok(listeners)
+}
+
+/// Coalesces render requests into a single render per animation frame.
+///
+/// Dropping the scheduler cancels any pending frame.
+pub struct FrameScheduler {
+    /// requestAnimationFrame handle while a frame is pending
+    pending: Rc<std::cell::Cell<Option<i32>>>,
+    callback: Closure<dyn FnMut()>,
+}
+
+impl FrameScheduler {
+    /// Create a scheduler that calls `render` on the next animation frame.
+    pub fn new(mut render: impl FnMut() + 'static) -> Self {
+        let pending = Rc::new(std::cell::Cell::new(None));
+        let pending_clone = pending.clone();
+        let callback = Closure::new(move || {
+            pending_clone.set(None);
+            render();
+        });
+        FrameScheduler { pending, callback }
+    }
+
+    /// Request a render on the next animation frame (no-op if already pending).
+    pub fn request(&self) {
+        if self.pending.get().is_some() {
+            return;
+        }
+        if let Some(window) = web_sys::window() {
+            if let Ok(handle) = window.request_animation_frame(self.callback.as_ref().unchecked_ref()) {
+                self.pending.set(Some(handle));
+            }
+        }
+    }
+
+    /// Cancel a pending render.
+    pub fn cancel(&self) {
+        if let Some(handle) = self.pending.take() {
+            if let Some(window) = web_sys::window() {
+                let _ = window.cancel_animation_frame(handle);
+            }
+        }
+    }
+}
+
+impl Drop for FrameScheduler {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Blink phase interval: DOS text blinks at about 1.9 Hz.
+const BLINK_INTERVAL_MS: i32 = 267;
+
+/// Interval timer that animates blinking text.
+///
+/// Each tick toggles the display's blink phase and requests a render when
+/// there is something blinking on screen (only in non-iCE mode).
+pub struct BlinkTimer {
+    handle: Option<i32>,
+    _callback: Closure<dyn FnMut()>,
+}
+
+impl BlinkTimer {
+    /// Start blinking for a terminal; the timer stops when dropped.
+    pub fn start(
+        terminal: Rc<RefCell<Terminal>>,
+        display: Rc<Display>,
+        frame: Rc<FrameScheduler>,
+    ) -> Result<Self, JsValue> {
+        let callback = Closure::<dyn FnMut()>::new(move || {
+            display.toggle_blink();
+            let term = terminal.borrow();
+            let blinking = !term.screen.ice_colors()
+                && (term.scrollback.is_active() || term.screen.has_blinking_cells());
+            if blinking {
+                frame.request();
+            }
+        });
+        let window = web_sys::window().ok_or("No window")?;
+        let handle = window.set_interval_with_callback_and_timeout_and_arguments_0(
+            callback.as_ref().unchecked_ref(),
+            BLINK_INTERVAL_MS,
+        )?;
+        Ok(BlinkTimer {
+            handle: Some(handle),
+            _callback: callback,
+        })
+    }
+
+    /// Stop the timer.
+    pub fn stop(&mut self) {
+        if let (Some(handle), Some(window)) = (self.handle.take(), web_sys::window()) {
+            window.clear_interval_with_handle(handle);
+        }
+    }
+}
+
+impl Drop for BlinkTimer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Self-referencing requestAnimationFrame callback slot.
+type AnimationFrameCallback = Rc<RefCell<Option<Closure<dyn FnMut()>>>>;
+
+/// Start the exit animation loop using requestAnimationFrame.
+///
+/// The animation loop will automatically stop if:
+/// - Animation completes (scrolled to bottom)
+/// - Animation is cancelled (user scrolled up or pressed Alt+K)
+/// - Scrollback mode is exited
+fn start_exit_animation(
+    terminal: Rc<RefCell<Terminal>>,
+    display: Rc<Display>,
+) {
+    let window = match web_sys::window() {
+        Some(w) => w,
+        None => return,
+    };
+
+    // Create the animation frame callback
+    let f: AnimationFrameCallback = Rc::new(RefCell::new(None));
+    let g = f.clone();
+
+    let terminal_clone = terminal.clone();
+    let display_clone = display.clone();
+
+    *g.borrow_mut() = Some(Closure::new(move || {
+        let mut term = terminal_clone.borrow_mut();
+
+        // Check if animation was cancelled by user input (scroll wheel, Alt+K, etc.)
+        // This happens when is_animating_exit() is false but scrollback is still active
+        if !term.scrollback.is_animating_exit() {
+            // Animation was cancelled or completed, stop the loop
+            let _ = f.borrow_mut().take();
+            return;
+        }
+
+        // Advance animation
+        let still_animating = term.scrollback.animate_exit_frame();
+
+        // Re-render
+        let _ = display_clone.render(&term.screen, &term.scrollback);
+
+        // Continue animation if needed
+        if still_animating && term.scrollback.is_animating_exit() {
+            drop(term); // Release borrow
+            if let Some(window) = web_sys::window() {
+                let _ = window.request_animation_frame(
+                    f.borrow().as_ref().unwrap().as_ref().unchecked_ref()
+                );
+            }
+        } else {
+            // Animation complete, clean up
+            let _ = f.borrow_mut().take();
+        }
+    }));
+
+    // Start the animation loop
+    let _ = window.request_animation_frame(
+        g.borrow().as_ref().unwrap().as_ref().unchecked_ref()
+    );
+}
+
+/// Toggle fullscreen mode for the canvas element.
+///
+/// When entering fullscreen:
+/// - The canvas fills the screen height (or width if aspect ratio requires)
+/// - Black background fills any remaining space
+/// - Canvas maintains its native aspect ratio (~1.37:1 for 1920x1400)
+///
+/// Uses the Fullscreen API with fallbacks for different browsers.
+fn toggle_fullscreen(canvas: &HtmlCanvasElement) {
+    let document = match web_sys::window().and_then(|w| w.document()) {
+        Some(d) => d,
+        None => return,
+    };
+
+    // Check if we're currently in fullscreen
+    let fullscreen_element = document.fullscreen_element();
+
+    if fullscreen_element.is_some() {
+        // Exit fullscreen
+        document.exit_fullscreen();
+    } else {
+        // Enter fullscreen - request on the canvas element
+        // The canvas will be centered with black background automatically
+        let _ = canvas.request_fullscreen();
+    }
+}
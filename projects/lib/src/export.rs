@@ -0,0 +1,180 @@
+//! Image export without a canvas.
+//!
+//! A screen, optionally below its scrollback history, is rasterized into an
+//! RGBA framebuffer at the font's own resolution times a scale factor (640x350
+//! for 80x25 with the EGA font at scale 1) and encoded as PNG. Blinking text
+//! is drawn visible.
+//!
+//! ```
+//! use std::rc::Rc;
+//! use webterm_dos_ansi::{export, font::Font, palette::Palette, parser::AnsiParser};
+//!
+//! let screen = AnsiParser::new().draw_document(b"\x1b[1;31mHello", 80, true);
+//! let png = export::to_png(&screen, None, Palette::Vga, Rc::new(Font::ega()), 1).unwrap();
+//! assert!(png.starts_with(b"\x89PNG"));
+//! ```
+
+use std::rc::Rc;
+
+use crate::font::Font;
+use crate::palette::{ansi_color_to_rgb_tuple, Palette};
+use crate::raster::{scaled_cell_size, Framebuffer, GlyphCache, Rgb, DEFAULT_CACHE_CAPACITY};
+use crate::screen::{Cell, Screen};
+use crate::scrollback::ScrollbackBuffer;
+
+/// Largest image that can be exported, in pixels (256 MB of RGBA).
+pub const MAX_PIXELS: usize = 64 * 1024 * 1024;
+
+/// Rasterize a screen, below the lines of `history` if given.
+///
+/// Each font pixel becomes `scale` x `scale` image pixels.
+pub fn rasterize(
+    screen: &Screen,
+    history: Option<&ScrollbackBuffer>,
+    palette: Palette,
+    font: Rc<Font>,
+    scale: usize,
+) -> Result<Framebuffer, String> {
+    let scale = scale.max(1);
+    let (columns, rows) = screen.dimensions();
+    let history_lines = history.map_or(0, ScrollbackBuffer::history_len);
+    if !fits(&font, columns, history_lines + rows, scale) {
+        return Err(format!(
+            "Image is too large ({}x{} cells at scale {})",
+            columns,
+            history_lines + rows,
+            scale
+        ));
+    }
+    let (cell_width, cell_height) = scaled_cell_size(&font, (scale, scale));
+
+    let mut framebuffer = Framebuffer::new(columns, history_lines + rows, (cell_width, cell_height));
+    let mut glyphs = GlyphCache::with_scale(font, DEFAULT_CACHE_CAPACITY, scale);
+    let mut draw_line = |y: usize, cells: &[Cell]| {
+        for (x, cell) in cells.iter().enumerate() {
+            let fg = color(cell.fg, cell.fg_rgb, palette);
+            let bg = color(cell.bg, cell.bg_rgb, palette);
+            framebuffer.draw_glyph(x, y, glyphs.glyph(cell.ch, fg, bg));
+        }
+    };
+
+    if let Some(history) = history {
+        for index in 0..history_lines {
+            if let Some(cells) = history.history_cells(index, screen.ice_colors()) {
+                draw_line(index, &cells);
+            }
+        }
+    }
+    for y in 0..rows {
+        if let Some(cells) = screen.get_line(y) {
+            draw_line(history_lines + y, &cells);
+        }
+    }
+    Ok(framebuffer)
+}
+
+/// Encode a framebuffer as an RGBA PNG.
+pub fn encode_png(framebuffer: &Framebuffer) -> Result<Vec<u8>, String> {
+    let (width, height) = framebuffer.dimensions();
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(framebuffer.pixels()).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+/// Rasterize a screen (and history) and encode it as PNG.
+pub fn to_png(
+    screen: &Screen,
+    history: Option<&ScrollbackBuffer>,
+    palette: Palette,
+    font: Rc<Font>,
+    scale: usize,
+) -> Result<Vec<u8>, String> {
+    encode_png(&rasterize(screen, history, palette, font, scale)?)
+}
+
+/// Whether an image of `columns` x `lines` cells fits in `MAX_PIXELS`
+/// (without overflowing on the way).
+fn fits(font: &Font, columns: usize, lines: usize, scale: usize) -> bool {
+    let pixels = || {
+        let width = columns.checked_mul(font.cell_width())?.checked_mul(scale)?;
+        let height = lines.checked_mul(font.height())?.checked_mul(scale)?;
+        width.checked_mul(height)
+    };
+    pixels().is_some_and(|pixels| pixels <= MAX_PIXELS)
+}
+
+/// Resolve a palette index, or the exact color of a true color cell.
+pub(crate) fn color(index: u8, rgb: Option<Rgb>, palette: Palette) -> Rgb {
+    rgb.unwrap_or_else(|| ansi_color_to_rgb_tuple(index, palette))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(framebuffer: &Framebuffer, x: usize, y: usize) -> [u8; 4] {
+        let (width, _) = framebuffer.dimensions();
+        let offset = (y * width + x) * 4;
+        framebuffer.pixels()[offset..offset + 4].try_into().unwrap()
+    }
+
+    /// 2x1 screen: a blinking light blue full block, then a space on an exact
+    /// color background.
+    fn sample_screen() -> Screen {
+        let mut screen = Screen::with_size(2, 1);
+        screen.set_ice_colors(false);
+        screen.set_cell(0, 0, Cell { ch: 0xDB, fg: 12, blink: true, ..Cell::default() });
+        screen.set_cell(1, 0, Cell { bg_rgb: Some((1, 2, 3)), ..Cell::default() });
+        screen
+    }
+
+    #[test]
+    fn test_rasterize_screen() {
+        let framebuffer = rasterize(&sample_screen(), None, Palette::Vga, Rc::new(Font::ega()), 1).unwrap();
+        assert_eq!(framebuffer.dimensions(), (16, 14));
+        assert_eq!(pixel(&framebuffer, 0, 0), [0x55, 0x55, 0xFF, 0xFF]);
+        assert_eq!(pixel(&framebuffer, 7, 13), [0x55, 0x55, 0xFF, 0xFF]);
+        assert_eq!(pixel(&framebuffer, 8, 0), [1, 2, 3, 0xFF]);
+
+        let framebuffer = rasterize(&sample_screen(), None, Palette::Cga, Rc::new(Font::ega()), 2).unwrap();
+        assert_eq!(framebuffer.dimensions(), (32, 28));
+        assert_eq!(pixel(&framebuffer, 16, 27), [1, 2, 3, 0xFF]);
+    }
+
+    #[test]
+    fn test_rasterize_with_history() {
+        let mut history = ScrollbackBuffer::with_size(10, 2, 1);
+        history.push_line(&[Cell { ch: 0xDB, fg: 9, ..Cell::default() }; 2]);
+
+        let framebuffer = rasterize(&sample_screen(), Some(&history), Palette::Vga, Rc::new(Font::ega()), 1).unwrap();
+        assert_eq!(framebuffer.dimensions(), (16, 28));
+        // History line first (light red), then the screen
+        assert_eq!(pixel(&framebuffer, 9, 0), [0xFF, 0x55, 0x55, 0xFF]);
+        assert_eq!(pixel(&framebuffer, 0, 14), [0x55, 0x55, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_too_large() {
+        let screen = Screen::document(80);
+        assert!(rasterize(&screen, None, Palette::Vga, Rc::new(Font::ega()), 100).is_err());
+        assert!(rasterize(&screen, None, Palette::Vga, Rc::new(Font::ega()), usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let png = to_png(&sample_screen(), None, Palette::Vga, Rc::new(Font::ega()), 1).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height, info.color_type), (16, 14, png::ColorType::Rgba));
+        let framebuffer = rasterize(&sample_screen(), None, Palette::Vga, Rc::new(Font::ega()), 1).unwrap();
+        assert_eq!(&pixels[..info.buffer_size()], framebuffer.pixels());
+    }
+}
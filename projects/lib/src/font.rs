@@ -1,27 +1,203 @@
-/// EGA 8x14 font handling
-///
-/// The EGA font is stored as raw binary data with 256 characters,
-/// each character is 14 bytes (one byte per scanline, 8 pixels wide).
+//! Bitmap fonts.
+//!
+//! The IBM EGA 8x14, VGA 8x16, and VGA 8x8 fonts are built in under their SAUCE
+//! names. Other fonts are loaded from raw .F08/.F14/.F16 dumps or PSF1/PSF2
+//! files and registered by name, so they can be selected at runtime (including
+//! by SAUCE font names like "Amiga Topaz 1+").
+
+use std::cell::RefCell;
+use std::rc::Rc;
 
 /// EGA 8x14 font data embedded at compile time
 static EGA_8X14_FONT: &[u8] = include_bytes!("../fonts/ega-8x14.bin");
 
-/// Font dimensions
-pub const FONT_WIDTH: usize = 8;
-pub const FONT_HEIGHT: usize = 14;
-pub const FONT_BYTES_PER_CHAR: usize = FONT_HEIGHT;
+/// VGA 8x16 and 8x8 ROM font data embedded at compile time
+static VGA_8X16_FONT: &[u8] = include_bytes!("../fonts/vga-8x16.bin");
+static VGA_8X8_FONT: &[u8] = include_bytes!("../fonts/vga-8x8.bin");
 
-/// Get the font data for a specific character
-///
-/// # Arguments
-/// * `char_code` - The CP437 character code (0-255)
-///
-/// # Returns
-/// A slice of 14 bytes representing the character's bitmap
-pub fn get_char_bitmap(char_code: u8) -> &'static [u8] {
-    let start = (char_code as usize) * FONT_BYTES_PER_CHAR;
-    let end = start + FONT_BYTES_PER_CHAR;
-    &EGA_8X14_FONT[start..end]
+/// Name of the built-in EGA font (the default).
+pub const DEFAULT_FONT_NAME: &str = "IBM EGA";
+
+/// Fonts registered at startup besides the EGA font, by SAUCE name.
+/// 80x50 (VGA50) and 80x43 (EGA43) modes both use the 8x8 font.
+const BUILTIN_FONTS: [(&str, &[u8]); 3] = [
+    ("IBM VGA", VGA_8X16_FONT),
+    ("IBM VGA50", VGA_8X8_FONT),
+    ("IBM EGA43", VGA_8X8_FONT),
+];
+
+/// Glyphs used from a font (CP437).
+pub const GLYPH_COUNT: usize = 256;
+
+/// Largest supported glyph size in pixels.
+const MAX_GLYPH_SIZE: usize = 32;
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+
+/// Line-drawing glyphs whose 8th column is repeated in 9-pixel cells.
+const LINE_GRAPHICS: std::ops::RangeInclusive<u8> = 0xC0..=0xDF;
+
+/// A bitmap font of 256 glyphs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Font {
+    name: String,
+    width: usize,
+    height: usize,
+    /// Cells are one pixel wider than glyphs (VGA 9-pixel letter spacing)
+    ninth_column: bool,
+    /// Bytes per glyph scanline ((width + 7) / 8, leftmost pixel in the high bit)
+    row_bytes: usize,
+    /// 256 glyphs of `height * row_bytes` bytes each
+    data: Vec<u8>,
+}
+
+impl Font {
+    /// The built-in EGA 8x14 font.
+    pub fn ega() -> Self {
+        Font {
+            name: DEFAULT_FONT_NAME.to_string(),
+            width: 8,
+            height: 14,
+            ninth_column: false,
+            row_bytes: 1,
+            data: EGA_8X14_FONT.to_vec(),
+        }
+    }
+
+    /// Load a font file, detecting PSF1 and PSF2 headers (anything else is
+    /// treated as a raw .F08/.F14/.F16 dump).
+    pub fn parse(name: &str, data: &[u8]) -> Result<Self, String> {
+        if data.starts_with(&PSF2_MAGIC) {
+            Self::from_psf2(name, data)
+        } else if data.starts_with(&PSF1_MAGIC) {
+            Self::from_psf1(name, data)
+        } else {
+            Self::from_raw(name, data)
+        }
+    }
+
+    /// Load a raw 8-pixel wide font: 256 glyphs, one byte per scanline, with
+    /// the height given by the file size (2048 bytes for 8x8, 4096 for 8x16).
+    pub fn from_raw(name: &str, data: &[u8]) -> Result<Self, String> {
+        if data.is_empty() || !data.len().is_multiple_of(GLYPH_COUNT) {
+            return Err(format!("Raw font size {} is not a multiple of 256", data.len()));
+        }
+        Self::new(name, 8, data.len() / GLYPH_COUNT, data)
+    }
+
+    /// Load a PSF1 font (8 pixels wide; glyphs past 256 are ignored).
+    pub fn from_psf1(name: &str, data: &[u8]) -> Result<Self, String> {
+        if data.len() < 4 || !data.starts_with(&PSF1_MAGIC) {
+            return Err("Not a PSF1 font".to_string());
+        }
+        let height = data[3] as usize;
+        Self::new(name, 8, height, &data[4..])
+    }
+
+    /// Load a PSF2 font (glyphs past 256 are ignored).
+    pub fn from_psf2(name: &str, data: &[u8]) -> Result<Self, String> {
+        if data.len() < 32 || !data.starts_with(&PSF2_MAGIC) {
+            return Err("Not a PSF2 font".to_string());
+        }
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) as usize
+        };
+        let header_size = u32_at(8);
+        let glyph_count = u32_at(16);
+        let glyph_size = u32_at(20);
+        let height = u32_at(24);
+        let width = u32_at(28);
+        if !(1..=MAX_GLYPH_SIZE).contains(&width) || !(1..=MAX_GLYPH_SIZE).contains(&height) {
+            return Err(format!("Unsupported glyph size {}x{}", width, height));
+        }
+        if glyph_size != height * width.div_ceil(8) {
+            return Err("Invalid PSF2 glyph size".to_string());
+        }
+        let glyphs = data
+            .get(header_size..)
+            .ok_or("PSF2 header is larger than the file")?;
+        // Fonts with fewer than 256 glyphs leave the rest blank
+        let available = glyph_count
+            .min(GLYPH_COUNT)
+            .checked_mul(glyph_size)
+            .ok_or("Invalid PSF2 glyph size")?;
+        let mut padded = glyphs.get(..available).ok_or("PSF2 glyph data is truncated")?.to_vec();
+        padded.resize(GLYPH_COUNT * glyph_size, 0);
+        Self::new(name, width, height, &padded)
+    }
+
+    /// Build a font from glyph data, using the first 256 glyphs.
+    fn new(name: &str, width: usize, height: usize, glyphs: &[u8]) -> Result<Self, String> {
+        if !(1..=MAX_GLYPH_SIZE).contains(&width) || !(1..=MAX_GLYPH_SIZE).contains(&height) {
+            return Err(format!("Unsupported glyph size {}x{}", width, height));
+        }
+        let row_bytes = width.div_ceil(8);
+        let size = GLYPH_COUNT * height * row_bytes;
+        let data = glyphs.get(..size).ok_or("Font data is truncated")?.to_vec();
+        Ok(Font {
+            name: name.to_string(),
+            width,
+            height,
+            ninth_column: false,
+            row_bytes,
+            data,
+        })
+    }
+
+    /// Name the font was registered under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Glyph width in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Glyph height in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The font with 9-pixel cells, as VGA text mode draws 8-pixel fonts.
+    ///
+    /// The 9th column is blank, except for line-drawing glyphs (0xC0-0xDF),
+    /// which repeat their 8th column so horizontal lines connect.
+    pub fn with_ninth_column(mut self) -> Self {
+        self.ninth_column = self.width == 8;
+        self
+    }
+
+    /// Cell width in pixels (glyph width, plus the 9th column if enabled).
+    pub fn cell_width(&self) -> usize {
+        self.width + self.ninth_column as usize
+    }
+
+    /// Bitmap of a glyph, `row_bytes` per scanline.
+    pub fn glyph(&self, ch: u8) -> &[u8] {
+        let size = self.height * self.row_bytes;
+        let start = ch as usize * size;
+        &self.data[start..start + size]
+    }
+
+    /// Whether pixel (x, y) of a glyph's cell is set (x < `cell_width()`).
+    pub fn is_set(&self, ch: u8, x: usize, y: usize) -> bool {
+        if x >= self.cell_width() || y >= self.height {
+            return false;
+        }
+        if x == self.width {
+            return LINE_GRAPHICS.contains(&ch) && self.is_set(ch, x - 1, y);
+        }
+        let row = &self.glyph(ch)[y * self.row_bytes..];
+        is_pixel_set(row[x / 8], (x % 8) as u8)
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self::ega()
+    }
 }
 
 /// Check if a specific pixel is set in a font byte
@@ -37,11 +213,76 @@ pub fn is_pixel_set(byte: u8, bit_position: u8) -> bool {
     (byte & (0x80 >> bit_position)) != 0
 }
 
+thread_local! {
+    /// Registered fonts, starting with the built-in EGA and VGA fonts.
+    static FONTS: RefCell<Vec<Rc<Font>>> = RefCell::new(
+        std::iter::once(Font::ega())
+            .chain(BUILTIN_FONTS.iter().map(|&(name, data)| {
+                Font::from_raw(name, data).expect("built-in fonts are 256 glyphs")
+            }))
+            .map(Rc::new)
+            .collect(),
+    );
+}
+
+/// Register a font under its name, replacing any font with the same name.
+pub fn register(font: Font) -> Rc<Font> {
+    let font = Rc::new(font);
+    let key = normalize_name(font.name());
+    FONTS.with(|fonts| {
+        let mut fonts = fonts.borrow_mut();
+        fonts.retain(|f| normalize_name(f.name()) != key);
+        fonts.push(font.clone());
+    });
+    font
+}
+
+/// Find a registered font by name (case-insensitive).
+///
+/// SAUCE names with a code page suffix ("IBM VGA 437") also match the font
+/// registered without it ("IBM VGA"). "IBM EGA", "IBM VGA", "IBM VGA50", and
+/// "IBM EGA43" are built in; other names such as "Amiga Topaz 1+" are found
+/// only once a font has been registered under them.
+pub fn find(name: &str) -> Option<Rc<Font>> {
+    let key = normalize_name(name);
+    let lookup = |key: &str| {
+        FONTS.with(|fonts| {
+            fonts
+                .borrow()
+                .iter()
+                .find(|f| normalize_name(f.name()) == key)
+                .cloned()
+        })
+    };
+    lookup(&key).or_else(|| {
+        let (base, code_page) = key.rsplit_once(' ')?;
+        code_page
+            .chars()
+            .all(|c| c.is_ascii_digit())
+            .then(|| lookup(base))
+            .flatten()
+    })
+}
+
+/// The default font ("IBM EGA", built in unless replaced).
+pub fn default_font() -> Rc<Font> {
+    find(DEFAULT_FONT_NAME).unwrap_or_else(|| Rc::new(Font::ega()))
+}
+
+/// Lowercase with whitespace runs collapsed to single spaces.
+fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     const FONT_CHAR_COUNT: usize = 256;
+    const FONT_BYTES_PER_CHAR: usize = 14;
 
     #[test]
     fn test_font_data_size() {
@@ -54,20 +295,23 @@ mod tests {
 
     #[test]
     fn test_get_char_bitmap() {
+        let font = Font::ega();
+
         // Test character 0 (null)
-        let char0 = get_char_bitmap(0);
+        let char0 = font.glyph(0);
         assert_eq!(char0.len(), 14);
 
         // Test character 65 ('A')
-        let char_a = get_char_bitmap(65);
+        let char_a = font.glyph(65);
         assert_eq!(char_a.len(), 14);
 
         // Test last character (255)
-        let char255 = get_char_bitmap(255);
+        let char255 = font.glyph(255);
         assert_eq!(char255.len(), 14);
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_is_pixel_set() {
         // Test with byte 0xFF (all pixels set)
         assert_eq!(is_pixel_set(0xFF, 0), true);
@@ -85,4 +329,102 @@ mod tests {
         assert_eq!(is_pixel_set(0x01, 7), true);
         assert_eq!(is_pixel_set(0x01, 6), false);
     }
+
+    #[test]
+    fn test_raw_fonts() {
+        let font = Font::parse("8x8", &[0xAA; 2048]).unwrap();
+        assert_eq!((font.width(), font.height()), (8, 8));
+        assert!(font.is_set(b'A', 0, 7));
+        assert!(!font.is_set(b'A', 1, 7));
+        assert!(!font.is_set(b'A', 0, 8));
+
+        let font = Font::parse("8x16", &[0; 4096]).unwrap();
+        assert_eq!(font.height(), 16);
+        assert!(Font::parse("bad", &[0; 1000]).is_err());
+    }
+
+    #[test]
+    fn test_psf1() {
+        let mut data = vec![0x36, 0x04, 0x01, 16];
+        data.extend(std::iter::repeat_n(0u8, 512 * 16));
+        data[4 + 0xDB * 16] = 0xFF;
+        let font = Font::parse("psf1", &data).unwrap();
+        assert_eq!((font.width(), font.height()), (8, 16));
+        assert!(font.is_set(0xDB, 7, 0));
+        assert!(Font::parse("short", &data[..1000]).is_err());
+    }
+
+    #[test]
+    fn test_psf2_wide_glyphs() {
+        // 10x12 glyphs (2 bytes per scanline), only 128 glyphs present
+        let mut data = PSF2_MAGIC.to_vec();
+        for value in [0u32, 32, 0, 128, 24, 12, 10] {
+            data.extend(value.to_le_bytes());
+        }
+        data.extend(std::iter::repeat_n(0u8, 128 * 24));
+        data[32 + b'A' as usize * 24] = 0x00;
+        data[32 + b'A' as usize * 24 + 1] = 0x40; // pixel 9 of row 0
+        let font = Font::parse("psf2", &data).unwrap();
+        assert_eq!((font.width(), font.height()), (10, 12));
+        assert!(font.is_set(b'A', 9, 0));
+        assert!(!font.is_set(b'A', 8, 0));
+        assert!(font.glyph(200).iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_psf2_oversized_header() {
+        // Sizes whose products overflow 32 bits
+        for (glyph_size, height, width) in [(0, 0x1000_0000, 0x80), (64, 0xFFFF_FFFF, 8), (0xFFFF_FFFF, 32, 8)] {
+            let mut data = PSF2_MAGIC.to_vec();
+            for value in [0u32, 32, 0, 256, glyph_size, height, width] {
+                data.extend(value.to_le_bytes());
+            }
+            assert!(Font::parse("psf2", &data).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ninth_column() {
+        let font = Font::ega();
+        assert_eq!(font.cell_width(), 8);
+        assert!(!font.is_set(0xC4, 8, 7));
+
+        let font = font.with_ninth_column();
+        assert_eq!(font.cell_width(), 9);
+        // Horizontal line (0xC4) extends into the 9th column
+        assert!(font.is_set(0xC4, 7, 7));
+        assert!(font.is_set(0xC4, 8, 7));
+        // Full block (0xDB) is in the range too
+        assert!(font.is_set(0xDB, 8, 0));
+        // Shade (0xB1) and letters leave it blank
+        assert!((0..14).all(|y| !font.is_set(0xB1, 8, y)));
+        assert!((0..14).all(|y| !font.is_set(b'M', 8, y)));
+        assert!(!font.is_set(0xC4, 9, 7));
+    }
+
+    #[test]
+    fn test_builtin_vga_fonts() {
+        let vga = find("IBM VGA").unwrap();
+        assert_eq!((vga.width(), vga.height()), (8, 16));
+        assert_eq!(vga.glyph(0xDB), &[0xFF; 16]);
+        assert_eq!(find("IBM VGA 437").unwrap().height(), 16);
+        assert_eq!(find("IBM VGA50").unwrap().height(), 8);
+        assert_eq!(find("IBM EGA43").unwrap().height(), 8);
+    }
+
+    #[test]
+    fn test_find_by_sauce_name() {
+        assert_eq!(default_font().name(), DEFAULT_FONT_NAME);
+        assert!(find("ibm  ega").is_some());
+        assert!(find("IBM EGA 437").is_some());
+        assert!(find("Amiga Topaz 1+").is_none());
+
+        register(Font::from_raw("Amiga Topaz 1+", &[0; 2048]).unwrap());
+        assert_eq!(find("amiga topaz 1+").unwrap().height(), 8);
+        assert!(find("Amiga Topaz 1").is_none());
+
+        // Re-registering replaces the font
+        register(Font::from_raw("Amiga Topaz 1+", &[0; 4096]).unwrap());
+        assert_eq!(find("Amiga Topaz 1+").unwrap().height(), 16);
+    }
 }
@@ -0,0 +1,431 @@
+//! WebGL text renderer.
+//!
+//! Draws the terminal without a 2D canvas: each frame uploads the visible
+//! cells as a small texture (character and attribute per cell, the scrollback
+//! CGA format, 4 KB at 80x25) and a fragment shader looks up glyphs in a font
+//! texture. Exact colors of true color cells go in a second texture, uploaded
+//! only when the view has any. The result is drawn into the post-processor's
+//! source texture.
+
+use std::cell::{Cell, RefCell};
+use wasm_bindgen::JsValue;
+use web_sys::{WebGlProgram, WebGlRenderingContext, WebGlTexture, WebGlUniformLocation};
+
+use crate::font::Font;
+use crate::postprocess::{create_program, setup_vertex_attributes, VERTEX_SHADER};
+use crate::palette::{ansi_color_to_rgb_tuple, Palette};
+use crate::screen::Screen;
+use crate::scrollback::ScrollbackBuffer;
+
+/// Glyphs per row in the font texture (16x16 glyphs).
+const ATLAS_GLYPHS_PER_ROW: usize = 16;
+
+/// Text fragment shader.
+///
+/// Output row 0 is the top of the screen (the quad's V coordinate is flipped
+/// for canvas uploads, so it is flipped back here).
+const TEXT_FRAGMENT_SHADER: &str = r#"
+    #ifdef GL_FRAGMENT_PRECISION_HIGH
+    precision highp float;
+    #else
+    precision mediump float;
+    #endif
+    uniform sampler2D u_cells;    // LUMINANCE_ALPHA: character, attribute
+    uniform sampler2D u_font;     // 16x16 glyph atlas
+    uniform sampler2D u_palette;  // 16x1 RGB
+    uniform sampler2D u_true_colors;  // RGBA, foreground and background texel per cell (alpha 0 = palette)
+    uniform vec2 u_grid;          // columns, rows
+    uniform vec2 u_glyph;         // cell size in font pixels
+    uniform bool u_blink_visible;
+    uniform bool u_ice_colors;
+    uniform bool u_true_color;
+    varying vec2 v_texcoord;
+
+    void main() {
+        vec2 pos = vec2(v_texcoord.x, 1.0 - v_texcoord.y) * u_grid;
+        vec2 cell = min(floor(pos), u_grid - 1.0);
+        vec4 data = texture2D(u_cells, (cell + 0.5) / u_grid);
+        float ch = floor(data.r * 255.0 + 0.5);
+        float attr = floor(data.a * 255.0 + 0.5);
+        float fg = mod(attr, 16.0);
+        float bg = floor(attr / 16.0);
+
+        // Without iCE colors, attribute bit 7 is the blink flag
+        bool hidden = false;
+        if (!u_ice_colors && bg >= 8.0) {
+            bg -= 8.0;
+            hidden = !u_blink_visible;
+        }
+
+        vec3 fg_color = texture2D(u_palette, vec2((fg + 0.5) / 16.0, 0.5)).rgb;
+        vec3 bg_color = texture2D(u_palette, vec2((bg + 0.5) / 16.0, 0.5)).rgb;
+        if (u_true_color) {
+            vec2 size = vec2(u_grid.x * 2.0, u_grid.y);
+            vec4 fg_rgb = texture2D(u_true_colors, (vec2(cell.x * 2.0, cell.y) + 0.5) / size);
+            vec4 bg_rgb = texture2D(u_true_colors, (vec2(cell.x * 2.0 + 1.0, cell.y) + 0.5) / size);
+            fg_color = fg_rgb.a > 0.5 ? fg_rgb.rgb : fg_color;
+            bg_color = bg_rgb.a > 0.5 ? bg_rgb.rgb : bg_color;
+        }
+        if (hidden) {
+            fg_color = bg_color;
+        }
+
+        vec2 glyph = vec2(mod(ch, 16.0), floor(ch / 16.0));
+        vec2 pixel = min(floor(fract(pos) * u_glyph), u_glyph - 1.0);
+        float lit = texture2D(u_font, (glyph * u_glyph + pixel + 0.5) / (u_glyph * 16.0)).r;
+        gl_FragColor = vec4(lit > 0.5 ? fg_color : bg_color, 1.0);
+    }
+"#;
+
+/// Terminal renderer that draws glyphs on the GPU.
+pub struct GlRenderer {
+    gl: WebGlRenderingContext,
+    program: WebGlProgram,
+    cell_texture: WebGlTexture,
+    font_texture: WebGlTexture,
+    palette_texture: WebGlTexture,
+    true_color_texture: WebGlTexture,
+    cells_loc: WebGlUniformLocation,
+    font_loc: WebGlUniformLocation,
+    palette_loc: WebGlUniformLocation,
+    true_colors_loc: WebGlUniformLocation,
+    grid_loc: WebGlUniformLocation,
+    glyph_loc: WebGlUniformLocation,
+    blink_visible_loc: WebGlUniformLocation,
+    ice_colors_loc: WebGlUniformLocation,
+    true_color_loc: WebGlUniformLocation,
+    /// Cell size in font pixels
+    glyph_size: (usize, usize),
+    /// Blink phase: blinking text is hidden while false
+    blink_visible: Cell<bool>,
+    /// Reused buffer for the cell texture
+    cells: RefCell<Vec<u8>>,
+    /// Reused buffer for the true color texture
+    true_colors: RefCell<Vec<u8>>,
+}
+
+impl GlRenderer {
+    /// Create a renderer on the display canvas's WebGL context.
+    pub fn new(gl: &WebGlRenderingContext, palette: Palette, font: &Font) -> Result<Self, JsValue> {
+        let program = create_program(gl, VERTEX_SHADER, TEXT_FRAGMENT_SHADER)?;
+        let uniform = |name: &str| {
+            gl.get_uniform_location(&program, name)
+                .ok_or_else(|| JsValue::from_str(&format!("Failed to get {} location for text", name)))
+        };
+        let cells_loc = uniform("u_cells")?;
+        let font_loc = uniform("u_font")?;
+        let palette_loc = uniform("u_palette")?;
+        let true_colors_loc = uniform("u_true_colors")?;
+        let grid_loc = uniform("u_grid")?;
+        let glyph_loc = uniform("u_glyph")?;
+        let blink_visible_loc = uniform("u_blink_visible")?;
+        let ice_colors_loc = uniform("u_ice_colors")?;
+        let true_color_loc = uniform("u_true_color")?;
+
+        // Cell rows are 2 bytes per cell, so rows need not be 4-byte aligned
+        gl.pixel_storei(WebGlRenderingContext::UNPACK_ALIGNMENT, 1);
+
+        let font_texture = create_data_texture(gl)?;
+        upload(
+            gl,
+            &font_texture,
+            WebGlRenderingContext::LUMINANCE,
+            ATLAS_GLYPHS_PER_ROW * font.cell_width(),
+            (256 / ATLAS_GLYPHS_PER_ROW) * font.height(),
+            &font_atlas(font),
+        )?;
+
+        // Never sampled until a view has true colors, but must be complete
+        let true_color_texture = create_data_texture(gl)?;
+        upload(gl, &true_color_texture, WebGlRenderingContext::RGBA, 1, 1, &[0; 4])?;
+
+        let renderer = GlRenderer {
+            gl: gl.clone(),
+            program,
+            cell_texture: create_data_texture(gl)?,
+            font_texture,
+            palette_texture: create_data_texture(gl)?,
+            true_color_texture,
+            cells_loc,
+            font_loc,
+            palette_loc,
+            true_colors_loc,
+            grid_loc,
+            glyph_loc,
+            blink_visible_loc,
+            ice_colors_loc,
+            true_color_loc,
+            glyph_size: (font.cell_width(), font.height()),
+            blink_visible: Cell::new(true),
+            cells: RefCell::new(Vec::new()),
+            true_colors: RefCell::new(Vec::new()),
+        };
+        renderer.set_palette(palette);
+        Ok(renderer)
+    }
+
+    /// Change the color palette (takes effect on the next render).
+    pub fn set_palette(&self, palette: Palette) {
+        let colors: Vec<u8> = (0..16)
+            .flat_map(|color| {
+                let (r, g, b) = ansi_color_to_rgb_tuple(color, palette);
+                [r, g, b]
+            })
+            .collect();
+        let _ = upload(
+            &self.gl,
+            &self.palette_texture,
+            WebGlRenderingContext::RGB,
+            16,
+            1,
+            &colors,
+        );
+    }
+
+    /// Advance the blink phase (takes effect on the next render).
+    pub fn toggle_blink(&self) {
+        self.blink_visible.set(!self.blink_visible.get());
+    }
+
+    /// Draw the current view (scrollback position or live screen) into the
+    /// bound framebuffer.
+    pub fn render(&self, screen: &Screen, scrollback: &ScrollbackBuffer) -> Result<(), JsValue> {
+        let gl = &self.gl;
+        let (columns, rows) = screen.dimensions();
+
+        let mut cells = self.cells.borrow_mut();
+        cell_grid(screen, scrollback, &mut cells);
+        upload(
+            gl,
+            &self.cell_texture,
+            WebGlRenderingContext::LUMINANCE_ALPHA,
+            columns,
+            rows,
+            &cells,
+        )?;
+
+        let mut true_colors = self.true_colors.borrow_mut();
+        let has_true_colors = true_color_grid(screen, scrollback, &mut true_colors);
+        if has_true_colors {
+            upload(
+                gl,
+                &self.true_color_texture,
+                WebGlRenderingContext::RGBA,
+                columns * 2,
+                rows,
+                &true_colors,
+            )?;
+        }
+
+        gl.use_program(Some(&self.program));
+        let textures = [
+            (&self.cell_texture, &self.cells_loc),
+            (&self.font_texture, &self.font_loc),
+            (&self.palette_texture, &self.palette_loc),
+            (&self.true_color_texture, &self.true_colors_loc),
+        ];
+        for (unit, (texture, location)) in textures.into_iter().enumerate() {
+            gl.active_texture(WebGlRenderingContext::TEXTURE0 + unit as u32);
+            gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(texture));
+            gl.uniform1i(Some(location), unit as i32);
+        }
+        gl.active_texture(WebGlRenderingContext::TEXTURE0);
+
+        gl.uniform2f(Some(&self.grid_loc), columns as f32, rows as f32);
+        gl.uniform2f(
+            Some(&self.glyph_loc),
+            self.glyph_size.0 as f32,
+            self.glyph_size.1 as f32,
+        );
+        gl.uniform1i(Some(&self.blink_visible_loc), self.blink_visible.get() as i32);
+        gl.uniform1i(Some(&self.ice_colors_loc), screen.ice_colors() as i32);
+        gl.uniform1i(Some(&self.true_color_loc), has_true_colors as i32);
+
+        setup_vertex_attributes(gl, &self.program)?;
+        gl.draw_arrays(WebGlRenderingContext::TRIANGLE_STRIP, 0, 4);
+
+        Ok(())
+    }
+}
+
+/// Fill `out` with the visible cells in CGA format (character, attribute),
+/// row by row, including the "SCROLLBACK" indicators.
+fn cell_grid(screen: &Screen, scrollback: &ScrollbackBuffer, out: &mut Vec<u8>) {
+    let (width, height) = screen.dimensions();
+    out.clear();
+    for y in 0..height {
+        match scrollback.get_display_line(y, screen) {
+            Some(line) => out.extend_from_slice(&line),
+            None => out.resize(out.len() + width * 2, 0),
+        }
+    }
+
+    // Top-left and top-right corners
+    if scrollback.should_show_indicators() {
+        let indicator = ScrollbackBuffer::scrollback_indicator();
+        let right = width.saturating_sub(10) * 2;
+        for start in [0, right] {
+            let end = (start + indicator.len()).min(width * 2);
+            out[start..end].copy_from_slice(&indicator[..end - start]);
+        }
+    }
+}
+
+/// Fill `out` with the exact colors of the visible cells, two RGBA texels per
+/// cell (foreground, background; alpha 0 where the palette color is used).
+///
+/// Returns false, leaving `out` empty, when no visible cell has true colors.
+fn true_color_grid(screen: &Screen, scrollback: &ScrollbackBuffer, out: &mut Vec<u8>) -> bool {
+    let (width, height) = screen.dimensions();
+    out.clear();
+    let lines: Vec<_> = (0..height)
+        .map(|y| scrollback.get_display_true_colors(y, screen))
+        .collect();
+    if lines.iter().all(Option::is_none) {
+        return false;
+    }
+
+    out.resize(width * height * 8, 0);
+    for (y, line) in lines.iter().enumerate() {
+        let Some(line) = line else { continue };
+        for (x, &(fg, bg)) in line.iter().take(width).enumerate() {
+            for (i, color) in [fg, bg].into_iter().enumerate() {
+                if let Some((r, g, b)) = color {
+                    let offset = ((y * width + x) * 2 + i) * 4;
+                    out[offset..offset + 4].copy_from_slice(&[r, g, b, 255]);
+                }
+            }
+        }
+    }
+
+    // The "SCROLLBACK" indicators use palette colors
+    if scrollback.should_show_indicators() {
+        let right = width.saturating_sub(10) * 8;
+        for start in [0, right] {
+            let end = (start + 10 * 8).min(width * 8);
+            out[start..end].fill(0);
+        }
+    }
+    true
+}
+
+/// All 256 glyph cells in a 16x16 grid, one byte per pixel (255 = set).
+fn font_atlas(font: &Font) -> Vec<u8> {
+    let atlas_width = ATLAS_GLYPHS_PER_ROW * font.cell_width();
+    let atlas_height = (256 / ATLAS_GLYPHS_PER_ROW) * font.height();
+    let mut atlas = vec![0u8; atlas_width * atlas_height];
+    for ch in 0..=255u8 {
+        let left = (ch as usize % ATLAS_GLYPHS_PER_ROW) * font.cell_width();
+        let top = (ch as usize / ATLAS_GLYPHS_PER_ROW) * font.height();
+        for y in 0..font.height() {
+            for x in 0..font.cell_width() {
+                if font.is_set(ch, x, y) {
+                    atlas[(top + y) * atlas_width + left + x] = 255;
+                }
+            }
+        }
+    }
+    atlas
+}
+
+/// Create a texture for lookup data (nearest filtering, no mipmaps).
+fn create_data_texture(gl: &WebGlRenderingContext) -> Result<WebGlTexture, JsValue> {
+    let texture = gl.create_texture().ok_or("Failed to create texture")?;
+
+    gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+    for (parameter, value) in [
+        (WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::CLAMP_TO_EDGE),
+        (WebGlRenderingContext::TEXTURE_WRAP_T, WebGlRenderingContext::CLAMP_TO_EDGE),
+        (WebGlRenderingContext::TEXTURE_MIN_FILTER, WebGlRenderingContext::NEAREST),
+        (WebGlRenderingContext::TEXTURE_MAG_FILTER, WebGlRenderingContext::NEAREST),
+    ] {
+        gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, parameter, value as i32);
+    }
+
+    Ok(texture)
+}
+
+/// Upload unsigned byte pixel data to a texture.
+fn upload(
+    gl: &WebGlRenderingContext,
+    texture: &WebGlTexture,
+    format: u32,
+    width: usize,
+    height: usize,
+    data: &[u8],
+) -> Result<(), JsValue> {
+    gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(texture));
+    gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+        WebGlRenderingContext::TEXTURE_2D,
+        0,
+        format as i32,
+        width as i32,
+        height as i32,
+        0,
+        format,
+        WebGlRenderingContext::UNSIGNED_BYTE,
+        Some(data),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::Cell;
+
+    #[test]
+    fn test_font_atlas_layout() {
+        let atlas = font_atlas(&Font::ega());
+        assert_eq!(atlas.len(), 128 * 224);
+        // Space is blank, full block (0xDB) is solid
+        let glyph_pixels = |ch: usize| {
+            let (left, top) = ((ch % 16) * 8, (ch / 16) * 14);
+            (0..14).flat_map(move |y| (0..8).map(move |x| (top + y) * 128 + left + x))
+        };
+        assert!(glyph_pixels(0x20).all(|i| atlas[i] == 0));
+        assert!(glyph_pixels(0xDB).all(|i| atlas[i] == 255));
+    }
+
+    #[test]
+    fn test_cell_grid_matches_cga_layout() {
+        let mut screen = Screen::with_size(40, 3);
+        screen.set_cell(1, 2, Cell { ch: b'A', fg: 14, bg: 1, blink: true, ..Cell::default() });
+        let scrollback = ScrollbackBuffer::with_size(100, 40, 3);
+
+        let mut grid = Vec::new();
+        cell_grid(&screen, &scrollback, &mut grid);
+        assert_eq!(grid.len(), 40 * 3 * 2);
+        let offset = (2 * 40 + 1) * 2;
+        assert_eq!(&grid[offset..offset + 2], &[b'A', 0x9E]);
+    }
+
+    #[test]
+    fn test_cell_grid_scrollback_indicators() {
+        let screen = Screen::with_size(40, 3);
+        let mut scrollback = ScrollbackBuffer::with_size(100, 40, 3);
+        scrollback.push_screen(&screen);
+        scrollback.enter_scrollback();
+
+        let mut grid = Vec::new();
+        cell_grid(&screen, &scrollback, &mut grid);
+        let indicator = ScrollbackBuffer::scrollback_indicator();
+        assert_eq!(&grid[..20], &indicator);
+        assert_eq!(&grid[60..80], &indicator);
+    }
+
+    #[test]
+    fn test_true_color_grid() {
+        let mut screen = Screen::with_size(4, 2);
+        let scrollback = ScrollbackBuffer::with_size(100, 4, 2);
+        let mut grid = Vec::new();
+        assert!(!true_color_grid(&screen, &scrollback, &mut grid));
+        assert!(grid.is_empty());
+
+        screen.set_cell(3, 1, Cell { bg_rgb: Some((10, 20, 30)), ..Cell::default() });
+        assert!(true_color_grid(&screen, &scrollback, &mut grid));
+        assert_eq!(grid.len(), 4 * 2 * 8);
+        let offset = (4 + 3) * 8;
+        assert_eq!(&grid[offset..offset + 8], &[0, 0, 0, 0, 10, 20, 30, 255]);
+        assert!(grid[..offset].iter().all(|&b| b == 0));
+    }
+}
@@ -0,0 +1,188 @@
+//! HTML export of screens as selectable text.
+//!
+//! A screen, optionally below its scrollback history, becomes a `<pre>` block
+//! with one line per row. CP437 characters are mapped to Unicode, and each run
+//! of cells with the same colors is wrapped in a `<span>` with inline colors
+//! from the palette (or the cells' exact colors). Blinking runs also get the
+//! `blink` class, for the page to style.
+//!
+//! ```
+//! use webterm_dos_ansi::{html, palette::Palette, parser::AnsiParser};
+//!
+//! let screen = AnsiParser::new().draw_document(b"\x1b[1;31mHello", 80, true);
+//! let html = html::to_html(&screen, None, Palette::Vga);
+//! assert!(html.contains("<span style=\"color:#ff5555\">Hello</span>"));
+//! ```
+
+use std::fmt::Write;
+
+use crate::cp437;
+use crate::export::color;
+use crate::palette::Palette;
+use crate::raster::Rgb;
+use crate::screen::{Cell, Screen};
+use crate::scrollback::ScrollbackBuffer;
+
+/// Foreground color of unstyled text.
+const DEFAULT_FG: u8 = 7;
+/// Background color of unstyled text.
+const DEFAULT_BG: u8 = 0;
+
+/// Colors shared by a run of cells.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Style {
+    fg: Rgb,
+    bg: Rgb,
+    blink: bool,
+}
+
+/// Convert a screen, below the lines of `history` if given, to HTML.
+///
+/// Blank cells on the default background at the end of a line are left out.
+pub fn to_html(screen: &Screen, history: Option<&ScrollbackBuffer>, palette: Palette) -> String {
+    let default = Style {
+        fg: color(DEFAULT_FG, None, palette),
+        bg: color(DEFAULT_BG, None, palette),
+        blink: false,
+    };
+    let mut html = format!(
+        "<pre class=\"webterm\" style=\"color:{};background-color:{}\">",
+        hex(default.fg),
+        hex(default.bg)
+    );
+
+    let mut lines = Vec::new();
+    if let Some(history) = history {
+        lines.extend((0..history.history_len()).filter_map(|index| history.history_cells(index, screen.ice_colors())));
+    }
+    let (_, rows) = screen.dimensions();
+    lines.extend((0..rows).filter_map(|y| screen.get_line(y)));
+
+    for (index, cells) in lines.iter().enumerate() {
+        if index > 0 {
+            html.push('\n');
+        }
+        write_line(&mut html, cells, palette, default);
+    }
+    html.push_str("</pre>");
+    html
+}
+
+/// Append one line of cells, grouped into runs of the same style.
+fn write_line(html: &mut String, cells: &[Cell], palette: Palette, default: Style) {
+    let end = cells.iter().rposition(|cell| !is_blank(cell)).map_or(0, |last| last + 1);
+    let mut current = default;
+    for cell in &cells[..end] {
+        let style = Style {
+            fg: color(cell.fg, cell.fg_rgb, palette),
+            bg: color(cell.bg, cell.bg_rgb, palette),
+            blink: cell.blink,
+        };
+        if style != current {
+            if current != default {
+                html.push_str("</span>");
+            }
+            if style != default {
+                open_span(html, style, default);
+            }
+            current = style;
+        }
+        push_char(html, cell.ch);
+    }
+    if current != default {
+        html.push_str("</span>");
+    }
+}
+
+/// Append a `<span>` setting the colors that differ from the defaults.
+fn open_span(html: &mut String, style: Style, default: Style) {
+    let mut css = Vec::new();
+    if style.fg != default.fg {
+        css.push(format!("color:{}", hex(style.fg)));
+    }
+    if style.bg != default.bg {
+        css.push(format!("background-color:{}", hex(style.bg)));
+    }
+    html.push_str("<span");
+    if style.blink {
+        html.push_str(" class=\"blink\"");
+    }
+    if !css.is_empty() {
+        let _ = write!(html, " style=\"{}\"", css.join(";"));
+    }
+    html.push('>');
+}
+
+/// Append a CP437 character as escaped Unicode text.
+fn push_char(html: &mut String, ch: u8) {
+    match cp437::to_unicode(ch) {
+        '\0' => html.push(' '),
+        '&' => html.push_str("&amp;"),
+        '<' => html.push_str("&lt;"),
+        '>' => html.push_str("&gt;"),
+        c => html.push(c),
+    }
+}
+
+/// Whether a cell shows nothing on the default background.
+fn is_blank(cell: &Cell) -> bool {
+    matches!(cell.ch, 0 | b' ' | 0xFF) && cell.bg == DEFAULT_BG && cell.bg_rgb.is_none()
+}
+
+/// Format a color as `#rrggbb`.
+fn hex((r, g, b): Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::AnsiParser;
+
+    fn render(ansi: &[u8]) -> String {
+        let screen = AnsiParser::new().draw_document(ansi, 80, true);
+        to_html(&screen, None, Palette::Vga)
+    }
+
+    #[test]
+    fn test_color_runs() {
+        assert_eq!(
+            render(b"plain \x1b[1;31mred\x1b[44mon blue\x1b[0m"),
+            "<pre class=\"webterm\" style=\"color:#aaaaaa;background-color:#000000\">plain \
+             <span style=\"color:#ff5555\">red</span>\
+             <span style=\"color:#ff5555;background-color:#0000aa\">on blue</span></pre>"
+        );
+    }
+
+    #[test]
+    fn test_escaping_and_cp437() {
+        let screen = AnsiParser::new().draw_document(b"<a href=\"x\">&\xDB\xB0\x9D</a>\r\n\x1b[5mhi", 80, false);
+        let html = to_html(&screen, None, Palette::Vga);
+        assert!(html.contains(">&lt;a href=\"x\"&gt;&amp;█░¥&lt;/a&gt;\n"));
+        assert!(html.contains("<span class=\"blink\">hi</span>"));
+    }
+
+    #[test]
+    fn test_exact_colors_and_palette() {
+        let html = render(b"\x1b[1;255;128;0tX");
+        assert!(html.contains("<span style=\"color:#ff8000\">X</span>"));
+
+        let screen = AnsiParser::new().draw_document(b"\x1b[34mX", 80, true);
+        let html = to_html(&screen, None, Palette::Cga);
+        assert!(html.contains("style=\"color:#c4c4c4;background-color:#000000\""));
+        assert!(html.contains("<span style=\"color:#0000c4\">X</span>"));
+    }
+
+    #[test]
+    fn test_with_history() {
+        let mut history = ScrollbackBuffer::with_size(10, 4, 1);
+        history.push_line(&[Cell { ch: b'o', ..Cell::default() }; 2]);
+        let mut screen = Screen::with_size(4, 2);
+        screen.set_cell(0, 0, Cell { ch: b'n', ..Cell::default() });
+        screen.set_cell(3, 1, Cell { bg: 1, ..Cell::default() });
+
+        let html = to_html(&screen, Some(&history), Palette::Vga);
+        let text = html.split_once('>').unwrap().1;
+        assert_eq!(text, "oo\nn\n   <span style=\"background-color:#aa0000\"> </span></pre>");
+    }
+}
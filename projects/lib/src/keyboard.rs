@@ -0,0 +1,242 @@
+//! Keyboard input encoding for BBS connections.
+//!
+//! Translates browser key names into the byte sequences a DOS ANSI-BBS
+//! host expects, and defines the output sink the bytes are delivered to.
+
+use crate::cp437;
+
+/// Destination for bytes typed by the user (WebSocket, JS callback, ...).
+pub trait OutputSink {
+    /// Send bytes to the remote host.
+    fn send(&self, bytes: &[u8]);
+}
+
+impl<F: Fn(&[u8])> OutputSink for F {
+    fn send(&self, bytes: &[u8]) {
+        self(bytes)
+    }
+}
+
+/// Deliver bytes to a JavaScript callback as a `Uint8Array`.
+#[cfg(feature = "web")]
+impl OutputSink for js_sys::Function {
+    fn send(&self, bytes: &[u8]) {
+        let array = js_sys::Uint8Array::from(bytes);
+        let _ = self.call1(&wasm_bindgen::JsValue::NULL, &array);
+    }
+}
+
+/// Deliver bytes as a binary WebSocket frame.
+#[cfg(feature = "web")]
+impl OutputSink for web_sys::WebSocket {
+    fn send(&self, bytes: &[u8]) {
+        if self.ready_state() == web_sys::WebSocket::OPEN {
+            let _ = self.send_with_u8_array(bytes);
+        }
+    }
+}
+
+/// Modifier keys held during a key press.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+/// Encode a key press as bytes for the remote host.
+///
+/// `key` is the `KeyboardEvent.key` value. Returns None for keys that
+/// produce no output (modifiers alone, unmapped characters, ...).
+pub fn encode_key(key: &str, modifiers: Modifiers) -> Option<Vec<u8>> {
+    let bytes: Vec<u8> = match key {
+        // Cursor keys
+        "ArrowUp" => b"\x1b[A".to_vec(),
+        "ArrowDown" => b"\x1b[B".to_vec(),
+        "ArrowRight" => b"\x1b[C".to_vec(),
+        "ArrowLeft" => b"\x1b[D".to_vec(),
+
+        // Editing keys (ANSI-BBS / SyncTERM conventions)
+        "Home" => b"\x1b[H".to_vec(),
+        "End" => b"\x1b[K".to_vec(),
+        "Insert" => b"\x1b[@".to_vec(),
+        "Delete" => vec![0x7F],
+        "PageUp" => b"\x1b[V".to_vec(),
+        "PageDown" => b"\x1b[U".to_vec(),
+
+        // Function keys (F1-F12)
+        _ if key.len() > 1 && key.starts_with('F') => function_key(key, modifiers.shift)?,
+
+        // Control keys
+        "Enter" => vec![b'\r'],
+        "Backspace" => vec![0x08],
+        "Tab" if modifiers.shift => b"\x1b[Z".to_vec(),
+        "Tab" => vec![0x09],
+        "Escape" => vec![0x1B],
+
+        _ => {
+            let mut chars = key.chars();
+            let ch = chars.next()?;
+            if chars.next().is_some() {
+                // Named key we don't handle (Shift, CapsLock, ...)
+                return None;
+            }
+            if modifiers.ctrl && modifiers.alt {
+                // AltGr reports Ctrl+Alt; the key is the character it typed
+                return cp437::from_unicode(ch).map(|b| vec![b]);
+            } else if modifiers.ctrl {
+                vec![control_code(ch)?]
+            } else {
+                vec![cp437::from_unicode(ch)?]
+            }
+        }
+    };
+
+    // Alt sends an ESC prefix (meta key convention)
+    if modifiers.alt {
+        let mut prefixed = Vec::with_capacity(bytes.len() + 1);
+        prefixed.push(0x1B);
+        prefixed.extend_from_slice(&bytes);
+        Some(prefixed)
+    } else {
+        Some(bytes)
+    }
+}
+
+/// Encode F1-F12. Shifted keys carry the xterm modifier parameter
+/// (Shift+F1 = ESC[1;2P, Shift+F5 = ESC[15;2~).
+fn function_key(key: &str, shift: bool) -> Option<Vec<u8>> {
+    let sequence = match key[1..].parse::<u8>().ok()? {
+        n @ 1..=4 => {
+            let last = (b'P' + n - 1) as char;
+            if shift {
+                format!("\x1b[1;2{last}")
+            } else {
+                format!("\x1bO{last}")
+            }
+        }
+        n @ 5..=12 => {
+            let code = [15, 17, 18, 19, 20, 21, 23, 24][n as usize - 5];
+            if shift {
+                format!("\x1b[{code};2~")
+            } else {
+                format!("\x1b[{code}~")
+            }
+        }
+        _ => return None,
+    };
+    Some(sequence.into_bytes())
+}
+
+/// Map Ctrl+key to its ASCII control code (Ctrl+A = 0x01, Ctrl+[ = ESC, ...).
+fn control_code(ch: char) -> Option<u8> {
+    match ch.to_ascii_uppercase() {
+        c @ '@'..='_' => Some(c as u8 & 0x1F),
+        ' ' => Some(0x00),
+        '?' => Some(0x7F),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(k: &str) -> Option<Vec<u8>> {
+        encode_key(k, Modifiers::default())
+    }
+
+    fn ctrl(k: &str) -> Option<Vec<u8>> {
+        encode_key(k, Modifiers { ctrl: true, ..Default::default() })
+    }
+
+    #[test]
+    fn test_arrow_keys() {
+        assert_eq!(key("ArrowUp"), Some(b"\x1b[A".to_vec()));
+        assert_eq!(key("ArrowDown"), Some(b"\x1b[B".to_vec()));
+        assert_eq!(key("ArrowRight"), Some(b"\x1b[C".to_vec()));
+        assert_eq!(key("ArrowLeft"), Some(b"\x1b[D".to_vec()));
+    }
+
+    #[test]
+    fn test_editing_and_function_keys() {
+        assert_eq!(key("Home"), Some(b"\x1b[H".to_vec()));
+        assert_eq!(key("End"), Some(b"\x1b[K".to_vec()));
+        assert_eq!(key("Insert"), Some(b"\x1b[@".to_vec()));
+        assert_eq!(key("Delete"), Some(vec![0x7F]));
+        assert_eq!(key("F1"), Some(b"\x1bOP".to_vec()));
+        assert_eq!(key("F12"), Some(b"\x1b[24~".to_vec()));
+    }
+
+    #[test]
+    fn test_control_keys() {
+        assert_eq!(key("Enter"), Some(vec![b'\r']));
+        assert_eq!(key("Backspace"), Some(vec![0x08]));
+        assert_eq!(key("Tab"), Some(vec![0x09]));
+        assert_eq!(key("Escape"), Some(vec![0x1B]));
+    }
+
+    #[test]
+    fn test_shifted_keys() {
+        let shift = Modifiers { shift: true, ..Default::default() };
+        assert_eq!(encode_key("Tab", shift), Some(b"\x1b[Z".to_vec()));
+        assert_eq!(encode_key("F1", shift), Some(b"\x1b[1;2P".to_vec()));
+        assert_eq!(encode_key("F4", shift), Some(b"\x1b[1;2S".to_vec()));
+        assert_eq!(encode_key("F5", shift), Some(b"\x1b[15;2~".to_vec()));
+        assert_eq!(encode_key("F12", shift), Some(b"\x1b[24;2~".to_vec()));
+        assert_eq!(key("F13"), None);
+        assert_eq!(key("Fn"), None);
+    }
+
+    #[test]
+    fn test_ctrl_letters() {
+        assert_eq!(ctrl("a"), Some(vec![0x01]));
+        assert_eq!(ctrl("C"), Some(vec![0x03]));
+        assert_eq!(ctrl("z"), Some(vec![0x1A]));
+        assert_eq!(ctrl("["), Some(vec![0x1B]));
+        assert_eq!(ctrl(" "), Some(vec![0x00]));
+        assert_eq!(ctrl("1"), None);
+    }
+
+    #[test]
+    fn test_typed_characters() {
+        assert_eq!(key("a"), Some(vec![b'a']));
+        assert_eq!(key("é"), Some(vec![0x82]));
+        assert_eq!(key("░"), Some(vec![0xB0]));
+        assert_eq!(key("€"), None);
+    }
+
+    #[test]
+    fn test_ignored_keys() {
+        assert_eq!(key("Shift"), None);
+        assert_eq!(key("CapsLock"), None);
+        assert_eq!(key(""), None);
+    }
+
+    #[test]
+    fn test_alt_prefix() {
+        let alt = Modifiers { alt: true, ..Default::default() };
+        assert_eq!(encode_key("x", alt), Some(vec![0x1B, b'x']));
+    }
+
+    #[test]
+    fn test_altgr_characters() {
+        let altgr = Modifiers { ctrl: true, alt: true, ..Default::default() };
+        assert_eq!(encode_key("@", altgr), Some(vec![b'@']));
+        assert_eq!(encode_key("{", altgr), Some(vec![b'{']));
+        assert_eq!(encode_key("[", altgr), Some(vec![b'[']));
+        assert_eq!(encode_key("\\", altgr), Some(vec![b'\\']));
+        assert_eq!(encode_key("€", altgr), None);
+        // Named keys keep the Alt prefix
+        assert_eq!(encode_key("ArrowUp", altgr), Some(b"\x1b\x1b[A".to_vec()));
+    }
+
+    #[test]
+    fn test_closure_sink() {
+        use std::cell::RefCell;
+        let sent = RefCell::new(Vec::new());
+        let sink = |bytes: &[u8]| sent.borrow_mut().extend_from_slice(bytes);
+        sink.send(b"hi");
+        assert_eq!(*sent.borrow(), b"hi".to_vec());
+    }
+}
@@ -0,0 +1,323 @@
+//! Color palettes for the 16 text mode colors.
+//!
+//! Colors are indexed in ANSI order (black, red, green, yellow/brown, blue,
+//! magenta, cyan, light gray, then the bright versions). Besides the built-in
+//! presets, a palette can be given as a list of 16 hex colors.
+//!
+//! Extended SGR colors (256-color and 24-bit) are mapped to the nearest
+//! palette entry.
+
+use crate::raster::Rgb;
+
+/// Number of colors in a palette.
+pub const PALETTE_SIZE: usize = 16;
+
+/// Color palette type
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Palette {
+    /// IBM 5153-accurate CGA colors
+    Cga,
+    /// Standard EGA/VGA colors (default)
+    #[default]
+    Vga,
+    /// Amiga ANSI colors (12-bit, yellow instead of brown)
+    Amiga,
+    /// Commodore 64 colors
+    C64,
+    /// Solarized terminal colors
+    Solarized,
+    /// User-defined colors
+    Custom([Rgb; PALETTE_SIZE]),
+}
+
+impl Palette {
+    /// Palette from 16 VGA DAC entries (6-bit RGB triples) in DOS attribute
+    /// order, as stored in XBin files.
+    pub fn from_dac(dac: &[u8; PALETTE_SIZE * 3]) -> Self {
+        let scale = |value: u8| {
+            let value = value & 0x3F;
+            (value << 2) | (value >> 4)
+        };
+        let mut colors = [(0, 0, 0); PALETTE_SIZE];
+        for (color, rgb) in colors.iter_mut().enumerate() {
+            let entry = dos_color(color as u8) as usize * 3;
+            *rgb = (scale(dac[entry]), scale(dac[entry + 1]), scale(dac[entry + 2]));
+        }
+        Palette::Custom(colors)
+    }
+
+    /// Parse a preset name (case-insensitive) or a list of 16 hex colors
+    /// (`"#000000,#aa0000,..."`, separated by commas or whitespace).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "cga" => Some(Palette::Cga),
+            "vga" | "ega" => Some(Palette::Vga),
+            "amiga" => Some(Palette::Amiga),
+            "c64" => Some(Palette::C64),
+            "solarized" => Some(Palette::Solarized),
+            list => parse_hex_list(list).map(Palette::Custom),
+        }
+    }
+}
+
+/// Convert a color between DOS attribute order (blue = 1, red = 4) and ANSI
+/// order (red = 1, blue = 4). The conversion is its own inverse.
+pub fn dos_color(color: u8) -> u8 {
+    (color & 0x0A) | ((color & 0x01) << 2) | ((color >> 2) & 0x01)
+}
+
+/// Parse exactly 16 hex colors (`#RRGGBB` or `#RGB`, `#` optional).
+fn parse_hex_list(s: &str) -> Option<[Rgb; PALETTE_SIZE]> {
+    let mut colors = [(0, 0, 0); PALETTE_SIZE];
+    let mut entries = s.split(|c: char| c == ',' || c.is_whitespace()).filter(|e| !e.is_empty());
+    for color in &mut colors {
+        *color = parse_hex_color(entries.next()?)?;
+    }
+    match entries.next() {
+        Some(_) => None,
+        None => Some(colors),
+    }
+}
+
+/// Parse one `#RRGGBB` or `#RGB` color.
+fn parse_hex_color(s: &str) -> Option<Rgb> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if !hex.is_ascii() {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        6 => Some((channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?)),
+        3 => Some((channel(&hex[0..1])? * 0x11, channel(&hex[1..2])? * 0x11, channel(&hex[2..3])? * 0x11)),
+        _ => None,
+    }
+}
+
+/// Convert ANSI color code (0-15) to RGB tuple (r, g, b).
+pub fn ansi_color_to_rgb_tuple(color: u8, palette: Palette) -> Rgb {
+    let table = match palette {
+        Palette::Cga => return ansi_color_cga(color),
+        Palette::Vga => return ansi_color_vga(color),
+        Palette::Amiga => &AMIGA_COLORS,
+        Palette::C64 => &C64_COLORS,
+        Palette::Solarized => &SOLARIZED_COLORS,
+        Palette::Custom(ref colors) => colors,
+    };
+    // Default to light gray
+    table.get(color as usize).copied().unwrap_or(table[7])
+}
+
+/// The palette entry closest to an RGB color (squared RGB distance).
+pub fn nearest_color(rgb: Rgb, palette: Palette) -> u8 {
+    let distance = |(r, g, b): Rgb| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, rgb.0) + d(g, rgb.1) + d(b, rgb.2)
+    };
+    (0..PALETTE_SIZE as u8)
+        .min_by_key(|&color| distance(ansi_color_to_rgb_tuple(color, palette)))
+        .unwrap_or(7)
+}
+
+/// RGB value of an xterm 256-color index (16-231 color cube, 232-255 grays).
+///
+/// Indices below 16 are the standard VGA colors.
+pub fn xterm_color(index: u8) -> Rgb {
+    const CUBE_LEVELS: [u8; 6] = [0x00, 0x5F, 0x87, 0xAF, 0xD7, 0xFF];
+    match index {
+        0..=15 => ansi_color_vga(index),
+        16..=231 => {
+            let i = (index - 16) as usize;
+            (CUBE_LEVELS[i / 36], CUBE_LEVELS[(i / 6) % 6], CUBE_LEVELS[i % 6])
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+/// IBM 5153-accurate CGA colors for authentic CRT appearance.
+fn ansi_color_cga(color: u8) -> (u8, u8, u8) {
+    match color {
+        0 => (0x00, 0x00, 0x00),  // Black
+        1 => (0xC4, 0x00, 0x00),  // Red
+        2 => (0x00, 0xC4, 0x00),  // Green
+        3 => (0xC4, 0x7E, 0x00),  // Brown
+        4 => (0x00, 0x00, 0xC4),  // Blue
+        5 => (0xC4, 0x00, 0xC4),  // Magenta
+        6 => (0x00, 0xC4, 0xC4),  // Cyan
+        7 => (0xC4, 0xC4, 0xC4),  // Light Gray
+        8 => (0x4E, 0x4E, 0x4E),  // Dark Gray
+        9 => (0xDC, 0x4E, 0x4E),  // Light Red
+        10 => (0x4E, 0xDC, 0x4E), // Light Green
+        11 => (0xF3, 0xF3, 0x4E), // Yellow
+        12 => (0x4E, 0x4E, 0xDC), // Light Blue
+        13 => (0xF3, 0x4E, 0xF3), // Light Magenta
+        14 => (0x4E, 0xF3, 0xF3), // Light Cyan
+        15 => (0xFF, 0xFF, 0xFF), // White
+        _ => (0xC4, 0xC4, 0xC4),  // Default to light gray
+    }
+}
+
+/// Standard VGA colors.
+fn ansi_color_vga(color: u8) -> (u8, u8, u8) {
+    match color {
+        0 => (0x00, 0x00, 0x00),  // Black
+        1 => (0xAA, 0x00, 0x00),  // Red
+        2 => (0x00, 0xAA, 0x00),  // Green
+        3 => (0xAA, 0x55, 0x00),  // Brown/Yellow
+        4 => (0x00, 0x00, 0xAA),  // Blue
+        5 => (0xAA, 0x00, 0xAA),  // Magenta
+        6 => (0x00, 0xAA, 0xAA),  // Cyan
+        7 => (0xAA, 0xAA, 0xAA),  // Light Gray
+        8 => (0x55, 0x55, 0x55),  // Dark Gray
+        9 => (0xFF, 0x55, 0x55),  // Light Red
+        10 => (0x55, 0xFF, 0x55), // Light Green
+        11 => (0xFF, 0xFF, 0x55), // Yellow
+        12 => (0x55, 0x55, 0xFF), // Light Blue
+        13 => (0xFF, 0x55, 0xFF), // Light Magenta
+        14 => (0x55, 0xFF, 0xFF), // Light Cyan
+        15 => (0xFF, 0xFF, 0xFF), // White
+        _ => (0xAA, 0xAA, 0xAA),  // Default to light gray
+    }
+}
+
+/// Amiga ANSI colors: 12-bit values, with a true yellow in place of brown.
+const AMIGA_COLORS: [Rgb; PALETTE_SIZE] = [
+    (0x00, 0x00, 0x00), // Black
+    (0xBB, 0x00, 0x00), // Red
+    (0x00, 0xBB, 0x00), // Green
+    (0xBB, 0xBB, 0x00), // Yellow
+    (0x00, 0x00, 0xBB), // Blue
+    (0xBB, 0x00, 0xBB), // Magenta
+    (0x00, 0xBB, 0xBB), // Cyan
+    (0xBB, 0xBB, 0xBB), // Light Gray
+    (0x55, 0x55, 0x55), // Dark Gray
+    (0xFF, 0x55, 0x55), // Light Red
+    (0x55, 0xFF, 0x55), // Light Green
+    (0xFF, 0xFF, 0x55), // Yellow
+    (0x55, 0x55, 0xFF), // Light Blue
+    (0xFF, 0x55, 0xFF), // Light Magenta
+    (0x55, 0xFF, 0xFF), // Light Cyan
+    (0xFF, 0xFF, 0xFF), // White
+];
+
+/// Commodore 64 colors (Pepto's measurements). The C64 has no light magenta
+/// or light cyan, so those are lightened purple and cyan.
+const C64_COLORS: [Rgb; PALETTE_SIZE] = [
+    (0x00, 0x00, 0x00), // Black
+    (0x68, 0x37, 0x2B), // Red
+    (0x58, 0x8D, 0x43), // Green
+    (0x6F, 0x4F, 0x25), // Orange
+    (0x35, 0x28, 0x79), // Blue
+    (0x6F, 0x3D, 0x86), // Purple
+    (0x70, 0xA4, 0xB2), // Cyan
+    (0x95, 0x95, 0x95), // Light Gray
+    (0x44, 0x44, 0x44), // Dark Gray
+    (0x9A, 0x67, 0x59), // Light Red
+    (0x9A, 0xD2, 0x84), // Light Green
+    (0xB8, 0xC7, 0x6F), // Yellow
+    (0x6C, 0x5E, 0xB5), // Light Blue
+    (0xA0, 0x6E, 0xB8), // Light Purple
+    (0xA0, 0xD0, 0xDC), // Light Cyan
+    (0xFF, 0xFF, 0xFF), // White
+];
+
+/// Solarized colors in the usual terminal arrangement.
+const SOLARIZED_COLORS: [Rgb; PALETTE_SIZE] = [
+    (0x07, 0x36, 0x42), // base02
+    (0xDC, 0x32, 0x2F), // red
+    (0x85, 0x99, 0x00), // green
+    (0xB5, 0x89, 0x00), // yellow
+    (0x26, 0x8B, 0xD2), // blue
+    (0xD3, 0x36, 0x82), // magenta
+    (0x2A, 0xA1, 0x98), // cyan
+    (0xEE, 0xE8, 0xD5), // base2
+    (0x00, 0x2B, 0x36), // base03
+    (0xCB, 0x4B, 0x16), // orange
+    (0x58, 0x6E, 0x75), // base01
+    (0x65, 0x7B, 0x83), // base00
+    (0x83, 0x94, 0x96), // base0
+    (0x6C, 0x71, 0xC4), // violet
+    (0x93, 0xA1, 0xA1), // base1
+    (0xFD, 0xF6, 0xE3), // base3
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_presets() {
+        assert_eq!(Palette::parse("CGA"), Some(Palette::Cga));
+        assert_eq!(Palette::parse(" vga "), Some(Palette::Vga));
+        assert_eq!(Palette::parse("Amiga"), Some(Palette::Amiga));
+        assert_eq!(Palette::parse("c64"), Some(Palette::C64));
+        assert_eq!(Palette::parse("Solarized"), Some(Palette::Solarized));
+        assert_eq!(Palette::parse("teletext"), None);
+    }
+
+    #[test]
+    fn test_parse_hex_list() {
+        let list = "#000000, #aa0000 #00AA00,#a50 000080,#A0A,#0AA,#AAA,\
+                    #555,#F55,#5F5,#FF5,#55F,#F5F,#5FF,#FFFFFF";
+        let Some(Palette::Custom(colors)) = Palette::parse(list) else {
+            panic!("hex list not parsed");
+        };
+        assert_eq!(colors[1], (0xAA, 0x00, 0x00));
+        assert_eq!(colors[3], (0xAA, 0x55, 0x00));
+        assert_eq!(colors[4], (0x00, 0x00, 0x80));
+        assert_eq!(colors[15], (0xFF, 0xFF, 0xFF));
+
+        // Exactly 16 valid colors are required
+        assert_eq!(Palette::parse("#000,#fff"), None);
+        assert_eq!(Palette::parse(&format!("{},#000", list)), None);
+        assert_eq!(Palette::parse(&list.replace("#AAA", "#AXA")), None);
+    }
+
+    #[test]
+    fn test_custom_palette_colors() {
+        let mut colors = [(0, 0, 0); PALETTE_SIZE];
+        colors[9] = (1, 2, 3);
+        assert_eq!(ansi_color_to_rgb_tuple(9, Palette::Custom(colors)), (1, 2, 3));
+        assert_eq!(ansi_color_to_rgb_tuple(3, Palette::Amiga), (0xBB, 0xBB, 0x00));
+    }
+
+    #[test]
+    fn test_dos_color_order() {
+        let ansi: Vec<u8> = (0..16).map(dos_color).collect();
+        assert_eq!(ansi, [0, 4, 2, 6, 1, 5, 3, 7, 8, 12, 10, 14, 9, 13, 11, 15]);
+        assert!((0..16).all(|color| dos_color(dos_color(color)) == color));
+    }
+
+    #[test]
+    fn test_from_dac() {
+        let mut dac = [0u8; 48];
+        dac[3..6].copy_from_slice(&[0, 0, 63]); // DOS color 1: blue
+        dac[18..21].copy_from_slice(&[42, 21, 0]); // DOS color 6: brown
+        let Palette::Custom(colors) = Palette::from_dac(&dac) else {
+            panic!("expected a custom palette");
+        };
+        assert_eq!(colors[4], (0x00, 0x00, 0xFF));
+        assert_eq!(colors[3], (0xAA, 0x55, 0x00));
+        assert_eq!(colors[1], (0x00, 0x00, 0x00));
+    }
+
+    #[test]
+    fn test_nearest_color() {
+        assert_eq!(nearest_color((0, 0, 0), Palette::Vga), 0);
+        assert_eq!(nearest_color((0xFF, 0xFF, 0xFF), Palette::Vga), 15);
+        assert_eq!(nearest_color((0xC0, 0x10, 0x10), Palette::Vga), 1);
+        assert_eq!(nearest_color((0xD0, 0x50, 0x10), Palette::Solarized), 9);
+    }
+
+    #[test]
+    fn test_xterm_color() {
+        assert_eq!(xterm_color(1), (0xAA, 0x00, 0x00));
+        assert_eq!(xterm_color(16), (0x00, 0x00, 0x00));
+        assert_eq!(xterm_color(196), (0xFF, 0x00, 0x00));
+        assert_eq!(xterm_color(231), (0xFF, 0xFF, 0xFF));
+        assert_eq!(xterm_color(232), (0x08, 0x08, 0x08));
+        assert_eq!(xterm_color(255), (0xEE, 0xEE, 0xEE));
+    }
+}
@@ -1,7 +1,10 @@
-/// ANSI escape sequence parser for VT-100/VT-102 sequences.
-///
-/// Parses ANSI escape sequences commonly used by DOS-era BBS systems.
+//! ANSI escape sequence parser for VT-100/VT-102 sequences.
+//!
+//! Parses ANSI escape sequences commonly used by DOS-era BBS systems.
 
+use crate::palette::{nearest_color, xterm_color, Palette};
+use crate::raster::Rgb;
+use crate::sauce::EOF_MARKER;
 use crate::screen::{Cell, Screen};
 
 /// Actions that may occur during parsing that callers need to know about.
@@ -15,16 +18,58 @@ pub enum ParseAction {
     LineScrolled,
 }
 
+/// Cursor position and SGR state captured by ESC[s / ESC 7.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SavedCursor {
+    x: usize,
+    y: usize,
+    fg: u8,
+    bg: u8,
+    fg_rgb: Option<Rgb>,
+    bg_rgb: Option<Rgb>,
+    bold: bool,
+    blink: bool,
+    reverse: bool,
+}
+
+impl Default for SavedCursor {
+    fn default() -> Self {
+        SavedCursor {
+            x: 0,
+            y: 0,
+            fg: 7,
+            bg: 0,
+            fg_rgb: None,
+            bg_rgb: None,
+            bold: false,
+            blink: false,
+            reverse: false,
+        }
+    }
+}
+
 /// ANSI parser state machine.
 pub struct AnsiParser {
     state: ParserState,
     params: Vec<u32>,
     current_param: String,
+    /// CSI sequence has a '?' private parameter marker (ESC[?...)
+    private: bool,
     current_fg: u8,
     current_bg: u8,
+    /// Exact colors from SGR 38/48 (true color mode only)
+    current_fg_rgb: Option<Rgb>,
+    current_bg_rgb: Option<Rgb>,
     bold: bool,
     blink: bool,
     reverse: bool,
+    saved_cursor: Option<SavedCursor>,
+    /// Stop processing at ^Z (end of file content, SAUCE follows)
+    stop_at_eof: bool,
+    /// Palette that SGR 38/48 colors are mapped into
+    palette: Palette,
+    /// Keep the exact SGR 38/48 colors in cells as well
+    true_color: bool,
 }
 
 #[derive(Debug, PartialEq)]
@@ -32,6 +77,13 @@ enum ParserState {
     Normal,
     Escape,
     Csi,  // Control Sequence Introducer (ESC[)
+    Eof,  // ^Z seen with stop_at_eof - all further input is ignored
+}
+
+impl Default for AnsiParser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AnsiParser {
@@ -40,12 +92,63 @@ impl AnsiParser {
             state: ParserState::Normal,
             params: Vec::new(),
             current_param: String::new(),
+            private: false,
             current_fg: 7,  // White
             current_bg: 0,  // Black
+            current_fg_rgb: None,
+            current_bg_rgb: None,
             bold: false,
             blink: false,
             reverse: false,
+            saved_cursor: None,
+            stop_at_eof: false,
+            palette: Palette::default(),
+            true_color: false,
+        }
+    }
+
+    /// Reset parser state (colors, saved cursor) but keep the color settings.
+    pub fn reset(&mut self) {
+        *self = AnsiParser {
+            palette: self.palette,
+            true_color: self.true_color,
+            ..AnsiParser::new()
+        };
+    }
+
+    /// Set the palette that extended (256-color and 24-bit) colors are
+    /// mapped into.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    /// The palette extended colors are mapped into.
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    /// Keep the exact extended colors in cells ("modern" mode) instead of
+    /// only the nearest palette entry.
+    pub fn set_true_color(&mut self, enabled: bool) {
+        self.true_color = enabled;
+    }
+
+    /// Stop processing at the first ^Z (0x1A), as when displaying a file.
+    ///
+    /// Everything after the marker (SAUCE metadata) is ignored.
+    pub fn set_stop_at_eof(&mut self, stop: bool) {
+        self.stop_at_eof = stop;
+    }
+
+    /// Draw a whole file into a document screen `columns` wide that grows to
+    /// the height of the art instead of scrolling.
+    pub fn draw_document(&mut self, content: &[u8], columns: usize, ice_colors: bool) -> Screen {
+        let mut screen = Screen::document(columns);
+        screen.set_ice_colors(ice_colors);
+        for &byte in content {
+            self.process_byte(byte, &mut screen);
         }
+        screen
     }
 
     /// Check if the parser is in normal state (not processing an escape sequence).
@@ -60,7 +163,7 @@ impl AnsiParser {
     /// This allows callers to capture the screen before it's cleared.
     pub fn will_clear_screen(&self, byte: u8) -> bool {
         // We're looking for ESC[2J - byte 'J' when in CSI state with param '2'
-        if self.state != ParserState::Csi {
+        if self.state != ParserState::Csi || self.private {
             return false;
         }
         if byte != b'J' {
@@ -85,6 +188,9 @@ impl AnsiParser {
                 if byte == 0x1B {  // ESC
                     self.state = ParserState::Escape;
                     ParseAction::None
+                } else if byte == EOF_MARKER && self.stop_at_eof {
+                    self.state = ParserState::Eof;
+                    ParseAction::None
                 } else if byte == b'\n' {
                     self.handle_newline(screen)
                 } else if byte == b'\r' {
@@ -101,8 +207,13 @@ impl AnsiParser {
                     self.state = ParserState::Csi;
                     self.params.clear();
                     self.current_param.clear();
+                    self.private = false;
                 } else {
-                    // Unknown escape sequence, return to normal
+                    match byte {
+                        b'7' => self.save_cursor(screen),     // DECSC
+                        b'8' => self.restore_cursor(screen),  // DECRC
+                        _ => {}                               // Unknown escape sequence
+                    }
                     self.state = ParserState::Normal;
                 }
                 ParseAction::None
@@ -114,14 +225,23 @@ impl AnsiParser {
                 } else if byte == b';' {
                     self.push_param();
                     ParseAction::None
+                } else if byte == b'?' && self.params.is_empty() && self.current_param.is_empty() {
+                    self.private = true;
+                    ParseAction::None
                 } else {
                     // Command byte
                     self.push_param();
-                    let action = self.handle_csi_command(byte, screen);
+                    let action = if self.private {
+                        self.handle_private_mode(byte, screen);
+                        ParseAction::None
+                    } else {
+                        self.handle_csi_command(byte, screen)
+                    };
                     self.state = ParserState::Normal;
                     action
                 }
             }
+            ParserState::Eof => ParseAction::None,
         }
     }
 
@@ -144,53 +264,95 @@ impl AnsiParser {
             b'J' => self.handle_erase_display(screen),           // Erase display
             b'K' => { self.handle_erase_line(screen); ParseAction::None }
             b'm' => { self.handle_sgr(); ParseAction::None }
+            b's' => { self.save_cursor(screen); ParseAction::None }
+            b'u' => { self.restore_cursor(screen); ParseAction::None }
+            b't' => { self.handle_rgb_color(); ParseAction::None }
             _ => ParseAction::None  // Unknown command
         }
     }
 
+    /// Handle DEC private mode set/reset (ESC[?...h / ESC[?...l).
+    fn handle_private_mode(&self, cmd: u8, screen: &mut Screen) {
+        let enable = match cmd {
+            b'h' => true,
+            b'l' => false,
+            _ => return,
+        };
+        for &param in &self.params {
+            // 33: blink attribute selects bright background (iCE colors)
+            if param == 33 {
+                screen.set_ice_colors(enable);
+            }
+        }
+    }
+
     fn handle_cursor_position(&self, screen: &mut Screen) {
-        let row = self.params.get(0).copied().unwrap_or(1).saturating_sub(1) as usize;
+        let row = self.params.first().copied().unwrap_or(1).saturating_sub(1) as usize;
         let col = self.params.get(1).copied().unwrap_or(1).saturating_sub(1) as usize;
         screen.set_cursor(col, row);
     }
 
     fn handle_cursor_up(&self, screen: &mut Screen) {
-        let n = self.params.get(0).copied().unwrap_or(1) as usize;
+        let n = self.params.first().copied().unwrap_or(1) as usize;
         let (x, y) = screen.cursor_pos();
         screen.set_cursor(x, y.saturating_sub(n));
     }
 
     fn handle_cursor_down(&self, screen: &mut Screen) {
-        let n = self.params.get(0).copied().unwrap_or(1) as usize;
+        let n = self.params.first().copied().unwrap_or(1) as usize;
         let (x, y) = screen.cursor_pos();
         screen.set_cursor(x, y + n);
     }
 
     fn handle_cursor_forward(&self, screen: &mut Screen) {
-        let n = self.params.get(0).copied().unwrap_or(1) as usize;
+        let n = self.params.first().copied().unwrap_or(1) as usize;
         let (x, y) = screen.cursor_pos();
         screen.set_cursor(x + n, y);
     }
 
     fn handle_cursor_backward(&self, screen: &mut Screen) {
-        let n = self.params.get(0).copied().unwrap_or(1) as usize;
+        let n = self.params.first().copied().unwrap_or(1) as usize;
         let (x, y) = screen.cursor_pos();
         screen.set_cursor(x.saturating_sub(n), y);
     }
 
     fn handle_erase_display(&self, screen: &mut Screen) -> ParseAction {
-        let mode = self.params.get(0).copied().unwrap_or(0);
+        let mode = self.params.first().copied().unwrap_or(0);
+        let (width, height) = screen.dimensions();
+        let cursor = screen.cursor_pos();
         match mode {
+            0 => {
+                // Erase from cursor to end of screen
+                let (bg, bg_rgb) = self.effective_bg(screen);
+                screen.erase_range(cursor, (width - 1, height - 1), bg, bg_rgb);
+                ParseAction::None
+            }
+            1 => {
+                // Erase from start of screen to cursor (inclusive)
+                let (bg, bg_rgb) = self.effective_bg(screen);
+                screen.erase_range((0, 0), cursor, bg, bg_rgb);
+                ParseAction::None
+            }
             2 => {
-                screen.clear_with_bg(self.effective_bg());  // Clear entire screen with current bg
+                let (bg, bg_rgb) = self.effective_bg(screen);
+                screen.clear_with_bg(bg, bg_rgb);  // Clear entire screen with current bg
                 ParseAction::ScreenCleared
             }
-            _ => ParseAction::None  // TODO: Implement other erase modes
+            _ => ParseAction::None
         }
     }
 
-    fn handle_erase_line(&self, _screen: &mut Screen) {
-        // TODO: Implement line erasing
+    fn handle_erase_line(&self, screen: &mut Screen) {
+        let mode = self.params.first().copied().unwrap_or(0);
+        let (width, _) = screen.dimensions();
+        let (x, y) = screen.cursor_pos();
+        let (bg, bg_rgb) = self.effective_bg(screen);
+        match mode {
+            0 => screen.erase_range((x, y), (width - 1, y), bg, bg_rgb),  // Cursor to end of line
+            1 => screen.erase_range((0, y), (x, y), bg, bg_rgb),          // Start of line to cursor
+            2 => screen.erase_range((0, y), (width - 1, y), bg, bg_rgb),  // Entire line
+            _ => {}
+        }
     }
 
     fn handle_sgr(&mut self) {
@@ -198,18 +360,23 @@ impl AnsiParser {
             // Reset all attributes
             self.current_fg = 7;
             self.current_bg = 0;
+            self.current_fg_rgb = None;
+            self.current_bg_rgb = None;
             self.bold = false;
             self.blink = false;
             self.reverse = false;
             return;
         }
 
-        for &param in &self.params {
+        let mut params = self.params.iter().copied();
+        while let Some(param) = params.next() {
             match param {
                 0 => {
                     // Reset
                     self.current_fg = 7;
                     self.current_bg = 0;
+                    self.current_fg_rgb = None;
+                    self.current_bg_rgb = None;
                     self.bold = false;
                     self.blink = false;
                     self.reverse = false;
@@ -217,46 +384,126 @@ impl AnsiParser {
                 1 => self.bold = true,
                 5 => self.blink = true,
                 7 => self.reverse = true,
-                30..=37 => self.current_fg = (param - 30) as u8,  // Foreground colors
-                40..=47 => self.current_bg = (param - 40) as u8,  // Background colors
-                90..=97 => self.current_fg = (param - 90 + 8) as u8,  // Bright foreground
-                100..=107 => self.current_bg = (param - 100 + 8) as u8,  // Bright background
+                30..=37 => (self.current_fg, self.current_fg_rgb) = ((param - 30) as u8, None),  // Foreground colors
+                40..=47 => (self.current_bg, self.current_bg_rgb) = ((param - 40) as u8, None),  // Background colors
+                90..=97 => (self.current_fg, self.current_fg_rgb) = ((param - 90 + 8) as u8, None),  // Bright foreground
+                100..=107 => (self.current_bg, self.current_bg_rgb) = ((param - 100 + 8) as u8, None),  // Bright background
+                38 => {
+                    // Extended foreground (5;n or 2;r;g;b)
+                    if let Some(color) = extended_color(&mut params, self.palette, self.true_color) {
+                        (self.current_fg, self.current_fg_rgb) = color;
+                    }
+                }
+                48 => {
+                    // Extended background (5;n or 2;r;g;b)
+                    if let Some(color) = extended_color(&mut params, self.palette, self.true_color) {
+                        (self.current_bg, self.current_bg_rgb) = color;
+                    }
+                }
                 _ => {}
             }
         }
     }
 
-    /// Get the effective foreground color (applying bold and reverse)
-    fn effective_fg(&self) -> u8 {
-        let fg = if self.reverse { self.current_bg } else { self.current_fg };
+    /// Handle the PabloDraw/SyncTERM 24-bit color extension: ESC[0;R;G;Bt sets
+    /// the background, ESC[1;R;G;Bt the foreground.
+    ///
+    /// Art relies on these colors, so the exact color is kept in every mode;
+    /// the palette index (nearest entry) is what scrollback attributes use.
+    fn handle_rgb_color(&mut self) {
+        let &[target, r, g, b] = self.params.as_slice() else {
+            return;
+        };
+        let rgb = (r.min(255) as u8, g.min(255) as u8, b.min(255) as u8);
+        let color = (nearest_color(rgb, self.palette), Some(rgb));
+        match target {
+            0 => (self.current_bg, self.current_bg_rgb) = color,
+            1 => (self.current_fg, self.current_fg_rgb) = color,
+            _ => {}
+        }
+    }
+
+    /// Save cursor position and SGR attributes (ESC[s / ESC 7).
+    fn save_cursor(&mut self, screen: &Screen) {
+        let (x, y) = screen.cursor_pos();
+        self.saved_cursor = Some(SavedCursor {
+            x,
+            y,
+            fg: self.current_fg,
+            bg: self.current_bg,
+            fg_rgb: self.current_fg_rgb,
+            bg_rgb: self.current_bg_rgb,
+            bold: self.bold,
+            blink: self.blink,
+            reverse: self.reverse,
+        });
+    }
+
+    /// Restore cursor position and SGR attributes (ESC[u / ESC 8).
+    ///
+    /// Without a prior save, the cursor homes and attributes reset.
+    fn restore_cursor(&mut self, screen: &mut Screen) {
+        let saved = self.saved_cursor.unwrap_or_default();
+        screen.set_cursor(saved.x, saved.y);
+        self.current_fg = saved.fg;
+        self.current_bg = saved.bg;
+        self.current_fg_rgb = saved.fg_rgb;
+        self.current_bg_rgb = saved.bg_rgb;
+        self.bold = saved.bold;
+        self.blink = saved.blink;
+        self.reverse = saved.reverse;
+    }
+
+    /// Get the effective foreground color and exact color (applying bold and reverse)
+    fn effective_fg(&self) -> (u8, Option<Rgb>) {
+        let (fg, fg_rgb) = if self.reverse {
+            (self.current_bg, self.current_bg_rgb)
+        } else {
+            (self.current_fg, self.current_fg_rgb)
+        };
         // Bold makes foreground bright (add 8 if not already bright)
-        if self.bold && fg < 8 { fg + 8 } else { fg }
+        (if self.bold && fg < 8 { fg + 8 } else { fg }, fg_rgb)
     }
 
-    /// Get the effective background color (applying blink and reverse)
-    fn effective_bg(&self) -> u8 {
-        let bg = if self.reverse { self.current_fg } else { self.current_bg };
-        // Blink makes background bright (DOS behavior - no actual blinking)
-        if self.blink && bg < 8 { bg + 8 } else { bg }
+    /// Get the effective background color and exact color (applying blink and reverse)
+    fn effective_bg(&self, screen: &Screen) -> (u8, Option<Rgb>) {
+        let (bg, bg_rgb) = if self.reverse {
+            (self.current_fg, self.current_fg_rgb)
+        } else {
+            (self.current_bg, self.current_bg_rgb)
+        };
+        let bg = if screen.ice_colors() {
+            // iCE colors: blink makes background bright
+            if self.blink && bg < 8 { bg + 8 } else { bg }
+        } else {
+            // Real blink mode: only 8 background colors are available
+            bg & 0x07
+        };
+        (bg, bg_rgb)
     }
 
     fn write_char(&self, ch: u8, screen: &mut Screen) -> ParseAction {
         let (x, y) = screen.cursor_pos();
+        let (fg, fg_rgb) = self.effective_fg();
+        let (bg, bg_rgb) = self.effective_bg(screen);
         let cell = Cell {
             ch,
-            fg: self.effective_fg(),
-            bg: self.effective_bg(),
+            fg,
+            bg,
+            blink: self.blink && !screen.ice_colors(),
+            fg_rgb,
+            bg_rgb,
         };
         screen.set_cell(x, y, cell);
 
         // Move cursor forward
-        let (width, height) = screen.dimensions();
+        let (width, _) = screen.dimensions();
         if x + 1 < width {
             screen.set_cursor(x + 1, y);
             ParseAction::None
         } else {
             // Line wrap: move to start of next line
-            if y + 1 < height {
+            if y + 1 < screen.max_rows() {
                 screen.set_cursor(0, y + 1);
                 ParseAction::None
             } else {
@@ -270,8 +517,7 @@ impl AnsiParser {
 
     fn handle_newline(&self, screen: &mut Screen) -> ParseAction {
         let (_, y) = screen.cursor_pos();
-        let (_, height) = screen.dimensions();
-        if y + 1 < height {
+        if y + 1 < screen.max_rows() {
             screen.set_cursor(0, y + 1);
             ParseAction::None
         } else {
@@ -287,3 +533,275 @@ impl AnsiParser {
         screen.set_cursor(0, y);
     }
 }
+
+/// Read the rest of an SGR 38/48 color (`5;n` or `2;r;g;b`).
+///
+/// Returns the palette index (the nearest entry for colors outside the
+/// palette) and, in true color mode, the exact color.
+fn extended_color(
+    params: &mut impl Iterator<Item = u32>,
+    palette: Palette,
+    true_color: bool,
+) -> Option<(u8, Option<Rgb>)> {
+    let rgb = match params.next()? {
+        5 => match u8::try_from(params.next()?).ok()? {
+            index @ 0..=15 => return Some((index, None)),
+            index => xterm_color(index),
+        },
+        2 => {
+            let mut channel = || params.next().map(|value| value.min(255) as u8);
+            (channel()?, channel()?, channel()?)
+        }
+        _ => return None,
+    };
+    Some((nearest_color(rgb, palette), true_color.then_some(rgb)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(parser: &mut AnsiParser, screen: &mut Screen, bytes: &[u8]) {
+        for &byte in bytes {
+            parser.process_byte(byte, screen);
+        }
+    }
+
+    #[test]
+    fn test_erase_line_to_end() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"ABCDEFGH\x1b[1;4H\x1b[44m\x1b[K");
+        assert!(screen.row_text(0).starts_with("ABC     "));
+        assert_eq!(screen.get_cell(3, 0).unwrap().bg, 4);
+        assert_eq!(screen.get_cell(79, 0).unwrap().bg, 4);
+        assert_eq!(screen.get_cell(2, 0).unwrap().bg, 0);
+        assert_eq!(screen.cursor_pos(), (3, 0));
+    }
+
+    #[test]
+    fn test_erase_line_modes() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"ABCDEFGH\x1b[1;4H\x1b[1K");
+        assert!(screen.row_text(0).starts_with("    EFGH"));
+
+        feed(&mut parser, &mut screen, b"\x1b[2K");
+        assert_eq!(screen.row_text(0).trim(), "");
+    }
+
+    #[test]
+    fn test_erase_display_modes() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"line1\r\nline2\r\nline3\x1b[2;3H\x1b[J");
+        assert_eq!(screen.row_text(0).trim(), "line1");
+        assert_eq!(screen.row_text(1).trim(), "li");
+        assert_eq!(screen.row_text(2).trim(), "");
+
+        feed(&mut parser, &mut screen, b"\x1b[1;3H\x1b[1J");
+        assert_eq!(screen.row_text(0).trim(), "e1");
+        assert_eq!(screen.cursor_pos(), (2, 0));
+    }
+
+    #[test]
+    fn test_save_restore_cursor_csi() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[5;10H\x1b[1;33;44m\x1b[s");
+        feed(&mut parser, &mut screen, b"\x1b[25;1H\x1b[0;37mStatus bar\x1b[u*");
+        let cell = screen.get_cell(9, 4).unwrap();
+        assert_eq!(cell.ch, b'*');
+        assert_eq!(cell.fg, 11);
+        assert_eq!(cell.bg, 4);
+        assert_eq!(screen.cursor_pos(), (10, 4));
+    }
+
+    #[test]
+    fn test_save_restore_cursor_dec() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[3;7H\x1b[31m\x1b7\x1b[1;1H\x1b[32mX\x1b8Y");
+        assert_eq!(screen.get_cell(0, 0).unwrap().fg, 2);
+        let cell = screen.get_cell(6, 2).unwrap();
+        assert_eq!(cell.ch, b'Y');
+        assert_eq!(cell.fg, 1);
+    }
+
+    #[test]
+    fn test_restore_without_save_homes_cursor() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[10;10H\x1b[1;31m\x1b[uZ");
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!(cell.ch, b'Z');
+        assert_eq!(cell.fg, 7);
+    }
+
+    #[test]
+    fn test_stop_at_eof() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        parser.set_stop_at_eof(true);
+        feed(&mut parser, &mut screen, b"AB\x1aSAUCE00");
+        assert_eq!(screen.row_text(0).trim(), "AB");
+    }
+
+    #[test]
+    fn test_eof_ignored_by_default() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"AB\x1aCD");
+        assert_eq!(screen.row_text(0).trim(), "ABCD");
+    }
+
+    #[test]
+    fn test_ice_colors_blink_is_bright_background() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[5;44mA");
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!(cell.bg, 12);
+        assert!(!cell.blink);
+    }
+
+    #[test]
+    fn test_blink_mode_sequence() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[?33l\x1b[5;44mA\x1b[104mB");
+        assert!(!screen.ice_colors());
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!(cell.bg, 4);
+        assert!(cell.blink);
+        assert_eq!(screen.get_cell(1, 0).unwrap().bg, 4);
+
+        feed(&mut parser, &mut screen, b"\x1b[?33hC");
+        assert!(screen.ice_colors());
+        let cell = screen.get_cell(2, 0).unwrap();
+        assert_eq!(cell.bg, 12);
+        assert!(!cell.blink);
+    }
+
+    #[test]
+    fn test_unknown_private_mode_ignored() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[?25lAB\x1b[?7h");
+        assert_eq!(screen.row_text(0).trim(), "AB");
+        assert!(screen.ice_colors());
+    }
+
+    #[test]
+    fn test_256_colors_map_to_palette() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[38;5;12;48;5;196mA\x1b[38;5;231;48;5;16mB");
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!((cell.fg, cell.bg), (12, 1));
+        assert_eq!((cell.fg_rgb, cell.bg_rgb), (None, None));
+        let cell = screen.get_cell(1, 0).unwrap();
+        assert_eq!((cell.fg, cell.bg), (15, 0));
+    }
+
+    #[test]
+    fn test_24_bit_colors_map_to_palette() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[38;2;170;0;0;1mA\x1b[0;48;2;0;0;160mB\x1b[31mC");
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!(cell.fg, 9);
+        assert_eq!(cell.fg_rgb, None);
+        assert_eq!(screen.get_cell(1, 0).unwrap().bg, 4);
+        assert_eq!(screen.get_cell(2, 0).unwrap().fg, 1);
+    }
+
+    #[test]
+    fn test_true_color_mode_keeps_exact_colors() {
+        let mut parser = AnsiParser::new();
+        parser.set_true_color(true);
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[38;2;250;128;114;48;5;22mA\x1b[7mB\x1b[K");
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!(cell.fg_rgb, Some((250, 128, 114)));
+        assert_eq!(cell.bg_rgb, Some((0x00, 0x5F, 0x00)));
+        assert_eq!(cell.fg, 9);
+
+        // Reverse swaps the exact colors, and erasing uses the background
+        let cell = screen.get_cell(1, 0).unwrap();
+        assert_eq!(cell.fg_rgb, Some((0x00, 0x5F, 0x00)));
+        assert_eq!(cell.bg_rgb, Some((250, 128, 114)));
+        assert_eq!(screen.get_cell(2, 0).unwrap().bg_rgb, Some((250, 128, 114)));
+
+        // Palette colors and reset clear them
+        feed(&mut parser, &mut screen, b"\x1b[0;34mC\x1b[0mD");
+        assert_eq!(screen.get_cell(2, 0).unwrap().fg_rgb, None);
+        assert_eq!(screen.get_cell(3, 0).unwrap().bg_rgb, None);
+    }
+
+    #[test]
+    fn test_extended_colors_follow_palette() {
+        let mut parser = AnsiParser::new();
+        parser.set_palette(Palette::Amiga);
+        parser.reset();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[38;2;187;187;0mA\x1b[38;5mB");
+        assert_eq!(screen.get_cell(0, 0).unwrap().fg, 3);
+        assert_eq!(screen.get_cell(1, 0).unwrap().fg, 3);
+    }
+
+    #[test]
+    fn test_pablodraw_rgb_colors() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[1;255;128;0t\x1b[0;16;32;48tA\x1b[31mB\x1b[0mC");
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!(cell.fg_rgb, Some((255, 128, 0)));
+        assert_eq!(cell.bg_rgb, Some((16, 32, 48)));
+        assert_eq!((cell.fg, cell.bg), (nearest_color((255, 128, 0), Palette::Vga), 0));
+
+        // Palette colors replace the exact color, reset clears both
+        let cell = screen.get_cell(1, 0).unwrap();
+        assert_eq!((cell.fg, cell.fg_rgb, cell.bg_rgb), (1, None, Some((16, 32, 48))));
+        let cell = screen.get_cell(2, 0).unwrap();
+        assert_eq!((cell.fg_rgb, cell.bg_rgb), (None, None));
+    }
+
+    #[test]
+    fn test_other_t_sequences_ignored() {
+        let mut parser = AnsiParser::new();
+        let mut screen = Screen::new();
+        feed(&mut parser, &mut screen, b"\x1b[8;50;132t\x1b[2;1;2;3t\x1b[1;1;2tA");
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!((cell.ch, cell.fg, cell.fg_rgb, cell.bg_rgb), (b'A', 7, None, None));
+    }
+
+    #[test]
+    fn test_draw_document() {
+        let mut parser = AnsiParser::new();
+        parser.set_stop_at_eof(true);
+        let mut art = b"\x1b[2Jtop\r\n".to_vec();
+        for _ in 0..40 {
+            art.extend(b"\r\n");
+        }
+        // Back up past the top of a 25-row page, then write below the end
+        art.extend(b"bottom\x1b[40AX\x1b[50;1Hend\r\n\x1a\r\n\r\n");
+        let screen = parser.draw_document(&art, 80, true);
+
+        assert_eq!(screen.dimensions(), (80, 51));
+        assert_eq!(&screen.row_text(0)[..3], "top");
+        assert_eq!(&screen.row_text(1)[..7], "      X");
+        assert_eq!(&screen.row_text(41)[..6], "bottom");
+        assert_eq!(&screen.row_text(49)[..3], "end");
+    }
+
+    #[test]
+    fn test_draw_document_clear_keeps_rows() {
+        let mut parser = AnsiParser::new();
+        let screen = parser.draw_document(b"old\r\n\r\n\r\nold\x1b[2Jnew", 80, false);
+        assert_eq!(screen.dimensions(), (80, 4));
+        assert_eq!(&screen.row_text(0)[..3], "new");
+        assert_eq!(&screen.row_text(3)[..3], "   ");
+        assert!(!screen.ice_colors());
+    }
+}
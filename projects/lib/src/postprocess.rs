@@ -1,6 +1,8 @@
-/// WebGL post-processing for CRT effects.
-///
-/// Applies gaussian blur and scanline effects to the rendered terminal output.
+//! WebGL post-processing for CRT effects.
+//!
+//! Applies a configurable effect chain to the rendered terminal output:
+//! gaussian blur, phosphor glow, barrel curvature, scanlines, an RGB shadow
+//! mask, and a vignette. `CrtEffects::NONE` gives crisp pixel output.
 
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
@@ -9,10 +11,10 @@ use web_sys::{
     WebGlShader, WebGlTexture, WebGlUniformLocation,
 };
 
-use crate::renderer::{CANVAS_HEIGHT, CANVAS_WIDTH};
+use crate::raster::SCALE_Y;
 
-/// Vertex shader source (shared by all passes)
-const VERTEX_SHADER: &str = r#"
+/// Vertex shader source (shared by all passes and the WebGL text renderer)
+pub const VERTEX_SHADER: &str = r#"
     attribute vec2 a_position;
     attribute vec2 a_texcoord;
     varying vec2 v_texcoord;
@@ -45,46 +47,181 @@ const BLUR_FRAGMENT_SHADER: &str = r#"
     }
 "#;
 
-/// Passthrough fragment shader (just copies texture)
-const PASSTHROUGH_FRAGMENT_SHADER: &str = r#"
+/// CRT composite fragment shader (curvature, glow, scanlines, shadow mask,
+/// vignette). With every setting at 0.0 it copies the texture unchanged.
+const CRT_FRAGMENT_SHADER: &str = r#"
+    #ifdef GL_FRAGMENT_PRECISION_HIGH
+    precision highp float;
+    #else
     precision mediump float;
+    #endif
     uniform sampler2D u_texture;
+    uniform sampler2D u_glow_texture;
+    uniform vec2 u_resolution;
+    uniform float u_scanline_period;  // output pixels per EGA scanline
+    uniform float u_scanlines;
+    uniform float u_glow;
+    uniform float u_curvature;
+    uniform float u_vignette;
+    uniform float u_shadow_mask;
     varying vec2 v_texcoord;
 
     void main() {
-        gl_FragColor = texture2D(u_texture, v_texcoord);
+        // Barrel curvature: push coordinates outward, more toward the corners
+        vec2 centered = v_texcoord * 2.0 - 1.0;
+        centered += centered * (centered.yx * centered.yx) * u_curvature * 0.25;
+        vec2 uv = centered * 0.5 + 0.5;
+        if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+            gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+            return;
+        }
+
+        vec3 color = texture2D(u_texture, uv).rgb;
+        color += texture2D(u_glow_texture, uv).rgb * u_glow;
+
+        // Scanlines: darken the gaps between EGA scanlines
+        float line = 0.5 - 0.5 * cos(6.2831853 * uv.y * u_resolution.y / u_scanline_period);
+        color *= 1.0 - u_scanlines * (1.0 - line);
+
+        // Shadow mask: each output column favors red, green, or blue
+        float phase = mod(floor(gl_FragCoord.x), 3.0);
+        vec3 triad = vec3(step(phase, 0.5), step(0.5, phase) * step(phase, 1.5), step(1.5, phase));
+        color *= mix(vec3(1.0 - u_shadow_mask), vec3(1.0), triad);
+
+        // Vignette: darken toward the edges
+        float edge = uv.x * uv.y * (1.0 - uv.x) * (1.0 - uv.y) * 16.0;
+        color *= mix(1.0, pow(edge, 0.25), u_vignette);
+
+        gl_FragColor = vec4(color, 1.0);
     }
 "#;
 
-/// WebGL post-processor for blur effects.
+/// Texel spacing of the wide blur used for phosphor glow.
+const GLOW_SPREAD: f32 = 4.0;
+
+/// CRT effect settings. Each effect is off at 0.0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrtEffects {
+    /// Blur spread in pixels per tap (0.0-4.0, default 1.0)
+    pub blur: f32,
+    /// Darkening between scanlines (0.0-1.0)
+    pub scanlines: f32,
+    /// Phosphor glow (bloom) strength (0.0-1.0)
+    pub glow: f32,
+    /// Barrel curvature of the tube (0.0-1.0)
+    pub curvature: f32,
+    /// Darkening toward the edges (0.0-1.0)
+    pub vignette: f32,
+    /// RGB shadow mask strength (0.0-1.0)
+    pub shadow_mask: f32,
+}
+
+impl CrtEffects {
+    /// No effects: crisp pixel output.
+    pub const NONE: CrtEffects = CrtEffects {
+        blur: 0.0,
+        scanlines: 0.0,
+        glow: 0.0,
+        curvature: 0.0,
+        vignette: 0.0,
+        shadow_mask: 0.0,
+    };
+
+    /// A curved, glowing CRT with visible scanlines.
+    pub const CRT: CrtEffects = CrtEffects {
+        blur: 1.0,
+        scanlines: 0.4,
+        glow: 0.35,
+        curvature: 0.3,
+        vignette: 0.4,
+        shadow_mask: 0.25,
+    };
+
+    /// Look up a preset by name ("none", "default", or "crt"; case-insensitive).
+    pub fn from_preset(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "none" => Some(CrtEffects::NONE),
+            "default" => Some(CrtEffects::default()),
+            "crt" => Some(CrtEffects::CRT),
+            _ => None,
+        }
+    }
+
+    /// Settings limited to their supported ranges (NaN counts as 0.0).
+    pub fn clamped(self) -> Self {
+        let limit = |value: f32, max: f32| if value.is_nan() { 0.0 } else { value.clamp(0.0, max) };
+        CrtEffects {
+            blur: limit(self.blur, 4.0),
+            scanlines: limit(self.scanlines, 1.0),
+            glow: limit(self.glow, 1.0),
+            curvature: limit(self.curvature, 1.0),
+            vignette: limit(self.vignette, 1.0),
+            shadow_mask: limit(self.shadow_mask, 1.0),
+        }
+    }
+}
+
+impl Default for CrtEffects {
+    /// A light blur to soften the scaled pixels.
+    fn default() -> Self {
+        CrtEffects {
+            blur: 1.0,
+            ..CrtEffects::NONE
+        }
+    }
+}
+
+/// WebGL post-processor for CRT effects.
 pub struct PostProcessor {
     gl: WebGlRenderingContext,
+    effects: CrtEffects,
     blur_program: WebGlProgram,
-    passthrough_program: WebGlProgram,
+    crt_program: WebGlProgram,
     source_texture: WebGlTexture,
     intermediate_texture: WebGlTexture,
+    /// Wide blur of the frame, only created when glow is enabled
+    glow_texture: Option<WebGlTexture>,
     framebuffer: WebGlFramebuffer,
+    /// Canvas size in pixels (texture and viewport size)
+    width: i32,
+    height: i32,
     /// Vertex buffer - kept alive for WebGL state, accessed via GL context not Rust
     _quad_buffer: WebGlBuffer,
     // Uniform locations for blur program
     blur_texture_loc: WebGlUniformLocation,
     blur_resolution_loc: WebGlUniformLocation,
     blur_direction_loc: WebGlUniformLocation,
-    // Uniform locations for passthrough program
-    passthrough_texture_loc: WebGlUniformLocation,
+    // Uniform locations for CRT program
+    crt_uniforms: CrtUniforms,
+}
+
+/// Uniform locations for the CRT composite program.
+struct CrtUniforms {
+    texture: WebGlUniformLocation,
+    glow_texture: WebGlUniformLocation,
+    resolution: WebGlUniformLocation,
+    scanline_period: WebGlUniformLocation,
+    scanlines: WebGlUniformLocation,
+    glow: WebGlUniformLocation,
+    curvature: WebGlUniformLocation,
+    vignette: WebGlUniformLocation,
+    shadow_mask: WebGlUniformLocation,
 }
 
 impl PostProcessor {
     /// Create a new post-processor for the given display canvas.
-    pub fn new(canvas: &HtmlCanvasElement) -> Result<Self, JsValue> {
+    pub fn new(canvas: &HtmlCanvasElement, effects: CrtEffects) -> Result<Self, JsValue> {
         let gl = canvas
             .get_context("webgl")?
             .ok_or("Failed to get WebGL context")?
             .dyn_into::<WebGlRenderingContext>()?;
+        let width = canvas.width() as i32;
+        let height = canvas.height() as i32;
+        let effects = effects.clamped();
 
         // Compile shaders and create programs
         let blur_program = create_program(&gl, VERTEX_SHADER, BLUR_FRAGMENT_SHADER)?;
-        let passthrough_program = create_program(&gl, VERTEX_SHADER, PASSTHROUGH_FRAGMENT_SHADER)?;
+        let crt_program = create_program(&gl, VERTEX_SHADER, CRT_FRAGMENT_SHADER)?;
 
         // Get uniform locations for blur program
         let blur_texture_loc = gl
@@ -97,28 +234,47 @@ impl PostProcessor {
             .get_uniform_location(&blur_program, "u_direction")
             .ok_or("Failed to get u_direction location for blur")?;
 
-        // Get uniform locations for passthrough program
-        let passthrough_texture_loc = gl
-            .get_uniform_location(&passthrough_program, "u_texture")
-            .ok_or("Failed to get u_texture location for passthrough")?;
-
-        // Create textures
-        let source_texture = create_texture(&gl)?;
-        let intermediate_texture = create_texture(&gl)?;
-
-        // Initialize intermediate texture with correct size
-        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&intermediate_texture));
-        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
-            WebGlRenderingContext::TEXTURE_2D,
-            0,
-            WebGlRenderingContext::RGBA as i32,
-            CANVAS_WIDTH as i32,
-            CANVAS_HEIGHT as i32,
-            0,
-            WebGlRenderingContext::RGBA,
-            WebGlRenderingContext::UNSIGNED_BYTE,
-            None,
-        )?;
+        // Get uniform locations for CRT program
+        let crt_uniform = |name: &str| {
+            gl.get_uniform_location(&crt_program, name)
+                .ok_or_else(|| JsValue::from_str(&format!("Failed to get {} location for CRT", name)))
+        };
+        let crt_uniforms = CrtUniforms {
+            texture: crt_uniform("u_texture")?,
+            glow_texture: crt_uniform("u_glow_texture")?,
+            resolution: crt_uniform("u_resolution")?,
+            scanline_period: crt_uniform("u_scanline_period")?,
+            scanlines: crt_uniform("u_scanlines")?,
+            glow: crt_uniform("u_glow")?,
+            curvature: crt_uniform("u_curvature")?,
+            vignette: crt_uniform("u_vignette")?,
+            shadow_mask: crt_uniform("u_shadow_mask")?,
+        };
+
+        // Create textures with correct size (the source texture is also a
+        // render target for the WebGL text renderer)
+        let create_sized_texture = || -> Result<WebGlTexture, JsValue> {
+            let texture = create_texture(&gl)?;
+            gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                WebGlRenderingContext::TEXTURE_2D,
+                0,
+                WebGlRenderingContext::RGBA as i32,
+                width,
+                height,
+                0,
+                WebGlRenderingContext::RGBA,
+                WebGlRenderingContext::UNSIGNED_BYTE,
+                None,
+            )?;
+            Ok(texture)
+        };
+        let source_texture = create_sized_texture()?;
+        let intermediate_texture = create_sized_texture()?;
+        let glow_texture = if effects.glow > 0.0 {
+            Some(create_sized_texture()?)
+        } else {
+            None
+        };
 
         // Create framebuffer for intermediate rendering
         let framebuffer = gl
@@ -133,19 +289,28 @@ impl PostProcessor {
 
         Ok(PostProcessor {
             gl,
+            effects,
             blur_program,
-            passthrough_program,
+            crt_program,
             source_texture,
             intermediate_texture,
+            glow_texture,
             framebuffer,
+            width,
+            height,
             _quad_buffer: quad_buffer,
             blur_texture_loc,
             blur_resolution_loc,
             blur_direction_loc,
-            passthrough_texture_loc,
+            crt_uniforms,
         })
     }
 
+    /// The WebGL context of the display canvas.
+    pub fn gl(&self) -> &WebGlRenderingContext {
+        &self.gl
+    }
+
     /// Apply post-processing effects to the source canvas and render to display.
     pub fn process(&self, source_canvas: &HtmlCanvasElement) -> Result<(), JsValue> {
         let gl = &self.gl;
@@ -164,23 +329,78 @@ impl PostProcessor {
             source_canvas,
         )?;
 
-        // Pass 1: Horizontal blur (source -> intermediate)
-        gl.bind_framebuffer(
-            WebGlRenderingContext::FRAMEBUFFER,
-            Some(&self.framebuffer),
+        self.apply()
+    }
+
+    /// Make the source texture the render target, for drawing the frame on the GPU.
+    ///
+    /// Follow with `apply()`. Row 0 of the texture is the top of the frame,
+    /// as with an uploaded canvas.
+    pub fn bind_source(&self) {
+        self.bind_target(&self.source_texture);
+    }
+
+    /// Apply post-processing effects to the source texture and render to display.
+    pub fn apply(&self) -> Result<(), JsValue> {
+        let gl = &self.gl;
+        let effects = &self.effects;
+        gl.active_texture(WebGlRenderingContext::TEXTURE0);
+
+        // Passes 1-2: Blur (source -> intermediate -> source)
+        if effects.blur > 0.0 {
+            self.blur(&self.source_texture, effects.blur)?;
+        }
+
+        // Passes 3-4: Wide blur for glow (source -> intermediate -> glow)
+        if let Some(glow_texture) = &self.glow_texture {
+            self.blur(glow_texture, GLOW_SPREAD)?;
+        }
+
+        // Final pass: CRT composite (source -> screen)
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+
+        gl.viewport(0, 0, self.width, self.height);
+        gl.use_program(Some(&self.crt_program));
+
+        let uniforms = &self.crt_uniforms;
+        gl.active_texture(WebGlRenderingContext::TEXTURE1);
+        gl.bind_texture(
+            WebGlRenderingContext::TEXTURE_2D,
+            Some(self.glow_texture.as_ref().unwrap_or(&self.source_texture)),
         );
-        gl.framebuffer_texture_2d(
-            WebGlRenderingContext::FRAMEBUFFER,
-            WebGlRenderingContext::COLOR_ATTACHMENT0,
+        gl.uniform1i(Some(&uniforms.glow_texture), 1);
+        gl.active_texture(WebGlRenderingContext::TEXTURE0);
+        gl.bind_texture(
             WebGlRenderingContext::TEXTURE_2D,
-            Some(&self.intermediate_texture),
-            0,
+            Some(&self.source_texture),
         );
+        gl.uniform1i(Some(&uniforms.texture), 0);
+
+        gl.uniform2f(Some(&uniforms.resolution), self.width as f32, self.height as f32);
+        gl.uniform1f(Some(&uniforms.scanline_period), SCALE_Y as f32);
+        gl.uniform1f(Some(&uniforms.scanlines), effects.scanlines);
+        gl.uniform1f(Some(&uniforms.glow), effects.glow);
+        gl.uniform1f(Some(&uniforms.curvature), effects.curvature);
+        gl.uniform1f(Some(&uniforms.vignette), effects.vignette);
+        gl.uniform1f(Some(&uniforms.shadow_mask), effects.shadow_mask);
+
+        setup_vertex_attributes(gl, &self.crt_program)?;
+        gl.draw_arrays(WebGlRenderingContext::TRIANGLE_STRIP, 0, 4);
+
+        Ok(())
+    }
 
-        gl.viewport(0, 0, CANVAS_WIDTH as i32, CANVAS_HEIGHT as i32);
+    /// Separable gaussian blur of the source texture into `target`, via the
+    /// intermediate texture. `spread` is the tap spacing in pixels.
+    ///
+    /// Each pass flips the image vertically, so two passes keep row 0 at the top.
+    fn blur(&self, target: &WebGlTexture, spread: f32) -> Result<(), JsValue> {
+        let gl = &self.gl;
+
+        // Horizontal (source -> intermediate)
+        self.bind_target(&self.intermediate_texture);
         gl.use_program(Some(&self.blur_program));
 
-        gl.active_texture(WebGlRenderingContext::TEXTURE0);
         gl.bind_texture(
             WebGlRenderingContext::TEXTURE_2D,
             Some(&self.source_texture),
@@ -189,49 +409,44 @@ impl PostProcessor {
         gl.uniform1i(Some(&self.blur_texture_loc), 0);
         gl.uniform2f(
             Some(&self.blur_resolution_loc),
-            CANVAS_WIDTH as f32,
-            CANVAS_HEIGHT as f32,
+            self.width as f32,
+            self.height as f32,
         );
-        gl.uniform2f(Some(&self.blur_direction_loc), 1.0, 0.0); // Horizontal
+        gl.uniform2f(Some(&self.blur_direction_loc), spread, 0.0);
 
         setup_vertex_attributes(gl, &self.blur_program)?;
         gl.draw_arrays(WebGlRenderingContext::TRIANGLE_STRIP, 0, 4);
 
-        // Pass 2: Vertical blur (intermediate -> source texture, reusing it)
-        gl.framebuffer_texture_2d(
-            WebGlRenderingContext::FRAMEBUFFER,
-            WebGlRenderingContext::COLOR_ATTACHMENT0,
-            WebGlRenderingContext::TEXTURE_2D,
-            Some(&self.source_texture),
-            0,
-        );
+        // Vertical (intermediate -> target)
+        self.bind_target(target);
 
         gl.bind_texture(
             WebGlRenderingContext::TEXTURE_2D,
             Some(&self.intermediate_texture),
         );
 
-        gl.uniform2f(Some(&self.blur_direction_loc), 0.0, 1.0); // Vertical
+        gl.uniform2f(Some(&self.blur_direction_loc), 0.0, spread);
 
         gl.draw_arrays(WebGlRenderingContext::TRIANGLE_STRIP, 0, 4);
 
-        // Pass 3: Passthrough (source -> screen)
-        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
-
-        gl.viewport(0, 0, CANVAS_WIDTH as i32, CANVAS_HEIGHT as i32);
-        gl.use_program(Some(&self.passthrough_program));
+        Ok(())
+    }
 
-        gl.bind_texture(
+    /// Render into a texture through the framebuffer.
+    fn bind_target(&self, texture: &WebGlTexture) {
+        let gl = &self.gl;
+        gl.bind_framebuffer(
+            WebGlRenderingContext::FRAMEBUFFER,
+            Some(&self.framebuffer),
+        );
+        gl.framebuffer_texture_2d(
+            WebGlRenderingContext::FRAMEBUFFER,
+            WebGlRenderingContext::COLOR_ATTACHMENT0,
             WebGlRenderingContext::TEXTURE_2D,
-            Some(&self.source_texture),
+            Some(texture),
+            0,
         );
-
-        gl.uniform1i(Some(&self.passthrough_texture_loc), 0);
-
-        setup_vertex_attributes(gl, &self.passthrough_program)?;
-        gl.draw_arrays(WebGlRenderingContext::TRIANGLE_STRIP, 0, 4);
-
-        Ok(())
+        gl.viewport(0, 0, self.width, self.height);
     }
 }
 
@@ -264,7 +479,7 @@ fn compile_shader(
 }
 
 /// Create a shader program from vertex and fragment shader sources.
-fn create_program(
+pub fn create_program(
     gl: &WebGlRenderingContext,
     vertex_src: &str,
     fragment_src: &str,
@@ -361,7 +576,7 @@ fn create_quad_buffer(gl: &WebGlRenderingContext) -> Result<WebGlBuffer, JsValue
 }
 
 /// Set up vertex attributes for the current program.
-fn setup_vertex_attributes(
+pub fn setup_vertex_attributes(
     gl: &WebGlRenderingContext,
     program: &WebGlProgram,
 ) -> Result<(), JsValue> {
@@ -393,3 +608,34 @@ fn setup_vertex_attributes(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(CrtEffects::from_preset("None"), Some(CrtEffects::NONE));
+        assert_eq!(CrtEffects::from_preset("crt"), Some(CrtEffects::CRT));
+        assert_eq!(CrtEffects::from_preset("default"), Some(CrtEffects::default()));
+        assert_eq!(CrtEffects::from_preset("sepia"), None);
+        assert_eq!(CrtEffects::from_preset("off"), None);
+        assert_eq!(CrtEffects::default().blur, 1.0);
+        assert_eq!(CrtEffects::default().scanlines, 0.0);
+    }
+
+    #[test]
+    fn test_clamped() {
+        let effects = CrtEffects {
+            blur: 10.0,
+            scanlines: -1.0,
+            glow: f32::NAN,
+            ..CrtEffects::CRT
+        }
+        .clamped();
+        assert_eq!(effects.blur, 4.0);
+        assert_eq!(effects.scanlines, 0.0);
+        assert_eq!(effects.glow, 0.0);
+        assert_eq!(effects.curvature, CrtEffects::CRT.curvature);
+    }
+}
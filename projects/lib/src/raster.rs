@@ -0,0 +1,392 @@
+//! Pure-Rust text rasterizer with a glyph cache.
+//!
+//! Glyphs are expanded once per (character, foreground, background) into
+//! scaled RGBA rows and copied into an RGBA framebuffer, so a frame can be
+//! handed to the canvas with a single `putImageData` call.
+
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use crate::font::{self, Font};
+
+/// An RGB color.
+pub type Rgb = (u8, u8, u8);
+
+/// Horizontal scaling per font pixel.
+pub const SCALE_X: usize = 3;
+/// Vertical scaling per font pixel.
+pub const SCALE_Y: usize = 4;
+
+/// Default number of glyphs kept in the cache.
+pub const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// Size of a character cell in pixels (8x14 scales to 24x56, or 27x56
+/// with 9-pixel cells).
+pub fn cell_size(font: &Font) -> (usize, usize) {
+    scaled_cell_size(font, (SCALE_X, SCALE_Y))
+}
+
+/// Size of a character cell in pixels at a given (horizontal, vertical)
+/// scale per font pixel.
+pub fn scaled_cell_size(font: &Font, (scale_x, scale_y): (usize, usize)) -> (usize, usize) {
+    (font.cell_width() * scale_x, font.height() * scale_y)
+}
+
+/// Expand a glyph into horizontally scaled RGBA scanlines.
+///
+/// Writes one row per font scanline, each `cell_size(font).0` pixels wide,
+/// to `out` (vertical scaling is applied when the glyph is drawn).
+pub fn expand_glyph(font: &Font, ch: u8, fg: Rgb, bg: Rgb, out: &mut Vec<u8>) {
+    expand_glyph_scaled(font, ch, fg, bg, SCALE_X, out);
+}
+
+/// Expand a glyph into RGBA scanlines with `scale_x` pixels per font pixel.
+pub fn expand_glyph_scaled(font: &Font, ch: u8, fg: Rgb, bg: Rgb, scale_x: usize, out: &mut Vec<u8>) {
+    out.clear();
+    out.reserve(font.cell_width() * scale_x * font.height() * 4);
+    for font_y in 0..font.height() {
+        for font_x in 0..font.cell_width() {
+            let (r, g, b) = if font.is_set(ch, font_x, font_y) { fg } else { bg };
+            for _ in 0..scale_x {
+                out.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+}
+
+/// Cache key: character plus resolved colors (so palette changes never
+/// return stale glyphs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    ch: u8,
+    fg: Rgb,
+    bg: Rgb,
+}
+
+/// Least-recently-used cache of expanded glyphs for one font.
+pub struct GlyphCache {
+    font: Rc<Font>,
+    /// Horizontal scale per font pixel
+    scale_x: usize,
+    capacity: usize,
+    /// Use counter for LRU eviction
+    tick: u64,
+    /// Glyphs and the tick of their last use
+    entries: HashMap<GlyphKey, (Vec<u8>, u64)>,
+    /// Uses in order, oldest first. A use superseded by a later one of the
+    /// same glyph is stale: skipped on eviction and dropped on compaction.
+    order: VecDeque<(GlyphKey, u64)>,
+}
+
+impl GlyphCache {
+    /// Create a cache holding up to `capacity` glyphs of `font`.
+    pub fn new(font: Rc<Font>, capacity: usize) -> Self {
+        Self::with_scale(font, capacity, SCALE_X)
+    }
+
+    /// Create a cache whose glyphs are `scale_x` pixels per font pixel wide.
+    pub fn with_scale(font: Rc<Font>, capacity: usize, scale_x: usize) -> Self {
+        GlyphCache {
+            font,
+            scale_x: scale_x.max(1),
+            capacity: capacity.max(1),
+            tick: 0,
+            entries: HashMap::with_capacity(capacity.max(1)),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Get the expanded glyph, expanding and caching it if needed.
+    pub fn glyph(&mut self, ch: u8, fg: Rgb, bg: Rgb) -> &[u8] {
+        self.tick += 1;
+        let key = GlyphKey { ch, fg, bg };
+        if !self.entries.contains_key(&key) {
+            if self.entries.len() >= self.capacity {
+                self.evict();
+            }
+            let mut pixels = Vec::new();
+            expand_glyph_scaled(&self.font, ch, fg, bg, self.scale_x, &mut pixels);
+            self.entries.insert(key, (pixels, 0));
+        }
+        self.entries.get_mut(&key).expect("glyph was just inserted").1 = self.tick;
+        self.order.push_back((key, self.tick));
+        if self.order.len() > 2 * self.capacity {
+            self.compact();
+        }
+        &self.entries[&key].0
+    }
+
+    /// The font glyphs are expanded from.
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    /// Number of cached glyphs.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether `(key, used)` is the latest use of a cached glyph.
+    fn is_current(&self, key: &GlyphKey, used: u64) -> bool {
+        self.entries.get(key).is_some_and(|&(_, tick)| tick == used)
+    }
+
+    /// Remove the least recently used glyph.
+    fn evict(&mut self) {
+        while let Some((key, used)) = self.order.pop_front() {
+            if self.is_current(&key, used) {
+                self.entries.remove(&key);
+                return;
+            }
+        }
+    }
+
+    /// Drop stale uses, leaving one per cached glyph.
+    fn compact(&mut self) {
+        let order = std::mem::take(&mut self.order);
+        self.order = order.into_iter().filter(|&(key, used)| self.is_current(&key, used)).collect();
+    }
+}
+
+impl Default for GlyphCache {
+    /// A cache for the default font.
+    fn default() -> Self {
+        Self::new(font::default_font(), DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+/// A pixel rectangle (x, y, width, height).
+pub type Rect = (usize, usize, usize, usize);
+
+/// RGBA framebuffer sized to a character grid, tracking the changed area.
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    /// Cell size in pixels
+    cell_width: usize,
+    cell_height: usize,
+    pixels: Vec<u8>,
+    /// Bounding box of cells drawn since the last `take_dirty` (x0, y0, x1, y1 exclusive)
+    dirty: Option<(usize, usize, usize, usize)>,
+}
+
+impl Framebuffer {
+    /// Create a black framebuffer for a screen of `columns` x `rows` cells
+    /// of the given pixel size (see `cell_size`).
+    pub fn new(columns: usize, rows: usize, (cell_width, cell_height): (usize, usize)) -> Self {
+        let width = columns * cell_width;
+        let height = rows * cell_height;
+        Framebuffer {
+            width,
+            height,
+            cell_width,
+            cell_height,
+            pixels: [0, 0, 0, 255].repeat(width * height),
+            dirty: None,
+        }
+    }
+
+    /// Size in pixels.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// RGBA pixel data, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Draw an expanded glyph (see `expand_glyph`) into a cell. Each glyph
+    /// scanline is repeated to fill the cell height.
+    pub fn draw_glyph(&mut self, column: usize, row: usize, glyph: &[u8]) {
+        let (cell_width, cell_height) = (self.cell_width, self.cell_height);
+        let px = column * cell_width;
+        let py = row * cell_height;
+        if px + cell_width > self.width || py + cell_height > self.height {
+            return;
+        }
+
+        let stride = self.width * 4;
+        let row_bytes = cell_width * 4;
+        let scale_y = cell_height / (glyph.len() / row_bytes).max(1);
+        for (font_y, src) in glyph.chunks_exact(row_bytes).take(cell_height / scale_y.max(1)).enumerate() {
+            for dy in 0..scale_y {
+                let offset = (py + font_y * scale_y + dy) * stride + px * 4;
+                self.pixels[offset..offset + row_bytes].copy_from_slice(src);
+            }
+        }
+
+        let (x0, y0, x1, y1) = self.dirty.unwrap_or((px, py, px, py));
+        self.dirty = Some((
+            x0.min(px),
+            y0.min(py),
+            x1.max(px + cell_width),
+            y1.max(py + cell_height),
+        ));
+    }
+
+    /// Take the area changed since the last call, if any.
+    pub fn take_dirty(&mut self) -> Option<Rect> {
+        self.dirty
+            .take()
+            .map(|(x0, y0, x1, y1)| (x0, y0, x1 - x0, y1 - y0))
+    }
+
+    /// Copy a rectangle of RGBA pixels into `out`.
+    pub fn copy_rect(&self, (x, y, width, height): Rect, out: &mut Vec<u8>) {
+        out.clear();
+        let stride = self.width * 4;
+        for row in y..y + height {
+            let offset = row * stride + x * 4;
+            out.extend_from_slice(&self.pixels[offset..offset + width * 4]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgb = (255, 255, 255);
+    const BLUE: Rgb = (0, 0, 170);
+
+    #[test]
+    fn test_expand_glyph_size() {
+        let font = Font::ega();
+        let mut out = Vec::new();
+        expand_glyph(&font, b'A', WHITE, BLUE, &mut out);
+        assert_eq!(out.len(), 14 * 24 * 4);
+        // Space is all background
+        expand_glyph(&font, b' ', WHITE, BLUE, &mut out);
+        assert!(out.chunks_exact(4).all(|px| px == [0, 0, 170, 255]));
+    }
+
+    #[test]
+    fn test_cache_hits_and_evicts() {
+        let mut cache = GlyphCache::new(font::default_font(), 2);
+        let first = cache.glyph(b'A', WHITE, BLUE).to_vec();
+        assert_eq!(cache.glyph(b'A', WHITE, BLUE), first.as_slice());
+        assert_eq!(cache.len(), 1);
+
+        cache.glyph(b'B', WHITE, BLUE);
+        cache.glyph(b'A', WHITE, BLUE);
+        // 'B' is least recently used
+        cache.glyph(b'C', WHITE, BLUE);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.entries.contains_key(&GlyphKey { ch: b'A', fg: WHITE, bg: BLUE }));
+        assert!(!cache.entries.contains_key(&GlyphKey { ch: b'B', fg: WHITE, bg: BLUE }));
+    }
+
+    #[test]
+    fn test_cache_evicts_in_use_order() {
+        let mut cache = GlyphCache::new(font::default_font(), 3);
+        // Many hits on 'A' between the misses (compacting the use queue)
+        for ch in [b'A', b'B', b'C'] {
+            cache.glyph(ch, WHITE, BLUE);
+            for _ in 0..10 {
+                cache.glyph(b'A', WHITE, BLUE);
+            }
+        }
+        cache.glyph(b'D', WHITE, BLUE);
+        cache.glyph(b'E', WHITE, BLUE);
+        let cached: Vec<u8> = [b'A', b'B', b'C', b'D', b'E']
+            .into_iter()
+            .filter(|&ch| cache.entries.contains_key(&GlyphKey { ch, fg: WHITE, bg: BLUE }))
+            .collect();
+        assert_eq!(cached, b"ADE");
+        assert!(cache.order.len() <= 2 * cache.capacity);
+    }
+
+    #[test]
+    fn test_cache_keys_include_colors() {
+        let mut cache = GlyphCache::default();
+        let white = cache.glyph(0xDB, WHITE, BLUE).to_vec();
+        let blue = cache.glyph(0xDB, BLUE, BLUE).to_vec();
+        assert_ne!(white, blue);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_framebuffer_draw_and_dirty_rect() {
+        let font = Font::ega();
+        let mut fb = Framebuffer::new(80, 25, cell_size(&font));
+        assert_eq!(fb.dimensions(), (1920, 1400));
+        assert_eq!(fb.take_dirty(), None);
+
+        let mut glyph = Vec::new();
+        expand_glyph(&font, 0xDB, WHITE, BLUE, &mut glyph);
+        fb.draw_glyph(1, 2, &glyph);
+        fb.draw_glyph(3, 2, &glyph);
+        assert_eq!(fb.take_dirty(), Some((24, 112, 72, 56)));
+        assert_eq!(fb.take_dirty(), None);
+
+        // Full block: every pixel of the cell is foreground
+        let mut rect = Vec::new();
+        fb.copy_rect((24, 112, 24, 56), &mut rect);
+        assert_eq!(rect.len(), 24 * 56 * 4);
+        assert!(rect.chunks_exact(4).all(|px| px == [255, 255, 255, 255]));
+        // Neighbouring cell untouched
+        fb.copy_rect((48, 112, 24, 56), &mut rect);
+        assert!(rect.chunks_exact(4).all(|px| px == [0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_geometry_follows_font() {
+        let font = Font::from_raw("8x8", &[0xFF; 2048]).unwrap();
+        assert_eq!(cell_size(&font), (24, 32));
+
+        let mut fb = Framebuffer::new(80, 50, cell_size(&font));
+        assert_eq!(fb.dimensions(), (1920, 1600));
+        let mut cache = GlyphCache::new(Rc::new(font), 16);
+        fb.draw_glyph(0, 1, cache.glyph(b'A', WHITE, BLUE));
+        assert_eq!(fb.take_dirty(), Some((0, 32, 24, 32)));
+    }
+
+    #[test]
+    fn test_nine_pixel_cells() {
+        let font = Font::ega().with_ninth_column();
+        assert_eq!(cell_size(&font), (27, 56));
+        let mut fb = Framebuffer::new(80, 25, cell_size(&font));
+        assert_eq!(fb.dimensions(), (2160, 1400));
+
+        // Horizontal line glyph: the 9th column continues the line
+        let mut glyph = Vec::new();
+        expand_glyph(&font, 0xC4, WHITE, BLUE, &mut glyph);
+        assert_eq!(glyph.len(), 14 * 27 * 4);
+        fb.draw_glyph(1, 0, &glyph);
+        let mut rect = Vec::new();
+        fb.copy_rect((27 + 24, 7 * 4, 3, 1), &mut rect);
+        assert!(rect.chunks_exact(4).all(|px| px == [255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_unscaled_glyphs() {
+        let font = Rc::new(Font::ega());
+        assert_eq!(scaled_cell_size(&font, (1, 1)), (8, 14));
+        let mut fb = Framebuffer::new(2, 1, scaled_cell_size(&font, (1, 1)));
+        assert_eq!(fb.dimensions(), (16, 14));
+
+        let mut cache = GlyphCache::with_scale(font, 16, 1);
+        assert_eq!(cache.glyph(0xDB, WHITE, BLUE).len(), 8 * 14 * 4);
+        fb.draw_glyph(1, 0, cache.glyph(0xDB, WHITE, BLUE));
+        assert_eq!(fb.take_dirty(), Some((8, 0, 8, 14)));
+        let mut rect = Vec::new();
+        fb.copy_rect((8, 0, 8, 14), &mut rect);
+        assert!(rect.chunks_exact(4).all(|px| px == [255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_draw_outside_is_ignored() {
+        let font = Font::ega();
+        let mut fb = Framebuffer::new(2, 2, cell_size(&font));
+        let mut glyph = Vec::new();
+        expand_glyph(&font, b'X', WHITE, BLUE, &mut glyph);
+        fb.draw_glyph(2, 0, &glyph);
+        assert_eq!(fb.take_dirty(), None);
+    }
+}
@@ -1,74 +1,141 @@
-/// Canvas renderer for terminal output.
-///
-/// Renders the terminal screen buffer to an HTML5 canvas with EGA font
-/// and proper aspect ratio correction (6x8 pixel scaling for 2x resolution).
-
+//! Canvas renderer for terminal output.
+//!
+//! Renders the terminal screen buffer to an HTML5 canvas with a bitmap font
+//! (EGA 8x14 by default) and aspect ratio correction (3x4 pixel scaling).
+//!
+//! Cells are rasterized into a Rust-side framebuffer through a glyph cache,
+//! and the changed area is copied to the canvas with one `putImageData`.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
 
-use crate::font;
+use crate::font::Font;
+use crate::palette::{ansi_color_to_rgb_tuple, Palette};
+use crate::raster::{cell_size, Framebuffer, GlyphCache, Rgb, DEFAULT_CACHE_CAPACITY};
 use crate::screen::Screen;
-use crate::scrollback::ScrollbackBuffer;
-
-/// Canvas dimensions (3x4 scaling per EGA pixel)
-pub const CANVAS_WIDTH: u32 = 1920;   // 80 * 8 * 3
-pub const CANVAS_HEIGHT: u32 = 1400;  // 25 * 14 * 4
+use crate::scrollback::{ScrollbackBuffer, TrueColors};
 
-/// Color palette type
-#[derive(Clone, Copy, PartialEq, Eq, Default)]
-pub enum Palette {
-    /// IBM 5153-accurate CGA colors
-    Cga,
-    /// Standard EGA/VGA colors (default)
-    #[default]
-    Vga,
-}
-
-impl Palette {
-    /// Parse palette from string (case-insensitive)
-    pub fn from_str(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "cga" => Palette::Cga,
-            _ => Palette::Vga,
-        }
-    }
+/// Canvas dimensions for a screen size and font (1920x1400 for 80x25 EGA).
+pub fn canvas_size(columns: usize, rows: usize, font: &Font) -> (u32, u32) {
+    let (cell_width, cell_height) = cell_size(font);
+    ((columns * cell_width) as u32, (rows * cell_height) as u32)
 }
 
 /// Terminal renderer that draws to a canvas.
 pub struct Renderer {
     context: CanvasRenderingContext2d,
-    palette: Palette,
+    palette: Cell<Palette>,
+    /// Blink phase: blinking text is hidden while false
+    blink_visible: Cell<bool>,
+    /// Blink phase of the last live screen render
+    blink_rendered: Cell<bool>,
+    /// Screen id and generation on the canvas (None = full redraw needed)
+    last_frame: Cell<Option<(u64, u64)>>,
+    glyphs: RefCell<GlyphCache>,
+    framebuffer: RefCell<Framebuffer>,
+    /// Reused buffer for copying the changed area out of the framebuffer
+    blit_buffer: RefCell<Vec<u8>>,
 }
 
 impl Renderer {
-    /// Create a new renderer for the given canvas with specified palette.
-    pub fn with_palette(canvas: &HtmlCanvasElement, palette: Palette) -> Result<Self, JsValue> {
+    /// Create a new renderer for the given canvas with specified palette and font.
+    pub fn new(canvas: &HtmlCanvasElement, palette: Palette, font: Rc<Font>) -> Result<Self, JsValue> {
         let context = canvas
             .get_context("2d")?
             .ok_or("Failed to get 2d context")?
             .dyn_into::<CanvasRenderingContext2d>()?;
 
-        Ok(Renderer { context, palette })
+        Ok(Renderer {
+            context,
+            palette: Cell::new(palette),
+            blink_visible: Cell::new(true),
+            blink_rendered: Cell::new(true),
+            last_frame: Cell::new(None),
+            glyphs: RefCell::new(GlyphCache::new(font, DEFAULT_CACHE_CAPACITY)),
+            framebuffer: RefCell::new(Framebuffer::new(0, 0, (0, 0))),
+            blit_buffer: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Change the color palette (takes effect on the next render).
+    pub fn set_palette(&self, palette: Palette) {
+        self.palette.set(palette);
+        self.last_frame.set(None);
+    }
+
+    /// Advance the blink phase (takes effect on the next render).
+    pub fn toggle_blink(&self) {
+        self.blink_visible.set(!self.blink_visible.get());
+    }
+
+    /// Make sure the framebuffer matches the screen size.
+    ///
+    /// Returns false if it had to be recreated (a full redraw is needed).
+    fn prepare_framebuffer(&self, screen: &Screen) -> bool {
+        let (columns, rows) = screen.dimensions();
+        let glyphs = self.glyphs.borrow();
+        let (canvas_width, canvas_height) = canvas_size(columns, rows, glyphs.font());
+        let mut framebuffer = self.framebuffer.borrow_mut();
+        if framebuffer.dimensions() == (canvas_width as usize, canvas_height as usize) {
+            return true;
+        }
+        *framebuffer = Framebuffer::new(columns, rows, cell_size(glyphs.font()));
+        false
+    }
+
+    /// Copy the area drawn since the last flush to the canvas.
+    fn flush(&self) -> Result<(), JsValue> {
+        let mut framebuffer = self.framebuffer.borrow_mut();
+        let Some(rect) = framebuffer.take_dirty() else {
+            return Ok(());
+        };
+        let mut buffer = self.blit_buffer.borrow_mut();
+        framebuffer.copy_rect(rect, &mut buffer);
+
+        let (x, y, width, height) = rect;
+        let image_data = ImageData::new_with_u8_clamped_array_and_sh(
+            wasm_bindgen::Clamped(&buffer),
+            width as u32,
+            height as u32,
+        )?;
+        self.context.put_image_data(&image_data, x as f64, y as f64)
     }
 
     /// Render the screen buffer to the canvas.
+    ///
+    /// Only cells changed since the last render are repainted (plus blinking
+    /// cells when the blink phase changed). A different screen, a palette
+    /// change, or a scrollback view forces a full redraw.
     pub fn render(&self, screen: &Screen) -> Result<(), JsValue> {
-        // Clear canvas to black
-        self.context.set_fill_style_str("#000000");
-        self.context.fill_rect(0.0, 0.0, CANVAS_WIDTH as f64, CANVAS_HEIGHT as f64);
-
-        // Render each character cell
+        let framebuffer_kept = self.prepare_framebuffer(screen);
+        let since = match self.last_frame.get() {
+            Some((id, generation)) if id == screen.id() && framebuffer_kept => Some(generation),
+            _ => None,
+        };
+        let blink_changed = self.blink_rendered.get() != self.blink_visible.get();
+
+        // Render each changed character cell
         let (width, height) = screen.dimensions();
         for y in 0..height {
             for x in 0..width {
                 if let Some(cell) = screen.get_cell(x, y) {
-                    self.render_cell(x, y, cell)?;
+                    let dirty = match since {
+                        Some(generation) => screen.is_dirty(x, y, generation) || (blink_changed && cell.blink),
+                        None => true,
+                    };
+                    if dirty {
+                        self.render_cell(x, y, cell);
+                    }
                 }
             }
         }
 
-        Ok(())
+        self.last_frame.set(Some((screen.id(), screen.generation())));
+        self.blink_rendered.set(self.blink_visible.get());
+        self.flush()
     }
 
     /// Render the screen with scrollback overlay if active.
@@ -85,88 +152,70 @@ impl Renderer {
             return self.render(screen);
         }
 
-        // Clear canvas to black
-        self.context.set_fill_style_str("#000000");
-        self.context.fill_rect(0.0, 0.0, CANVAS_WIDTH as f64, CANVAS_HEIGHT as f64);
+        // Full redraw; the next live render must repaint everything too
+        self.last_frame.set(None);
+        self.prepare_framebuffer(screen);
 
         // Render each line from the scrollback view
-        for y in 0..25 {
+        let (width, height) = screen.dimensions();
+        for y in 0..height {
             if let Some(cga_line) = scrollback.get_display_line(y, screen) {
-                self.render_cga_line(y, &cga_line)?;
+                let true_colors = scrollback.get_display_true_colors(y, screen);
+                self.render_cga_line(y, &cga_line, true_colors.as_ref(), screen.ice_colors());
             }
         }
 
         // Render "SCROLLBACK" indicators (unless in viewer mode)
         if scrollback.should_show_indicators() {
-            self.render_scrollback_indicator()?;
+            self.render_scrollback_indicator(width);
         }
 
-        Ok(())
+        self.flush()
     }
 
-    /// Render a single line from CGA format.
-    fn render_cga_line(&self, y: usize, cga_line: &[u8; 160]) -> Result<(), JsValue> {
-        for x in 0..80 {
-            let ch = cga_line[x * 2];
-            let attr = cga_line[x * 2 + 1];
+    /// Render a single line from CGA format, with the exact colors of any
+    /// true color cells.
+    ///
+    /// Without iCE colors, attribute bit 7 is the blink flag.
+    fn render_cga_line(&self, y: usize, cga_line: &[u8], true_colors: Option<&TrueColors>, ice_colors: bool) {
+        for (x, cga) in cga_line.chunks_exact(2).enumerate() {
+            let ch = cga[0];
+            let attr = cga[1];
             let fg = attr & 0x0F;
-            let bg = (attr >> 4) & 0x0F;
-            self.render_char_at(x, y, ch, fg, bg)?;
+            let mut bg = (attr >> 4) & 0x0F;
+            let mut hidden = false;
+            if !ice_colors && bg >= 8 {
+                bg -= 8;
+                hidden = !self.blink_visible.get();
+            }
+            let (fg_rgb, bg_rgb) = true_colors.and_then(|colors| colors.get(x).copied()).unwrap_or_default();
+            let bg = self.color(bg, bg_rgb);
+            let fg = if hidden { bg } else { self.color(fg, fg_rgb) };
+            self.draw_glyph(x, y, ch, fg, bg);
         }
-        Ok(())
     }
 
-    /// Render a single character at the specified position.
-    fn render_char_at(
-        &self,
-        x: usize,
-        y: usize,
-        ch: u8,
-        fg: u8,
-        bg: u8,
-    ) -> Result<(), JsValue> {
-        const SCALE_X: usize = 3;
-        const SCALE_Y: usize = 4;
-        const CELL_WIDTH: usize = font::FONT_WIDTH * SCALE_X;
-        const CELL_HEIGHT: usize = font::FONT_HEIGHT * SCALE_Y;
-
-        let px = x * CELL_WIDTH;
-        let py = y * CELL_HEIGHT;
-
-        let bitmap = font::get_char_bitmap(ch);
-        let fg_rgb = ansi_color_to_rgb_tuple(fg, self.palette);
-        let bg_rgb = ansi_color_to_rgb_tuple(bg, self.palette);
-
-        let mut pixel_data: Vec<u8> = Vec::with_capacity(CELL_WIDTH * CELL_HEIGHT * 4);
-
-        for font_y in 0..font::FONT_HEIGHT {
-            let scanline = bitmap[font_y];
-            for _ in 0..SCALE_Y {
-                for font_x in 0..font::FONT_WIDTH {
-                    let is_set = font::is_pixel_set(scanline, font_x as u8);
-                    let (r, g, b) = if is_set { fg_rgb } else { bg_rgb };
-                    for _ in 0..SCALE_X {
-                        pixel_data.push(r);
-                        pixel_data.push(g);
-                        pixel_data.push(b);
-                        pixel_data.push(255);
-                    }
-                }
-            }
-        }
+    /// Resolve a palette index, or the exact color of a true color cell.
+    fn color(&self, index: u8, rgb: Option<Rgb>) -> Rgb {
+        rgb.unwrap_or_else(|| ansi_color_to_rgb_tuple(index, self.palette.get()))
+    }
 
-        let image_data = ImageData::new_with_u8_clamped_array_and_sh(
-            wasm_bindgen::Clamped(&pixel_data),
-            CELL_WIDTH as u32,
-            CELL_HEIGHT as u32,
-        )?;
+    /// Render a single character at the specified position.
+    fn render_char_at(&self, x: usize, y: usize, ch: u8, fg: u8, bg: u8) {
+        let palette = self.palette.get();
+        self.draw_glyph(x, y, ch, ansi_color_to_rgb_tuple(fg, palette), ansi_color_to_rgb_tuple(bg, palette));
+    }
 
-        self.context.put_image_data(&image_data, px as f64, py as f64)?;
-        Ok(())
+    /// Draw a glyph into the framebuffer through the glyph cache.
+    fn draw_glyph(&self, x: usize, y: usize, ch: u8, fg: Rgb, bg: Rgb) {
+        let mut glyphs = self.glyphs.borrow_mut();
+        self.framebuffer
+            .borrow_mut()
+            .draw_glyph(x, y, glyphs.glyph(ch, fg, bg));
     }
 
     /// Render the "SCROLLBACK" indicator at top-left and top-right corners.
-    fn render_scrollback_indicator(&self) -> Result<(), JsValue> {
+    fn render_scrollback_indicator(&self, width: usize) {
         let indicator = ScrollbackBuffer::scrollback_indicator();
 
         // Render at top-left (columns 0-9)
@@ -175,128 +224,25 @@ impl Renderer {
             let attr = indicator[x * 2 + 1];
             let fg = attr & 0x0F;
             let bg = (attr >> 4) & 0x0F;
-            self.render_char_at(x, 0, ch, fg, bg)?;
+            self.render_char_at(x, 0, ch, fg, bg);
         }
 
-        // Render at top-right (columns 70-79)
+        // Render at top-right (last 10 columns)
+        let right = width.saturating_sub(10);
         for x in 0..10 {
             let ch = indicator[x * 2];
             let attr = indicator[x * 2 + 1];
             let fg = attr & 0x0F;
             let bg = (attr >> 4) & 0x0F;
-            self.render_char_at(70 + x, 0, ch, fg, bg)?;
+            self.render_char_at(right + x, 0, ch, fg, bg);
         }
-
-        Ok(())
     }
 
     /// Render a single character cell.
-    fn render_cell(&self, x: usize, y: usize, cell: &crate::screen::Cell) -> Result<(), JsValue> {
-        // Each character is 8x14 pixels, scaled to 24x56 (3x4) for aspect ratio correction
-        const SCALE_X: usize = 3;
-        const SCALE_Y: usize = 4;
-        const CELL_WIDTH: usize = font::FONT_WIDTH * SCALE_X;   // 24
-        const CELL_HEIGHT: usize = font::FONT_HEIGHT * SCALE_Y; // 56
-
-        let px = x * CELL_WIDTH;
-        let py = y * CELL_HEIGHT;
-
-        // Get font bitmap for this character
-        let bitmap = font::get_char_bitmap(cell.ch);
-
-        // Get foreground and background colors as RGB tuples
-        let fg_rgb = ansi_color_to_rgb_tuple(cell.fg, self.palette);
-        let bg_rgb = ansi_color_to_rgb_tuple(cell.bg, self.palette);
-
-        // Create ImageData for this cell (24x56 pixels, RGBA format)
-        let mut pixel_data: Vec<u8> = Vec::with_capacity(CELL_WIDTH * CELL_HEIGHT * 4);
-
-        // Render each scanline of the character
-        for font_y in 0..font::FONT_HEIGHT {
-            let scanline = bitmap[font_y];
-
-            // Each font scanline is rendered SCALE_Y times vertically
-            for _ in 0..SCALE_Y {
-                // Render each pixel in the scanline
-                for font_x in 0..font::FONT_WIDTH {
-                    let is_set = font::is_pixel_set(scanline, font_x as u8);
-                    let (r, g, b) = if is_set { fg_rgb } else { bg_rgb };
-
-                    // Each font pixel is rendered SCALE_X times horizontally
-                    for _ in 0..SCALE_X {
-                        pixel_data.push(r);
-                        pixel_data.push(g);
-                        pixel_data.push(b);
-                        pixel_data.push(255); // Alpha
-                    }
-                }
-            }
-        }
-
-        // Create ImageData and put it on the canvas
-        let image_data = ImageData::new_with_u8_clamped_array_and_sh(
-            wasm_bindgen::Clamped(&pixel_data),
-            CELL_WIDTH as u32,
-            CELL_HEIGHT as u32,
-        )?;
-
-        self.context.put_image_data(&image_data, px as f64, py as f64)?;
-
-        Ok(())
+    fn render_cell(&self, x: usize, y: usize, cell: &crate::screen::Cell) {
+        // Blinking text is drawn in the background color during the off phase
+        let bg = self.color(cell.bg, cell.bg_rgb);
+        let fg = if cell.blink && !self.blink_visible.get() { bg } else { self.color(cell.fg, cell.fg_rgb) };
+        self.draw_glyph(x, y, cell.ch, fg, bg);
     }
 }
-
-/// Convert ANSI color code (0-15) to RGB tuple (r, g, b).
-fn ansi_color_to_rgb_tuple(color: u8, palette: Palette) -> (u8, u8, u8) {
-    match palette {
-        Palette::Cga => ansi_color_cga(color),
-        Palette::Vga => ansi_color_vga(color),
-    }
-}
-
-/// IBM 5153-accurate CGA colors for authentic CRT appearance.
-fn ansi_color_cga(color: u8) -> (u8, u8, u8) {
-    match color {
-        0 => (0x00, 0x00, 0x00),  // Black
-        1 => (0xC4, 0x00, 0x00),  // Red
-        2 => (0x00, 0xC4, 0x00),  // Green
-        3 => (0xC4, 0x7E, 0x00),  // Brown
-        4 => (0x00, 0x00, 0xC4),  // Blue
-        5 => (0xC4, 0x00, 0xC4),  // Magenta
-        6 => (0x00, 0xC4, 0xC4),  // Cyan
-        7 => (0xC4, 0xC4, 0xC4),  // Light Gray
-        8 => (0x4E, 0x4E, 0x4E),  // Dark Gray
-        9 => (0xDC, 0x4E, 0x4E),  // Light Red
-        10 => (0x4E, 0xDC, 0x4E), // Light Green
-        11 => (0xF3, 0xF3, 0x4E), // Yellow
-        12 => (0x4E, 0x4E, 0xDC), // Light Blue
-        13 => (0xF3, 0x4E, 0xF3), // Light Magenta
-        14 => (0x4E, 0xF3, 0xF3), // Light Cyan
-        15 => (0xFF, 0xFF, 0xFF), // White
-        _ => (0xC4, 0xC4, 0xC4),  // Default to light gray
-    }
-}
-
-/// Standard VGA colors.
-fn ansi_color_vga(color: u8) -> (u8, u8, u8) {
-    match color {
-        0 => (0x00, 0x00, 0x00),  // Black
-        1 => (0xAA, 0x00, 0x00),  // Red
-        2 => (0x00, 0xAA, 0x00),  // Green
-        3 => (0xAA, 0x55, 0x00),  // Brown/Yellow
-        4 => (0x00, 0x00, 0xAA),  // Blue
-        5 => (0xAA, 0x00, 0xAA),  // Magenta
-        6 => (0x00, 0xAA, 0xAA),  // Cyan
-        7 => (0xAA, 0xAA, 0xAA),  // Light Gray
-        8 => (0x55, 0x55, 0x55),  // Dark Gray
-        9 => (0xFF, 0x55, 0x55),  // Light Red
-        10 => (0x55, 0xFF, 0x55), // Light Green
-        11 => (0xFF, 0xFF, 0x55), // Yellow
-        12 => (0x55, 0x55, 0xFF), // Light Blue
-        13 => (0xFF, 0x55, 0xFF), // Light Magenta
-        14 => (0x55, 0xFF, 0xFF), // Light Cyan
-        15 => (0xFF, 0xFF, 0xFF), // White
-        _ => (0xAA, 0xAA, 0xAA),  // Default to light gray
-    }
-}
-
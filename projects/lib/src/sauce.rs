@@ -0,0 +1,315 @@
+//! SAUCE (Standard Architecture for Universal Comment Extensions) metadata.
+//!
+//! Art files carry a 128-byte SAUCE record at the end of the file, optionally
+//! preceded by a COMNT block, both after a ^Z (0x1A) end-of-file marker.
+
+#[cfg(feature = "web")]
+use wasm_bindgen::prelude::*;
+
+/// End-of-file marker separating content from SAUCE metadata.
+pub const EOF_MARKER: u8 = 0x1A;
+
+/// Size of the SAUCE record.
+const RECORD_SIZE: usize = 128;
+
+/// Size of the COMNT block header ("COMNT").
+const COMMENT_ID_SIZE: usize = 5;
+
+/// Size of each comment line.
+const COMMENT_LINE_SIZE: usize = 64;
+
+/// SAUCE DataType: character-based (ANSI, ASCII, ...).
+pub const DATA_TYPE_CHARACTER: u8 = 1;
+/// SAUCE DataType: raw binary text (.BIN).
+pub const DATA_TYPE_BINARY_TEXT: u8 = 5;
+/// SAUCE DataType: XBin.
+pub const DATA_TYPE_XBIN: u8 = 6;
+
+/// Letter spacing requested by the ANSiFlags LS bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LetterSpacing {
+    #[default]
+    Legacy,
+    /// 8 pixel cells
+    EightPixel,
+    /// 9 pixel cells (VGA)
+    NinePixel,
+}
+
+/// Aspect ratio requested by the ANSiFlags AR bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AspectRatio {
+    #[default]
+    Legacy,
+    /// Stretch pixels vertically as on a CRT
+    Stretch,
+    /// Square pixels
+    Square,
+}
+
+/// Parsed SAUCE record.
+#[cfg_attr(feature = "web", wasm_bindgen(getter_with_clone))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sauce {
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    /// Creation date as CCYYMMDD
+    pub date: String,
+    /// Original file size (excluding SAUCE)
+    #[cfg_attr(feature = "web", wasm_bindgen(js_name = fileSize))]
+    pub file_size: u32,
+    #[cfg_attr(feature = "web", wasm_bindgen(js_name = dataType))]
+    pub data_type: u8,
+    #[cfg_attr(feature = "web", wasm_bindgen(js_name = fileType))]
+    pub file_type: u8,
+    #[cfg_attr(feature = "web", wasm_bindgen(js_name = tInfo1))]
+    pub tinfo1: u16,
+    #[cfg_attr(feature = "web", wasm_bindgen(js_name = tInfo2))]
+    pub tinfo2: u16,
+    #[cfg_attr(feature = "web", wasm_bindgen(js_name = tInfo3))]
+    pub tinfo3: u16,
+    #[cfg_attr(feature = "web", wasm_bindgen(js_name = tInfo4))]
+    pub tinfo4: u16,
+    /// ANSiFlags (TFlags)
+    pub flags: u8,
+    /// Font name (TInfoS), e.g. "IBM VGA"
+    #[cfg_attr(feature = "web", wasm_bindgen(js_name = fontName))]
+    pub font_name: String,
+    pub comments: Vec<String>,
+}
+
+#[cfg_attr(feature = "web", wasm_bindgen)]
+impl Sauce {
+    /// Character width for character-based files (TInfo1, or FileType × 2
+    /// for BIN), if specified.
+    pub fn width(&self) -> Option<u16> {
+        let width = match self.data_type {
+            DATA_TYPE_BINARY_TEXT => self.file_type as u16 * 2,
+            _ => self.tinfo1,
+        };
+        (self.is_character_data() && width > 0).then_some(width)
+    }
+
+    /// Character height for character-based files (TInfo2), if specified.
+    pub fn height(&self) -> Option<u16> {
+        (self.is_character_data() && self.tinfo2 > 0).then_some(self.tinfo2)
+    }
+
+    /// Whether the iCE colors flag is set (blink bit selects bright background).
+    #[cfg_attr(feature = "web", wasm_bindgen(js_name = iceColors))]
+    pub fn ice_colors(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+}
+
+impl Sauce {
+    /// Whether the file is character-based (ANSI, BIN, XBin, ...).
+    fn is_character_data(&self) -> bool {
+        matches!(
+            self.data_type,
+            DATA_TYPE_CHARACTER | DATA_TYPE_BINARY_TEXT | DATA_TYPE_XBIN
+        )
+    }
+
+    /// Letter spacing from the ANSiFlags LS bits.
+    pub fn letter_spacing(&self) -> LetterSpacing {
+        match (self.flags >> 1) & 0x03 {
+            1 => LetterSpacing::EightPixel,
+            2 => LetterSpacing::NinePixel,
+            _ => LetterSpacing::Legacy,
+        }
+    }
+
+    /// Aspect ratio from the ANSiFlags AR bits.
+    pub fn aspect_ratio(&self) -> AspectRatio {
+        match (self.flags >> 3) & 0x03 {
+            1 => AspectRatio::Stretch,
+            2 => AspectRatio::Square,
+            _ => AspectRatio::Legacy,
+        }
+    }
+}
+
+/// Parse the SAUCE record at the end of a file, if present.
+pub fn parse(content: &[u8]) -> Option<Sauce> {
+    if content.len() < RECORD_SIZE {
+        return None;
+    }
+    let record_start = content.len() - RECORD_SIZE;
+    let record = &content[record_start..];
+    if &record[0..5] != b"SAUCE" {
+        return None;
+    }
+
+    let u16_at = |offset: usize| u16::from_le_bytes([record[offset], record[offset + 1]]);
+
+    let comment_lines = record[104] as usize;
+    let comments = parse_comments(&content[..record_start], comment_lines);
+
+    Some(Sauce {
+        title: field_to_string(&record[7..42]),
+        author: field_to_string(&record[42..62]),
+        group: field_to_string(&record[62..82]),
+        date: field_to_string(&record[82..90]),
+        file_size: u32::from_le_bytes([record[90], record[91], record[92], record[93]]),
+        data_type: record[94],
+        file_type: record[95],
+        tinfo1: u16_at(96),
+        tinfo2: u16_at(98),
+        tinfo3: u16_at(100),
+        tinfo4: u16_at(102),
+        flags: record[105],
+        font_name: field_to_string(&record[106..128]),
+        comments,
+    })
+}
+
+/// Content without the SAUCE record, its COMNT block, and the EOF marker
+/// before them. Content without SAUCE is returned whole.
+pub fn strip(content: &[u8]) -> &[u8] {
+    let Some(sauce) = parse(content) else {
+        return content;
+    };
+    let mut end = content.len() - RECORD_SIZE;
+    if !sauce.comments.is_empty() {
+        end -= COMMENT_ID_SIZE + sauce.comments.len() * COMMENT_LINE_SIZE;
+    }
+    if end > 0 && content[end - 1] == EOF_MARKER {
+        end -= 1;
+    }
+    &content[..end]
+}
+
+/// Parse the COMNT block immediately preceding the SAUCE record.
+fn parse_comments(before_record: &[u8], lines: usize) -> Vec<String> {
+    let block_size = COMMENT_ID_SIZE + lines * COMMENT_LINE_SIZE;
+    if lines == 0 || before_record.len() < block_size {
+        return Vec::new();
+    }
+    let block = &before_record[before_record.len() - block_size..];
+    if &block[..COMMENT_ID_SIZE] != b"COMNT" {
+        return Vec::new();
+    }
+    block[COMMENT_ID_SIZE..]
+        .chunks(COMMENT_LINE_SIZE)
+        .map(field_to_string)
+        .collect()
+}
+
+/// Convert a space/NUL padded CP437 field to a trimmed string.
+fn field_to_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    field[..end]
+        .iter()
+        .map(|&b| crate::cp437::to_unicode(b))
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+/// Parse SAUCE metadata from file content.
+///
+/// Returns undefined if the file has no SAUCE record.
+///
+/// # Example (JavaScript)
+/// ```javascript
+/// const sauce = parseSauce(bytes);
+/// if (sauce) console.log(`${sauce.title} by ${sauce.author}`);
+/// ```
+#[cfg_attr(feature = "web", wasm_bindgen(js_name = parseSauce))]
+pub fn parse_sauce(content: &[u8]) -> Option<Sauce> {
+    parse(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a SAUCE record with the given fields.
+    fn build_record(title: &str, tinfo1: u16, tinfo2: u16, flags: u8, font: &str, comments: u8) -> Vec<u8> {
+        fn pad(s: &str, len: usize) -> Vec<u8> {
+            let mut field = s.as_bytes().to_vec();
+            field.resize(len, b' ');
+            field
+        }
+        let mut record = b"SAUCE00".to_vec();
+        record.extend(pad(title, 35));
+        record.extend(pad("Artist", 20));
+        record.extend(pad("Group", 20));
+        record.extend(b"19960315");
+        record.extend(1234u32.to_le_bytes());
+        record.push(DATA_TYPE_CHARACTER);
+        record.push(1); // ANSi
+        record.extend(tinfo1.to_le_bytes());
+        record.extend(tinfo2.to_le_bytes());
+        record.extend(0u16.to_le_bytes());
+        record.extend(0u16.to_le_bytes());
+        record.push(comments);
+        record.push(flags);
+        let mut font_field = font.as_bytes().to_vec();
+        font_field.resize(22, 0);
+        record.extend(font_field);
+        assert_eq!(record.len(), RECORD_SIZE);
+        record
+    }
+
+    #[test]
+    fn test_parse_record() {
+        let mut file = b"\x1b[1;31mHello\x1a".to_vec();
+        file.extend(build_record("My Art", 80, 50, 0x01 | (2 << 1) | (1 << 3), "IBM VGA", 0));
+
+        let sauce = parse(&file).unwrap();
+        assert_eq!(sauce.title, "My Art");
+        assert_eq!(sauce.author, "Artist");
+        assert_eq!(sauce.group, "Group");
+        assert_eq!(sauce.date, "19960315");
+        assert_eq!(sauce.file_size, 1234);
+        assert_eq!(sauce.width(), Some(80));
+        assert_eq!(sauce.height(), Some(50));
+        assert!(sauce.ice_colors());
+        assert_eq!(sauce.letter_spacing(), LetterSpacing::NinePixel);
+        assert_eq!(sauce.aspect_ratio(), AspectRatio::Stretch);
+        assert_eq!(sauce.font_name, "IBM VGA");
+        assert!(sauce.comments.is_empty());
+    }
+
+    #[test]
+    fn test_parse_comments() {
+        let mut file = b"art\x1a".to_vec();
+        file.extend(b"COMNT");
+        let mut line = b"First comment".to_vec();
+        line.resize(COMMENT_LINE_SIZE, b' ');
+        file.extend(&line);
+        let mut line = b"Second".to_vec();
+        line.resize(COMMENT_LINE_SIZE, b' ');
+        file.extend(&line);
+        file.extend(build_record("", 0, 0, 0, "", 2));
+
+        let sauce = parse(&file).unwrap();
+        assert_eq!(sauce.comments, vec!["First comment", "Second"]);
+        assert_eq!(sauce.width(), None);
+        assert!(!sauce.ice_colors());
+        assert_eq!(strip(&file), b"art");
+    }
+
+    #[test]
+    fn test_binary_text_width() {
+        let mut file = vec![0x1a; 4];
+        let mut record = build_record("", 0, 0, 0, "", 0);
+        record[94] = DATA_TYPE_BINARY_TEXT;
+        record[95] = 80;
+        file.extend(record);
+
+        assert_eq!(parse(&file).unwrap().width(), Some(160));
+        // Only the EOF marker just before SAUCE is removed
+        assert_eq!(strip(&file), [0x1a; 3]);
+    }
+
+    #[test]
+    fn test_no_sauce() {
+        assert_eq!(parse(b"plain text"), None);
+        assert_eq!(strip(b"plain text"), b"plain text");
+        assert_eq!(parse(&[b' '; 200]), None);
+    }
+}
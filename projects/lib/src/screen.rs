@@ -1,4 +1,25 @@
-/// Screen buffer for 80x25 text mode terminal.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::palette::dos_color;
+use crate::raster::Rgb;
+
+/// Source of unique screen ids, so a renderer can tell a replaced screen
+/// from a modified one.
+static NEXT_SCREEN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Default number of text columns.
+pub const DEFAULT_COLUMNS: usize = 80;
+
+/// Default number of text rows.
+pub const DEFAULT_ROWS: usize = 25;
+
+/// Maximum number of text columns.
+pub const MAX_COLUMNS: usize = 255;
+
+/// Maximum number of rows a document screen grows to.
+pub const MAX_DOCUMENT_ROWS: usize = 10_000;
+
+/// Screen buffer for a text mode terminal (80x25 by default).
 ///
 /// Represents the complete terminal state including character cells,
 /// colors, and attributes.
@@ -8,6 +29,16 @@ pub struct Screen {
     cells: Vec<Cell>,
     cursor_x: usize,
     cursor_y: usize,
+    /// iCE colors: the blink bit selects a bright background instead of blinking
+    ice_colors: bool,
+    /// Grows downward (up to `MAX_DOCUMENT_ROWS`) instead of scrolling
+    document: bool,
+    /// Unique id of this screen buffer
+    id: u64,
+    /// Incremented on every cell change
+    generation: u64,
+    /// Generation at which each cell last changed
+    cell_generations: Vec<u64>,
 }
 
 /// A single character cell in the terminal.
@@ -16,6 +47,9 @@ pub struct Cell {
     pub ch: u8,        // CP437 character code
     pub fg: u8,        // Foreground color (0-15)
     pub bg: u8,        // Background color (0-15)
+    pub blink: bool,   // Blinking foreground (non-iCE mode only)
+    pub fg_rgb: Option<Rgb>,  // Exact 24-bit foreground color, if any
+    pub bg_rgb: Option<Rgb>,  // Exact 24-bit background color, if any
 }
 
 impl Default for Cell {
@@ -24,22 +58,129 @@ impl Default for Cell {
             ch: b' ',
             fg: 7,  // White
             bg: 0,  // Black
+            blink: false,
+            fg_rgb: None,
+            bg_rgb: None,
+        }
+    }
+}
+
+impl Cell {
+    /// Cell from a DOS text mode character and attribute byte (colors in DOS
+    /// order). Without iCE colors, attribute bit 7 is the blink flag.
+    pub fn from_attribute(ch: u8, attr: u8, ice_colors: bool) -> Self {
+        let fg = dos_color(attr & 0x0F);
+        let bg = dos_color(attr >> 4);
+        Cell {
+            ch,
+            fg,
+            bg: if ice_colors { bg } else { bg & 0x07 },
+            blink: !ice_colors && attr & 0x80 != 0,
+            ..Cell::default()
         }
     }
 }
 
+impl Default for Screen {
+    /// An 80x25 screen.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Screen {
     /// Create a new 80x25 screen buffer.
     pub fn new() -> Self {
+        Self::with_size(DEFAULT_COLUMNS, DEFAULT_ROWS)
+    }
+
+    /// Create a screen buffer with the given number of columns and rows
+    /// (80x43, 80x50, 132x25, ...). The width is limited to `MAX_COLUMNS`.
+    pub fn with_size(width: usize, height: usize) -> Self {
+        let width = width.clamp(1, MAX_COLUMNS);
+        let height = height.max(1);
         Screen {
-            width: 80,
-            height: 25,
-            cells: vec![Cell::default(); 80 * 25],
+            width,
+            height,
+            cells: vec![Cell::default(); width * height],
             cursor_x: 0,
             cursor_y: 0,
+            ice_colors: true,
+            document: false,
+            id: NEXT_SCREEN_ID.fetch_add(1, Ordering::Relaxed),
+            generation: 1,
+            cell_generations: vec![1; width * height],
         }
     }
 
+    /// Create a document screen: one row that grows as the cursor moves
+    /// down, so a whole art file can be drawn without scrolling.
+    pub fn document(width: usize) -> Self {
+        Screen {
+            document: true,
+            ..Self::with_size(width, 1)
+        }
+    }
+
+    /// Rows the cursor can reach: the height, or `MAX_DOCUMENT_ROWS` for a
+    /// document screen.
+    pub fn max_rows(&self) -> usize {
+        if self.document {
+            MAX_DOCUMENT_ROWS
+        } else {
+            self.height
+        }
+    }
+
+    /// Grow a document screen to at least `rows` rows.
+    fn grow(&mut self, rows: usize) {
+        if rows <= self.height {
+            return;
+        }
+        let start = self.cells.len();
+        self.height = rows;
+        self.cells.resize(rows * self.width, Cell::default());
+        self.cell_generations.resize(rows * self.width, 0);
+        self.touch(start..self.cells.len());
+    }
+
+    /// Unique id of this screen buffer.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Current change generation (increases whenever a cell changes).
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Whether the cell at the given position changed after `generation`.
+    pub fn is_dirty(&self, x: usize, y: usize, generation: u64) -> bool {
+        x < self.width && y < self.height && self.cell_generations[y * self.width + x] > generation
+    }
+
+    /// Mark a range of cell indices as changed.
+    fn touch(&mut self, range: std::ops::Range<usize>) {
+        self.generation += 1;
+        let generation = self.generation;
+        self.cell_generations[range].fill(generation);
+    }
+
+    /// Whether iCE colors are enabled (bright backgrounds instead of blink).
+    pub fn ice_colors(&self) -> bool {
+        self.ice_colors
+    }
+
+    /// Switch between iCE colors and real blinking (ESC[?33h / ESC[?33l).
+    pub fn set_ice_colors(&mut self, enabled: bool) {
+        self.ice_colors = enabled;
+    }
+
+    /// Whether any cell on screen is blinking.
+    pub fn has_blinking_cells(&self) -> bool {
+        self.cells.iter().any(|cell| cell.blink)
+    }
+
     /// Get a cell at the specified position.
     pub fn get_cell(&self, x: usize, y: usize) -> Option<&Cell> {
         if x < self.width && y < self.height {
@@ -52,7 +193,9 @@ impl Screen {
     /// Set a cell at the specified position.
     pub fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
         if x < self.width && y < self.height {
-            self.cells[y * self.width + x] = cell;
+            let idx = y * self.width + x;
+            self.cells[idx] = cell;
+            self.touch(idx..idx + 1);
         }
     }
 
@@ -61,23 +204,47 @@ impl Screen {
         (self.cursor_x, self.cursor_y)
     }
 
-    /// Set cursor position.
+    /// Set cursor position. A document screen grows to include the row.
     pub fn set_cursor(&mut self, x: usize, y: usize) {
         self.cursor_x = x.min(self.width - 1);
-        self.cursor_y = y.min(self.height - 1);
+        self.cursor_y = y.min(self.max_rows() - 1);
+        self.grow(self.cursor_y + 1);
     }
 
-    /// Clear the screen with specified background color.
-    pub fn clear_with_bg(&mut self, bg: u8) {
+    /// Clear the screen with specified background color (and exact color in
+    /// true color mode). A document screen keeps its height, so rows drawn
+    /// before the clear stay in place (blank) and the art can draw over them.
+    pub fn clear_with_bg(&mut self, bg: u8, bg_rgb: Option<Rgb>) {
         for cell in &mut self.cells {
-            cell.ch = b' ';
-            cell.fg = 7;
-            cell.bg = bg;
+            *cell = Cell { bg, bg_rgb, ..Cell::default() };
         }
+        self.touch(0..self.cells.len());
         self.cursor_x = 0;
         self.cursor_y = 0;
     }
 
+    /// Erase an inclusive range of cells in reading order.
+    ///
+    /// Cells from `(x1, y1)` through `(x2, y2)` are reset to spaces with the
+    /// given background color (ANSI.SYS behavior). The cursor is not moved.
+    pub fn erase_range(
+        &mut self,
+        (x1, y1): (usize, usize),
+        (x2, y2): (usize, usize),
+        bg: u8,
+        bg_rgb: Option<Rgb>,
+    ) {
+        let start = y1 * self.width + x1.min(self.width - 1);
+        let end = (y2 * self.width + x2.min(self.width - 1)).min(self.cells.len() - 1);
+        if start > end {
+            return;
+        }
+        for cell in &mut self.cells[start..=end] {
+            *cell = Cell { bg, bg_rgb, ..Cell::default() };
+        }
+        self.touch(start..end + 1);
+    }
+
     /// Get screen dimensions.
     pub fn dimensions(&self) -> (usize, usize) {
         (self.width, self.height)
@@ -99,6 +266,7 @@ impl Screen {
         for x in 0..self.width {
             self.cells[bottom_start + x] = Cell::default();
         }
+        self.touch(0..self.cells.len());
     }
 
     /// Get a full line of cells at the specified row.
@@ -112,4 +280,162 @@ impl Screen {
         let end = start + self.width;
         Some(self.cells[start..end].to_vec())
     }
+
+    /// Characters of a row as a string, one char per byte value.
+    #[cfg(test)]
+    pub(crate) fn row_text(&self, y: usize) -> String {
+        (0..self.width).map(|x| self.get_cell(x, y).unwrap().ch as char).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fill the screen with a marker character so erased cells stand out.
+    fn filled_screen() -> Screen {
+        let mut screen = Screen::new();
+        for y in 0..25 {
+            for x in 0..80 {
+                screen.set_cell(x, y, Cell { ch: b'X', fg: 15, bg: 1, blink: true, ..Cell::default() });
+            }
+        }
+        screen
+    }
+
+    fn is_erased(screen: &Screen, x: usize, y: usize, bg: u8) -> bool {
+        let cell = screen.get_cell(x, y).unwrap();
+        cell.ch == b' ' && cell.fg == 7 && cell.bg == bg && !cell.blink
+    }
+
+    #[test]
+    fn test_erase_range_within_line() {
+        let mut screen = filled_screen();
+        screen.erase_range((10, 5), (79, 5), 4, None);
+        assert!(!is_erased(&screen, 9, 5, 4));
+        assert!((10..80).all(|x| is_erased(&screen, x, 5, 4)));
+        assert!(!is_erased(&screen, 0, 6, 4));
+        assert!(!is_erased(&screen, 79, 4, 4));
+    }
+
+    #[test]
+    fn test_erase_range_spans_lines() {
+        let mut screen = filled_screen();
+        screen.erase_range((40, 10), (79, 24), 0, None);
+        assert!(!is_erased(&screen, 39, 10, 0));
+        assert!(is_erased(&screen, 40, 10, 0));
+        assert!((0..80).all(|x| is_erased(&screen, x, 11, 0)));
+        assert!(is_erased(&screen, 79, 24, 0));
+        assert!(!is_erased(&screen, 79, 9, 0));
+    }
+
+    #[test]
+    fn test_erase_range_to_cursor() {
+        let mut screen = filled_screen();
+        screen.erase_range((0, 0), (5, 2), 2, None);
+        assert!(is_erased(&screen, 0, 0, 2));
+        assert!(is_erased(&screen, 79, 1, 2));
+        assert!(is_erased(&screen, 5, 2, 2));
+        assert!(!is_erased(&screen, 6, 2, 2));
+    }
+
+    #[test]
+    fn test_erase_range_keeps_cursor() {
+        let mut screen = filled_screen();
+        screen.set_cursor(12, 7);
+        screen.erase_range((0, 7), (79, 7), 0, None);
+        assert_eq!(screen.cursor_pos(), (12, 7));
+    }
+
+    #[test]
+    fn test_with_size() {
+        let mut screen = Screen::with_size(132, 50);
+        assert_eq!(screen.dimensions(), (132, 50));
+        screen.set_cursor(200, 60);
+        assert_eq!(screen.cursor_pos(), (131, 49));
+        screen.erase_range((0, 49), (131, 49), 4, None);
+        assert!(is_erased(&screen, 131, 49, 4));
+    }
+
+    #[test]
+    fn test_dirty_tracking() {
+        let mut screen = Screen::new();
+        let rendered = screen.generation();
+        assert!(!screen.is_dirty(0, 0, rendered));
+
+        screen.set_cell(3, 4, Cell { ch: b'A', ..Cell::default() });
+        assert!(screen.is_dirty(3, 4, rendered));
+        assert!(!screen.is_dirty(4, 4, rendered));
+
+        let rendered = screen.generation();
+        screen.erase_range((0, 10), (79, 10), 0, None);
+        assert!((0..80).all(|x| screen.is_dirty(x, 10, rendered)));
+        assert!(!screen.is_dirty(3, 4, rendered));
+
+        let rendered = screen.generation();
+        screen.scroll_up();
+        assert!(screen.is_dirty(0, 0, rendered));
+        assert!(screen.is_dirty(79, 24, rendered));
+    }
+
+    #[test]
+    fn test_screens_have_unique_ids() {
+        assert_ne!(Screen::new().id(), Screen::new().id());
+    }
+
+    #[test]
+    fn test_erase_keeps_true_color_background() {
+        let mut screen = filled_screen();
+        screen.erase_range((0, 0), (79, 0), 4, Some((1, 2, 3)));
+        let cell = screen.get_cell(5, 0).unwrap();
+        assert_eq!((cell.bg, cell.bg_rgb, cell.fg_rgb), (4, Some((1, 2, 3)), None));
+
+        screen.clear_with_bg(0, None);
+        assert_eq!(screen.get_cell(5, 0).unwrap().bg_rgb, None);
+    }
+
+    #[test]
+    fn test_cell_from_attribute() {
+        let cell = Cell::from_attribute(b'A', 0x9C, true);
+        assert_eq!((cell.fg, cell.bg, cell.blink), (9, 12, false));
+        let cell = Cell::from_attribute(b'A', 0x9C, false);
+        assert_eq!((cell.fg, cell.bg, cell.blink), (9, 4, true));
+        let cell = Cell::from_attribute(b'B', 0x1E, false);
+        assert_eq!((cell.fg, cell.bg, cell.blink), (11, 4, false));
+    }
+
+    #[test]
+    fn test_erase_range_empty() {
+        let mut screen = filled_screen();
+        screen.erase_range((10, 3), (5, 3), 0, None);
+        assert!((0..80).all(|x| !is_erased(&screen, x, 3, 0)));
+    }
+
+    #[test]
+    fn test_document_grows() {
+        let mut screen = Screen::document(80);
+        assert_eq!(screen.dimensions(), (80, 1));
+
+        screen.set_cursor(3, 40);
+        assert_eq!(screen.dimensions(), (80, 41));
+        assert!(screen.get_cell(0, 40).is_some());
+        screen.set_cursor(0, 0);
+        assert_eq!(screen.dimensions(), (80, 41));
+
+        screen.set_cursor(0, usize::MAX);
+        assert_eq!(screen.cursor_pos(), (0, MAX_DOCUMENT_ROWS - 1));
+
+    }
+
+    #[test]
+    fn test_document_clear_keeps_height() {
+        let mut screen = Screen::document(80);
+        screen.set_cursor(5, 30);
+        screen.set_cell(5, 30, Cell { ch: b'A', ..Cell::default() });
+        screen.clear_with_bg(4, None);
+        assert_eq!(screen.dimensions(), (80, 31));
+        assert_eq!(screen.cursor_pos(), (0, 0));
+        let cell = screen.get_cell(5, 30).unwrap();
+        assert_eq!((cell.ch, cell.bg), (b' ', 4));
+    }
 }
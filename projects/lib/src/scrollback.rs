@@ -1,21 +1,23 @@
-/// Scrollback buffer for terminal history.
-///
-/// Stores terminal history in CGA-compatible format (2 bytes per character)
-/// and manages scrollback viewing mode.
+//! Scrollback buffer for terminal history.
+//!
+//! Stores terminal history in CGA-compatible format (2 bytes per character)
+//! and manages scrollback viewing mode.
 
-use crate::screen::{Cell, Screen};
+use crate::raster::Rgb;
+use crate::screen::{Cell, Screen, DEFAULT_COLUMNS, DEFAULT_ROWS};
 
 /// Default maximum number of lines to retain in scrollback history.
 pub const DEFAULT_MAX_LINES: usize = 5000;
 
-/// Width of the terminal in characters.
-const SCREEN_WIDTH: usize = 80;
-
-/// Height of the terminal in characters.
-const SCREEN_HEIGHT: usize = 25;
+/// Exact (foreground, background) colors of a line's cells, kept alongside
+/// the CGA bytes for lines with true color cells.
+pub type TrueColors = Vec<(Option<Rgb>, Option<Rgb>)>;
 
-/// Bytes per line in CGA format (80 chars * 2 bytes).
-const LINE_BYTES: usize = SCREEN_WIDTH * 2;
+/// Where a displayed row comes from.
+enum DisplaySource {
+    History(usize),
+    Screen(usize),
+}
 
 /// Scrollback buffer storing terminal history in CGA format.
 ///
@@ -23,11 +25,21 @@ const LINE_BYTES: usize = SCREEN_WIDTH * 2;
 /// Each character is stored as 2 bytes:
 /// - Byte 0: Character code (CP437)
 /// - Byte 1: Attribute byte
-///   - Bits 7-4: Background color (0-15)
+///   - Bits 7-4: Background color (0-15); in blink mode bit 7 is the
+///     blink flag and bits 6-4 the background color (0-7), as on real CGA
 ///   - Bits 3-0: Foreground color (0-15)
+///
+/// Exact colors of true color cells are stored separately, only for the
+/// lines that have them.
 pub struct ScrollbackBuffer {
-    /// History buffer: Vec of lines, each line is 160 bytes (80 chars * 2 bytes)
-    history: Vec<[u8; LINE_BYTES]>,
+    /// History buffer: Vec of lines, each line is 2 bytes per column
+    history: Vec<Vec<u8>>,
+    /// Exact colors for each history line that has true color cells
+    true_colors: Vec<Option<TrueColors>>,
+    /// Screen width in characters
+    width: usize,
+    /// Screen height in characters (one page)
+    height: usize,
     /// Maximum history lines to retain
     max_lines: usize,
     /// Whether scrollback mode is active
@@ -45,15 +57,18 @@ pub struct ScrollbackBuffer {
 }
 
 impl ScrollbackBuffer {
-    /// Create a new scrollback buffer with the default maximum lines.
+    /// Create a new scrollback buffer for an 80x25 screen with the default maximum lines.
     pub fn new() -> Self {
-        Self::with_max_lines(DEFAULT_MAX_LINES)
+        Self::with_size(DEFAULT_MAX_LINES, DEFAULT_COLUMNS, DEFAULT_ROWS)
     }
 
-    /// Create a new scrollback buffer with a specified maximum lines.
-    pub fn with_max_lines(max_lines: usize) -> Self {
+    /// Create a new scrollback buffer for a screen of the given size.
+    pub fn with_size(max_lines: usize, width: usize, height: usize) -> Self {
         ScrollbackBuffer {
             history: Vec::new(),
+            true_colors: Vec::new(),
+            width,
+            height,
             max_lines,
             active: false,
             keyboard_entry: false,
@@ -68,7 +83,8 @@ impl ScrollbackBuffer {
     fn cell_to_cga(cell: &Cell) -> [u8; 2] {
         // Byte 0: character
         // Byte 1: attribute (bg in high nibble, fg in low nibble)
-        let attr = ((cell.bg & 0x0F) << 4) | (cell.fg & 0x0F);
+        let bg = if cell.blink { cell.bg | 0x08 } else { cell.bg };
+        let attr = ((bg & 0x0F) << 4) | (cell.fg & 0x0F);
         [cell.ch, attr]
     }
 
@@ -79,20 +95,33 @@ impl ScrollbackBuffer {
             ch: cga[0],
             fg: cga[1] & 0x0F,
             bg: (cga[1] >> 4) & 0x0F,
+            ..Cell::default()
+        }
+    }
+
+    /// Exact colors of a line, if any cell has them.
+    fn cells_true_colors(&self, cells: &[Cell]) -> Option<TrueColors> {
+        let cells = &cells[..cells.len().min(self.width)];
+        if !cells.iter().any(|cell| cell.fg_rgb.is_some() || cell.bg_rgb.is_some()) {
+            return None;
         }
+        let mut colors: TrueColors = cells.iter().map(|cell| (cell.fg_rgb, cell.bg_rgb)).collect();
+        colors.resize(self.width, (None, None));
+        Some(colors)
     }
 
     /// Push a single line to the history buffer.
     ///
-    /// The line should be exactly SCREEN_WIDTH cells.
+    /// The line should be exactly one screen width of cells.
     pub fn push_line(&mut self, cells: &[Cell]) {
-        let mut line = [0u8; LINE_BYTES];
-        for (i, cell) in cells.iter().take(SCREEN_WIDTH).enumerate() {
+        let mut line = vec![0u8; self.width * 2];
+        for (i, cell) in cells.iter().take(self.width).enumerate() {
             let cga = Self::cell_to_cga(cell);
             line[i * 2] = cga[0];
             line[i * 2 + 1] = cga[1];
         }
         self.history.push(line);
+        self.true_colors.push(self.cells_true_colors(cells));
 
         // For mouse mode with offset > 0 (not at bottom), increment offset to keep view sticky
         // This makes the viewport stay at the same position as new content comes in
@@ -104,6 +133,7 @@ impl ScrollbackBuffer {
         // Trim if over max_lines
         if self.history.len() > self.max_lines {
             self.history.remove(0);
+            self.true_colors.remove(0);
             // For keyboard mode, adjust absolute position when history is trimmed
             if self.keyboard_entry && self.viewport_position > 0 {
                 self.viewport_position = self.viewport_position.saturating_sub(1);
@@ -121,14 +151,11 @@ impl ScrollbackBuffer {
     ///
     /// Called before a screen clear to preserve the display.
     pub fn push_screen(&mut self, screen: &Screen) {
-        for y in 0..SCREEN_HEIGHT {
-            let mut cells = Vec::with_capacity(SCREEN_WIDTH);
-            for x in 0..SCREEN_WIDTH {
-                if let Some(cell) = screen.get_cell(x, y) {
-                    cells.push(*cell);
-                }
+        let (_, height) = screen.dimensions();
+        for y in 0..height {
+            if let Some(cells) = screen.get_line(y) {
+                self.push_line(&cells);
             }
-            self.push_line(&cells);
         }
     }
 
@@ -200,6 +227,13 @@ impl ScrollbackBuffer {
         self.viewer_mode = false;
     }
 
+    /// Discard all history and leave scrollback mode.
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.true_colors.clear();
+        self.exit_scrollback();
+    }
+
     /// Start animated exit - scrolls to bottom over time.
     /// Returns true if animation started, false if already at bottom or not active.
     pub fn start_animated_exit(&mut self) -> bool {
@@ -261,8 +295,8 @@ impl ScrollbackBuffer {
             self.animating_exit = false;
             // Switch to keyboard mode at current position
             // Convert mouse-style offset to keyboard-style absolute position
-            let total_virtual_lines = self.history.len() + SCREEN_HEIGHT;
-            let view_start = total_virtual_lines.saturating_sub(SCREEN_HEIGHT + self.viewport_position);
+            let total_virtual_lines = self.history.len() + self.height;
+            let view_start = total_virtual_lines.saturating_sub(self.height + self.viewport_position);
             self.viewport_position = view_start;
             self.keyboard_entry = true;
             return;
@@ -287,11 +321,35 @@ impl ScrollbackBuffer {
     }
 
     /// Get the total number of lines in history.
-    #[cfg(test)]
     pub fn history_len(&self) -> usize {
         self.history.len()
     }
 
+    /// Cells of a history line (0 = oldest), including exact colors. Without
+    /// iCE colors, attribute bit 7 is read back as the blink flag.
+    pub fn history_cells(&self, index: usize, ice_colors: bool) -> Option<Vec<Cell>> {
+        let line = self.history.get(index)?;
+        let true_colors = self.true_colors[index].as_ref();
+        let cells = line
+            .chunks_exact(2)
+            .enumerate()
+            .map(|(x, cga)| {
+                let (fg_rgb, bg_rgb) = true_colors.and_then(|colors| colors.get(x).copied()).unwrap_or_default();
+                let bg = cga[1] >> 4;
+                let blink = !ice_colors && bg >= 8;
+                Cell {
+                    ch: cga[0],
+                    fg: cga[1] & 0x0F,
+                    bg: if blink { bg - 8 } else { bg },
+                    blink,
+                    fg_rgb,
+                    bg_rgb,
+                }
+            })
+            .collect();
+        Some(cells)
+    }
+
     /// Scroll up (back in history) by the specified number of lines.
     ///
     /// Entering scrollback mode if not already active.
@@ -340,32 +398,53 @@ impl ScrollbackBuffer {
         }
     }
 
-    /// Scroll up by one full page (SCREEN_HEIGHT lines).
+    /// Scroll up by one full page (screen height lines).
     pub fn page_up(&mut self) {
-        self.scroll_up(SCREEN_HEIGHT);
+        self.scroll_up(self.height);
     }
 
-    /// Scroll down by one full page (SCREEN_HEIGHT lines).
+    /// Scroll down by one full page (screen height lines).
     pub fn page_down(&mut self) {
-        self.scroll_down(SCREEN_HEIGHT);
+        self.scroll_down(self.height);
     }
 
     /// Get the line to display at a given screen row.
     ///
     /// Returns the CGA-format line bytes for the given row, taking into account
     /// the current viewport position. Returns None if the row is out of bounds.
+    pub fn get_display_line(&self, y: usize, screen: &Screen) -> Option<Vec<u8>> {
+        match self.display_source(y)? {
+            DisplaySource::History(index) => Some(self.history[index].clone()),
+            DisplaySource::Screen(screen_y) => self.screen_line_to_cga(screen, screen_y),
+        }
+    }
+
+    /// Get the exact colors of the line displayed at a given screen row.
     ///
-    /// Virtual buffer model: [history...][current_screen_25_lines]
+    /// Returns None if the row is out of bounds or has no true color cells.
+    pub fn get_display_true_colors(&self, y: usize, screen: &Screen) -> Option<TrueColors> {
+        match self.display_source(y)? {
+            DisplaySource::History(index) => self.true_colors[index].clone(),
+            DisplaySource::Screen(screen_y) if screen_y < self.height => {
+                self.cells_true_colors(&screen.get_line(screen_y)?)
+            }
+            DisplaySource::Screen(_) => None,
+        }
+    }
+
+    /// Find the history line or screen row shown at a given screen row.
+    ///
+    /// Virtual buffer model: [history...][current_screen_lines]
     /// - Mouse mode: viewport_position is offset from END (0 = live screen)
     /// - Keyboard mode: viewport_position is absolute index (fixed position as content grows)
-    pub fn get_display_line(&self, y: usize, screen: &Screen) -> Option<[u8; LINE_BYTES]> {
-        if y >= SCREEN_HEIGHT {
+    fn display_source(&self, y: usize) -> Option<DisplaySource> {
+        if y >= self.height {
             return None;
         }
 
         if !self.active {
             // Not in scrollback mode - show current screen
-            return self.screen_line_to_cga(screen, y);
+            return Some(DisplaySource::Screen(y));
         }
 
         // Calculate the absolute line index to display
@@ -376,30 +455,29 @@ impl ScrollbackBuffer {
             // Mouse mode: viewport_position is offset from end
             if self.viewport_position == 0 {
                 // At bottom - show live screen
-                return self.screen_line_to_cga(screen, y);
+                return Some(DisplaySource::Screen(y));
             }
-            // Virtual buffer: history + screen (25 lines)
-            let total_virtual_lines = self.history.len() + SCREEN_HEIGHT;
-            let view_start = total_virtual_lines.saturating_sub(SCREEN_HEIGHT + self.viewport_position);
+            // Virtual buffer: history + screen
+            let total_virtual_lines = self.history.len() + self.height;
+            let view_start = total_virtual_lines.saturating_sub(self.height + self.viewport_position);
             view_start + y
         };
 
         // Fetch from history or current screen
         if line_index < self.history.len() {
-            Some(self.history[line_index])
+            Some(DisplaySource::History(line_index))
         } else {
-            let screen_y = line_index - self.history.len();
-            self.screen_line_to_cga(screen, screen_y)
+            Some(DisplaySource::Screen(line_index - self.history.len()))
         }
     }
 
     /// Convert a screen line to CGA format.
-    fn screen_line_to_cga(&self, screen: &Screen, y: usize) -> Option<[u8; LINE_BYTES]> {
-        if y >= SCREEN_HEIGHT {
+    fn screen_line_to_cga(&self, screen: &Screen, y: usize) -> Option<Vec<u8>> {
+        if y >= self.height {
             return None;
         }
-        let mut line = [0u8; LINE_BYTES];
-        for x in 0..SCREEN_WIDTH {
+        let mut line = vec![0u8; self.width * 2];
+        for x in 0..self.width {
             if let Some(cell) = screen.get_cell(x, y) {
                 let cga = Self::cell_to_cga(cell);
                 line[x * 2] = cga[0];
@@ -439,7 +517,7 @@ mod tests {
 
     #[test]
     fn test_cell_to_cga_roundtrip() {
-        let cell = Cell { ch: b'A', fg: 14, bg: 4 };
+        let cell = Cell { ch: b'A', fg: 14, bg: 4, blink: false, ..Cell::default() };
         let cga = ScrollbackBuffer::cell_to_cga(&cell);
         let result = ScrollbackBuffer::cga_to_cell(cga);
         assert_eq!(result.ch, cell.ch);
@@ -447,6 +525,12 @@ mod tests {
         assert_eq!(result.bg, cell.bg);
     }
 
+    #[test]
+    fn test_blink_stored_in_attribute_bit7() {
+        let cell = Cell { ch: b'B', fg: 15, bg: 1, blink: true, ..Cell::default() };
+        assert_eq!(ScrollbackBuffer::cell_to_cga(&cell), [b'B', 0x9F]);
+    }
+
     #[test]
     fn test_scrollback_indicator() {
         let indicator = ScrollbackBuffer::scrollback_indicator();
@@ -461,7 +545,7 @@ mod tests {
         let mut buffer = ScrollbackBuffer::new();
 
         // Push some test lines
-        let cell = Cell { ch: b' ', fg: 7, bg: 0 };
+        let cell = Cell { ch: b' ', fg: 7, bg: 0, blink: false, ..Cell::default() };
         let line = vec![cell; 80];
         for _ in 0..50 {
             buffer.push_line(&line);
@@ -490,10 +574,26 @@ mod tests {
         assert_eq!(buffer.viewport_position(), 0);
     }
 
+    #[test]
+    fn test_custom_size() {
+        let mut buffer = ScrollbackBuffer::with_size(100, 132, 43);
+        let line = vec![Cell { ch: b'W', fg: 7, bg: 0, blink: false, ..Cell::default() }; 132];
+        for _ in 0..100 {
+            buffer.push_line(&line);
+        }
+        let screen = Screen::with_size(132, 43);
+        buffer.page_up();
+        assert_eq!(buffer.viewport_position(), 43);
+        let display = buffer.get_display_line(42, &screen).unwrap();
+        assert_eq!(display.len(), 264);
+        assert_eq!(display[262], b'W');
+        assert!(buffer.get_display_line(43, &screen).is_none());
+    }
+
     #[test]
     fn test_max_lines_trimming() {
-        let mut buffer = ScrollbackBuffer::with_max_lines(10);
-        let cell = Cell { ch: b'X', fg: 7, bg: 0 };
+        let mut buffer = ScrollbackBuffer::with_size(10, 80, 25);
+        let cell = Cell { ch: b'X', fg: 7, bg: 0, blink: false, ..Cell::default() };
         let line = vec![cell; 80];
 
         // Add 15 lines
@@ -504,4 +604,41 @@ mod tests {
         // Should be trimmed to 10
         assert_eq!(buffer.history_len(), 10);
     }
+
+    #[test]
+    fn test_true_colors_kept_in_history() {
+        let mut buffer = ScrollbackBuffer::with_size(3, 4, 2);
+        let plain = vec![Cell::default(); 4];
+        let mut colored = plain.clone();
+        colored[1].bg_rgb = Some((1, 2, 3));
+        buffer.push_line(&colored);
+        buffer.push_line(&plain);
+
+        let screen = Screen::with_size(4, 2);
+        buffer.enter_viewer_mode();
+        let colors = buffer.get_display_true_colors(0, &screen).unwrap();
+        assert_eq!(colors, vec![(None, None), (None, Some((1, 2, 3))), (None, None), (None, None)]);
+        assert!(buffer.get_display_true_colors(1, &screen).is_none());
+
+        // Trimming keeps lines and colors together
+        buffer.push_line(&plain);
+        buffer.push_line(&plain);
+        assert!(buffer.get_display_true_colors(0, &screen).is_none());
+    }
+
+    #[test]
+    fn test_history_cells() {
+        let mut buffer = ScrollbackBuffer::with_size(3, 2, 2);
+        let blinking = Cell { ch: b'B', fg: 12, bg: 4, blink: true, ..Cell::default() };
+        let colored = Cell { ch: b'C', fg_rgb: Some((1, 2, 3)), ..Cell::default() };
+        buffer.push_line(&[blinking, colored]);
+
+        let cells = buffer.history_cells(0, false).unwrap();
+        assert_eq!((cells[0].ch, cells[0].fg, cells[0].bg, cells[0].blink), (b'B', 12, 4, true));
+        assert_eq!((cells[1].ch, cells[1].fg_rgb), (b'C', Some((1, 2, 3))));
+        // In iCE mode the same attribute is a bright background
+        let cells = buffer.history_cells(0, true).unwrap();
+        assert_eq!((cells[0].bg, cells[0].blink), (12, false));
+        assert!(buffer.history_cells(1, true).is_none());
+    }
 }
@@ -0,0 +1,396 @@
+//! Telnet protocol negotiation for BBS sessions.
+//!
+//! Sits in front of the ANSI parser and strips IAC command sequences from
+//! the incoming stream, producing replies for option negotiation and
+//! subnegotiation (TTYPE, NAWS, BINARY).
+
+/// Interpret As Command
+pub const IAC: u8 = 255;
+pub const DONT: u8 = 254;
+pub const DO: u8 = 253;
+pub const WONT: u8 = 252;
+pub const WILL: u8 = 251;
+/// Subnegotiation begin
+pub const SB: u8 = 250;
+/// Subnegotiation end
+pub const SE: u8 = 240;
+
+/// Binary transmission (RFC 856)
+pub const OPT_BINARY: u8 = 0;
+/// Echo (RFC 857)
+pub const OPT_ECHO: u8 = 1;
+/// Suppress go-ahead (RFC 858)
+pub const OPT_SGA: u8 = 3;
+/// Terminal type (RFC 1091)
+pub const OPT_TTYPE: u8 = 24;
+/// Negotiate about window size (RFC 1073)
+pub const OPT_NAWS: u8 = 31;
+
+/// TTYPE subnegotiation: IS
+const TTYPE_IS: u8 = 0;
+/// TTYPE subnegotiation: SEND
+const TTYPE_SEND: u8 = 1;
+
+/// Terminal type reported to the server.
+pub const TERMINAL_TYPE: &[u8] = b"ANSI-BBS";
+
+/// Maximum subnegotiation payload retained (anything longer is truncated).
+const MAX_SB_LEN: usize = 256;
+
+/// Result of filtering a chunk of incoming bytes.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TelnetOutput {
+    /// Data bytes for the terminal (IAC sequences removed).
+    pub data: Vec<u8>,
+    /// Bytes to send back to the server (negotiation replies).
+    pub reply: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TelnetState {
+    Data,
+    /// Previous byte was a CR (a following NUL is dropped)
+    CarriageReturn,
+    Iac,
+    /// IAC DO/DONT/WILL/WONT, waiting for the option byte
+    Negotiate(u8),
+    /// Inside IAC SB, collecting payload
+    Subnegotiation,
+    /// IAC seen inside subnegotiation
+    SubnegotiationIac,
+}
+
+/// Telnet protocol state machine.
+pub struct TelnetFilter {
+    state: TelnetState,
+    sb_buffer: Vec<u8>,
+    /// Options we have agreed to perform (WILL sent, DO received)
+    local: [bool; 256],
+    /// Options the server has agreed to perform (DO sent, WILL received)
+    remote: [bool; 256],
+    columns: u16,
+    rows: u16,
+}
+
+impl TelnetFilter {
+    /// Create a new telnet filter reporting an 80x25 window.
+    pub fn new() -> Self {
+        TelnetFilter {
+            state: TelnetState::Data,
+            sb_buffer: Vec::new(),
+            local: [false; 256],
+            remote: [false; 256],
+            columns: 80,
+            rows: 25,
+        }
+    }
+
+    /// Check whether we have agreed to perform an option.
+    pub fn is_local_enabled(&self, option: u8) -> bool {
+        self.local[option as usize]
+    }
+
+    /// Check whether the server has agreed to perform an option.
+    pub fn is_remote_enabled(&self, option: u8) -> bool {
+        self.remote[option as usize]
+    }
+
+    /// Set the window size reported via NAWS.
+    ///
+    /// Returns a NAWS subnegotiation to send if NAWS is already active.
+    pub fn set_window_size(&mut self, columns: u16, rows: u16) -> Vec<u8> {
+        self.columns = columns;
+        self.rows = rows;
+        if self.is_local_enabled(OPT_NAWS) {
+            self.naws_subnegotiation()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Filter incoming bytes from the server.
+    pub fn process(&mut self, input: &[u8]) -> TelnetOutput {
+        let mut output = TelnetOutput::default();
+        for &byte in input {
+            self.process_byte(byte, &mut output);
+        }
+        output
+    }
+
+    fn process_byte(&mut self, byte: u8, output: &mut TelnetOutput) {
+        match self.state {
+            TelnetState::Data | TelnetState::CarriageReturn => {
+                let after_cr = self.state == TelnetState::CarriageReturn;
+                self.state = TelnetState::Data;
+                match byte {
+                    IAC => self.state = TelnetState::Iac,
+                    // CR NUL is a bare carriage return outside binary mode
+                    0 if after_cr && !self.is_remote_enabled(OPT_BINARY) => {}
+                    b'\r' => {
+                        output.data.push(byte);
+                        self.state = TelnetState::CarriageReturn;
+                    }
+                    _ => output.data.push(byte),
+                }
+            }
+            TelnetState::Iac => {
+                self.state = TelnetState::Data;
+                match byte {
+                    IAC => output.data.push(IAC), // Escaped 0xFF
+                    DO | DONT | WILL | WONT => self.state = TelnetState::Negotiate(byte),
+                    SB => {
+                        self.sb_buffer.clear();
+                        self.state = TelnetState::Subnegotiation;
+                    }
+                    _ => {} // NOP, GA, AYT, etc. are ignored
+                }
+            }
+            TelnetState::Negotiate(command) => {
+                self.state = TelnetState::Data;
+                self.handle_negotiation(command, byte, &mut output.reply);
+            }
+            TelnetState::Subnegotiation => {
+                if byte == IAC {
+                    self.state = TelnetState::SubnegotiationIac;
+                } else if self.sb_buffer.len() < MAX_SB_LEN {
+                    self.sb_buffer.push(byte);
+                }
+            }
+            TelnetState::SubnegotiationIac => match byte {
+                SE => {
+                    self.state = TelnetState::Data;
+                    self.handle_subnegotiation(&mut output.reply);
+                }
+                IAC => {
+                    self.state = TelnetState::Subnegotiation;
+                    if self.sb_buffer.len() < MAX_SB_LEN {
+                        self.sb_buffer.push(IAC);
+                    }
+                }
+                _ => {
+                    // Malformed subnegotiation - abandon it
+                    self.state = TelnetState::Data;
+                }
+            },
+        }
+    }
+
+    /// Answer DO/DONT/WILL/WONT.
+    ///
+    /// Replies are only sent when an option changes state, which prevents
+    /// negotiation loops (RFC 854).
+    fn handle_negotiation(&mut self, command: u8, option: u8, reply: &mut Vec<u8>) {
+        let idx = option as usize;
+        match command {
+            DO => {
+                let supported = matches!(option, OPT_BINARY | OPT_SGA | OPT_TTYPE | OPT_NAWS);
+                if supported {
+                    if !self.local[idx] {
+                        self.local[idx] = true;
+                        reply.extend_from_slice(&[IAC, WILL, option]);
+                    }
+                    if option == OPT_NAWS {
+                        reply.extend_from_slice(&self.naws_subnegotiation());
+                    }
+                } else {
+                    reply.extend_from_slice(&[IAC, WONT, option]);
+                }
+            }
+            DONT if self.local[idx] => {
+                self.local[idx] = false;
+                reply.extend_from_slice(&[IAC, WONT, option]);
+            }
+            WILL => {
+                let supported = matches!(option, OPT_BINARY | OPT_ECHO | OPT_SGA);
+                if supported {
+                    if !self.remote[idx] {
+                        self.remote[idx] = true;
+                        reply.extend_from_slice(&[IAC, DO, option]);
+                    }
+                } else {
+                    reply.extend_from_slice(&[IAC, DONT, option]);
+                }
+            }
+            WONT if self.remote[idx] => {
+                self.remote[idx] = false;
+                reply.extend_from_slice(&[IAC, DONT, option]);
+            }
+            _ => {}
+        }
+    }
+
+    /// Answer a completed subnegotiation.
+    fn handle_subnegotiation(&mut self, reply: &mut Vec<u8>) {
+        if let [OPT_TTYPE, TTYPE_SEND, ..] = self.sb_buffer.as_slice() {
+            reply.extend_from_slice(&[IAC, SB, OPT_TTYPE, TTYPE_IS]);
+            reply.extend_from_slice(TERMINAL_TYPE);
+            reply.extend_from_slice(&[IAC, SE]);
+        }
+    }
+
+    /// Build an `IAC SB NAWS <cols> <rows> IAC SE` sequence.
+    fn naws_subnegotiation(&self) -> Vec<u8> {
+        let mut out = vec![IAC, SB, OPT_NAWS];
+        for value in [self.columns, self.rows] {
+            for byte in value.to_be_bytes() {
+                out.push(byte);
+                if byte == IAC {
+                    out.push(IAC);
+                }
+            }
+        }
+        out.extend_from_slice(&[IAC, SE]);
+        out
+    }
+
+    /// Escape outbound bytes (keystrokes) for the telnet stream.
+    ///
+    /// 0xFF is doubled, and outside binary mode a bare CR is sent as CR NUL.
+    pub fn escape_outbound(&self, input: &[u8]) -> Vec<u8> {
+        let binary = self.is_local_enabled(OPT_BINARY);
+        let mut out = Vec::with_capacity(input.len());
+        for (i, &byte) in input.iter().enumerate() {
+            out.push(byte);
+            if byte == IAC {
+                out.push(IAC);
+            } else if byte == b'\r' && !binary && input.get(i + 1) != Some(&b'\n') {
+                out.push(0);
+            }
+        }
+        out
+    }
+}
+
+impl Default for TelnetFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_data_passes_through() {
+        let mut telnet = TelnetFilter::new();
+        let out = telnet.process(b"\x1b[1;37mHello\xB0\xDB");
+        assert_eq!(out.data, b"\x1b[1;37mHello\xB0\xDB");
+        assert!(out.reply.is_empty());
+    }
+
+    #[test]
+    fn test_escaped_iac_is_data() {
+        let mut telnet = TelnetFilter::new();
+        let out = telnet.process(&[b'A', IAC, IAC, b'B']);
+        assert_eq!(out.data, vec![b'A', 0xFF, b'B']);
+    }
+
+    #[test]
+    fn test_do_supported_options() {
+        let mut telnet = TelnetFilter::new();
+        let out = telnet.process(&[IAC, DO, OPT_BINARY, IAC, DO, OPT_TTYPE]);
+        assert!(out.data.is_empty());
+        assert_eq!(out.reply, vec![IAC, WILL, OPT_BINARY, IAC, WILL, OPT_TTYPE]);
+        assert!(telnet.is_local_enabled(OPT_BINARY));
+
+        // Repeated DO does not produce another reply
+        let out = telnet.process(&[IAC, DO, OPT_BINARY]);
+        assert!(out.reply.is_empty());
+    }
+
+    #[test]
+    fn test_do_unsupported_option() {
+        let mut telnet = TelnetFilter::new();
+        let out = telnet.process(&[IAC, DO, 39]);
+        assert_eq!(out.reply, vec![IAC, WONT, 39]);
+    }
+
+    #[test]
+    fn test_will_options() {
+        let mut telnet = TelnetFilter::new();
+        let out = telnet.process(&[IAC, WILL, OPT_ECHO, IAC, WILL, 5]);
+        assert_eq!(out.reply, vec![IAC, DO, OPT_ECHO, IAC, DONT, 5]);
+        assert!(telnet.is_remote_enabled(OPT_ECHO));
+
+        let out = telnet.process(&[IAC, WONT, OPT_ECHO]);
+        assert_eq!(out.reply, vec![IAC, DONT, OPT_ECHO]);
+        assert!(!telnet.is_remote_enabled(OPT_ECHO));
+    }
+
+    #[test]
+    fn test_dont_disables_option() {
+        let mut telnet = TelnetFilter::new();
+        telnet.process(&[IAC, DO, OPT_BINARY]);
+        let out = telnet.process(&[IAC, DONT, OPT_BINARY]);
+        assert_eq!(out.reply, vec![IAC, WONT, OPT_BINARY]);
+        assert!(!telnet.is_local_enabled(OPT_BINARY));
+
+        // DONT for an option that is already off is not answered
+        let out = telnet.process(&[IAC, DONT, OPT_BINARY]);
+        assert!(out.reply.is_empty());
+    }
+
+    #[test]
+    fn test_naws() {
+        let mut telnet = TelnetFilter::new();
+        let out = telnet.process(&[IAC, DO, OPT_NAWS]);
+        assert_eq!(
+            out.reply,
+            vec![IAC, WILL, OPT_NAWS, IAC, SB, OPT_NAWS, 0, 80, 0, 25, IAC, SE]
+        );
+
+        let update = telnet.set_window_size(132, 50);
+        assert_eq!(update, vec![IAC, SB, OPT_NAWS, 0, 132, 0, 50, IAC, SE]);
+    }
+
+    #[test]
+    fn test_naws_escapes_iac_in_size() {
+        let mut telnet = TelnetFilter::new();
+        telnet.process(&[IAC, DO, OPT_NAWS]);
+        let update = telnet.set_window_size(255, 25);
+        assert_eq!(update, vec![IAC, SB, OPT_NAWS, 0, IAC, IAC, 0, 25, IAC, SE]);
+    }
+
+    #[test]
+    fn test_ttype_subnegotiation() {
+        let mut telnet = TelnetFilter::new();
+        let out = telnet.process(&[IAC, SB, OPT_TTYPE, TTYPE_SEND, IAC, SE, b'X']);
+        assert_eq!(out.data, vec![b'X']);
+        let mut expected = vec![IAC, SB, OPT_TTYPE, TTYPE_IS];
+        expected.extend_from_slice(b"ANSI-BBS");
+        expected.extend_from_slice(&[IAC, SE]);
+        assert_eq!(out.reply, expected);
+    }
+
+    #[test]
+    fn test_sequence_split_across_chunks() {
+        let mut telnet = TelnetFilter::new();
+        let first = telnet.process(&[b'A', IAC]);
+        assert_eq!(first.data, vec![b'A']);
+        let second = telnet.process(&[WILL]);
+        assert!(second.data.is_empty() && second.reply.is_empty());
+        let third = telnet.process(&[OPT_SGA, b'B']);
+        assert_eq!(third.data, vec![b'B']);
+        assert_eq!(third.reply, vec![IAC, DO, OPT_SGA]);
+    }
+
+    #[test]
+    fn test_cr_nul_stripped() {
+        let mut telnet = TelnetFilter::new();
+        let out = telnet.process(b"A\r\0B\r\nC");
+        assert_eq!(out.data, b"A\rB\r\nC");
+    }
+
+    #[test]
+    fn test_escape_outbound() {
+        let mut telnet = TelnetFilter::new();
+        assert_eq!(telnet.escape_outbound(&[b'a', 0xFF]), vec![b'a', 0xFF, 0xFF]);
+        assert_eq!(telnet.escape_outbound(b"\r"), vec![b'\r', 0]);
+        assert_eq!(telnet.escape_outbound(b"\r\n"), b"\r\n".to_vec());
+
+        // In binary mode CR is sent as-is
+        telnet.process(&[IAC, DO, OPT_BINARY]);
+        assert_eq!(telnet.escape_outbound(b"\r"), vec![b'\r']);
+    }
+}
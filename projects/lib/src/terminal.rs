@@ -1,25 +1,34 @@
-/// Terminal state management combining screen, parser, and scrollback.
-///
-/// This module provides a unified interface for terminal operations,
-/// ensuring scrollback capture happens during scroll operations.
+//! Terminal state management combining screen, parser, and scrollback.
+//!
+//! This module provides a unified interface for terminal operations,
+//! ensuring scrollback capture happens during scroll operations.
 
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-use std::rc::Rc;
 use std::cell::RefCell;
-use web_sys::{HtmlCanvasElement, KeyboardEvent, MouseEvent, WheelEvent};
+use std::rc::Rc;
 
+use crate::keyboard::{self, Modifiers, OutputSink};
 use crate::parser::AnsiParser;
-use crate::postprocess::PostProcessor;
-use crate::renderer::Renderer;
-use crate::screen::Screen;
+use crate::screen::{Screen, DEFAULT_COLUMNS, DEFAULT_ROWS};
 use crate::scrollback::ScrollbackBuffer;
 
+/// Outcome of a key press passed to `Terminal::key_down`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyInput {
+    /// Consumed locally (Alt+K, or any key while scrollback is active).
+    Local,
+    /// Encoded and delivered to the output sink.
+    Sent,
+    /// Produces no output, or no sink is attached; leave it to the browser.
+    Ignored,
+}
+
 /// Combined terminal state for coordinated updates.
 pub struct Terminal {
     pub screen: Screen,
     pub parser: AnsiParser,
     pub scrollback: ScrollbackBuffer,
+    /// Destination for keyboard input (None = input is discarded)
+    output: Option<Rc<dyn OutputSink>>,
 }
 
 impl Terminal {
@@ -28,12 +37,20 @@ impl Terminal {
         Self::with_scrollback_lines(crate::scrollback::DEFAULT_MAX_LINES)
     }
 
-    /// Create a new terminal with specified scrollback buffer size.
+    /// Create a new 80x25 terminal with specified scrollback buffer size.
     pub fn with_scrollback_lines(max_lines: usize) -> Self {
+        Self::with_size(DEFAULT_COLUMNS, DEFAULT_ROWS, max_lines)
+    }
+
+    /// Create a new terminal with the given screen size and scrollback buffer size.
+    pub fn with_size(columns: usize, rows: usize, max_lines: usize) -> Self {
+        let screen = Screen::with_size(columns, rows);
+        let (columns, rows) = screen.dimensions();
         Terminal {
-            screen: Screen::new(),
+            screen,
             parser: AnsiParser::new(),
-            scrollback: ScrollbackBuffer::with_max_lines(max_lines),
+            scrollback: ScrollbackBuffer::with_size(max_lines, columns, rows),
+            output: None,
         }
     }
 
@@ -78,12 +95,12 @@ impl Terminal {
             return false;
         }
 
-        let (_width, height) = self.screen.dimensions();
+        let (width, height) = self.screen.dimensions();
         let (cursor_x, cursor_y) = self.screen.cursor_pos();
 
         match byte {
             0x0A => cursor_y == height - 1, // Newline at bottom
-            b if b >= 32 => cursor_y == height - 1 && cursor_x == 79, // Char at bottom-right
+            b if b >= 32 => cursor_y == height - 1 && cursor_x == width - 1, // Char at bottom-right
             _ => false,
         }
     }
@@ -95,6 +112,83 @@ impl Terminal {
         }
     }
 
+    /// Show a complete image (a decoded art file) in place of the screen.
+    ///
+    /// Rows that don't fit go to scrollback as if they had scrolled off the
+    /// top, so the last page ends up on the screen. The image's iCE color
+    /// mode is used.
+    pub fn load_screen(&mut self, image: &Screen) {
+        let (_, rows) = self.screen.dimensions();
+        let (image_columns, image_rows) = image.dimensions();
+        let scrolled = image_rows.saturating_sub(rows);
+
+        self.clear();
+        self.screen.set_ice_colors(image.ice_colors());
+        for y in 0..image_rows {
+            let Some(line) = image.get_line(y) else { continue };
+            if y < scrolled {
+                self.scrollback.push_line(&line);
+            } else {
+                for (x, &cell) in line.iter().enumerate().take(image_columns) {
+                    self.screen.set_cell(x, y - scrolled, cell);
+                }
+            }
+        }
+    }
+
+    /// Clear the screen and discard scrollback history.
+    pub fn clear(&mut self) {
+        self.screen.clear_with_bg(0, None);
+        self.scrollback.clear();
+    }
+
+    /// Reset the terminal to its initial state.
+    ///
+    /// Screen, parser state (colors, saved cursor), and scrollback are reset.
+    /// The output sink, screen size, and iCE color mode are kept.
+    pub fn reset(&mut self) {
+        let ice_colors = self.screen.ice_colors();
+        let (columns, rows) = self.screen.dimensions();
+        self.screen = Screen::with_size(columns, rows);
+        self.screen.set_ice_colors(ice_colors);
+        self.parser.reset();
+        self.scrollback.clear();
+    }
+
+    /// Set the sink that receives bytes typed by the user.
+    pub fn set_output_sink(&mut self, sink: Rc<dyn OutputSink>) {
+        self.output = Some(sink);
+    }
+
+    /// Get the current output sink.
+    ///
+    /// Callers should release their terminal borrow before sending, since the
+    /// sink may call back into the terminal (e.g. local echo via `write()`).
+    pub fn output_sink(&self) -> Option<Rc<dyn OutputSink>> {
+        self.output.clone()
+    }
+
+    /// Route a key press: scrollback keys stay local, everything else is
+    /// encoded and sent to the output sink.
+    ///
+    /// Takes the shared cell rather than `&mut self` so the terminal borrow
+    /// is released before sending; the sink may write back (local echo).
+    pub fn key_down(terminal: &RefCell<Terminal>, key: &str, modifiers: Modifiers) -> KeyInput {
+        let mut term = terminal.borrow_mut();
+        if term.handle_key(key, modifiers.alt) {
+            return KeyInput::Local;
+        }
+        let Some(output) = term.output_sink() else {
+            return KeyInput::Ignored;
+        };
+        let Some(bytes) = keyboard::encode_key(key, modifiers) else {
+            return KeyInput::Ignored;
+        };
+        drop(term);
+        output.send(&bytes);
+        KeyInput::Sent
+    }
+
     /// Handle a wheel scroll event.
     ///
     /// Returns true if the event was handled and should not propagate.
@@ -139,11 +233,9 @@ impl Terminal {
         // If in scrollback mode, handle navigation or block all other keys
         if self.scrollback.is_active() {
             match key {
-                "Escape" => {
-                    // Don't exit in viewer mode (instant render)
-                    if !self.scrollback.is_viewer_mode() {
-                        self.scrollback.start_animated_exit();
-                    }
+                // Don't exit in viewer mode (instant render)
+                "Escape" if !self.scrollback.is_viewer_mode() => {
+                    self.scrollback.start_animated_exit();
                 }
                 "ArrowUp" => {
                     self.scrollback.scroll_up(1);
@@ -188,252 +280,115 @@ impl Default for Terminal {
     }
 }
 
-/// Set up event listeners for scrollback on a canvas element.
-///
-/// This attaches wheel, keyboard, and click events to the canvas container.
-pub fn setup_scrollback_events(
-    canvas: &HtmlCanvasElement,
-    terminal: Rc<RefCell<Terminal>>,
-    renderer: Rc<Renderer>,
-    offscreen_canvas: Rc<HtmlCanvasElement>,
-    post_processor: Rc<PostProcessor>,
-) -> Result<(), JsValue> {
-    // Make canvas focusable for keyboard events
-    canvas.set_tab_index(0);
-
-    // Create options for passive: false (required to preventDefault on wheel)
-    let wheel_options = web_sys::AddEventListenerOptions::new();
-    wheel_options.set_passive(false);
-
-    // Set up wheel event listener with passive: false
-    {
-        let terminal = terminal.clone();
-        let renderer = renderer.clone();
-        let offscreen_canvas = offscreen_canvas.clone();
-        let post_processor = post_processor.clone();
-
-        let closure = Closure::<dyn Fn(WheelEvent)>::new(move |event: WheelEvent| {
-            // Always capture wheel events on canvas to prevent page scrolling
-            event.prevent_default();
-            event.stop_propagation();
-
-            let mut term = terminal.borrow_mut();
-            let was_animating = term.scrollback.is_animating_exit();
-            if term.handle_wheel(event.delta_y()) {
-                // Re-render with scrollback
-                let _ = renderer.render_with_scrollback(&term.screen, &term.scrollback);
-                let _ = post_processor.process(&offscreen_canvas);
-
-                // Start animation only if it just started (wasn't already running)
-                if !was_animating && term.scrollback.is_animating_exit() {
-                    drop(term); // Release borrow before starting animation
-                    start_exit_animation(
-                        terminal.clone(),
-                        renderer.clone(),
-                        offscreen_canvas.clone(),
-                        post_processor.clone(),
-                    );
-                }
-            }
-        });
-
-        canvas.add_event_listener_with_callback_and_add_event_listener_options(
-            "wheel",
-            closure.as_ref().unchecked_ref(),
-            &wheel_options,
-        )?;
-        closure.forget(); // Keep the closure alive
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture_output(term: &mut Terminal) -> Rc<RefCell<Vec<u8>>> {
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let sink = sent.clone();
+        term.set_output_sink(Rc::new(move |bytes: &[u8]| {
+            sink.borrow_mut().extend_from_slice(bytes)
+        }));
+        sent
     }
 
-    // Set up keyboard event listener on the canvas itself (requires focus)
-    {
-        let terminal = terminal.clone();
-        let renderer = renderer.clone();
-        let offscreen_canvas = offscreen_canvas.clone();
-        let post_processor = post_processor.clone();
-        let canvas_for_fullscreen = canvas.clone();
-
-        let closure = Closure::<dyn Fn(KeyboardEvent)>::new(move |event: KeyboardEvent| {
-            // Handle Alt+Enter for fullscreen toggle
-            if event.key() == "Enter" && event.alt_key() {
-                event.prevent_default();
-                event.stop_propagation();
-                toggle_fullscreen(&canvas_for_fullscreen);
-                return;
-            }
-
-            let mut term = terminal.borrow_mut();
-            let was_animating = term.scrollback.is_animating_exit();
-            if term.handle_key(&event.key(), event.alt_key()) {
-                event.prevent_default();
-                event.stop_propagation();
-
-                // Re-render with scrollback
-                let _ = renderer.render_with_scrollback(&term.screen, &term.scrollback);
-                let _ = post_processor.process(&offscreen_canvas);
-
-                // Start animation only if it just started (wasn't already running)
-                if !was_animating && term.scrollback.is_animating_exit() {
-                    drop(term); // Release borrow before starting animation
-                    start_exit_animation(
-                        terminal.clone(),
-                        renderer.clone(),
-                        offscreen_canvas.clone(),
-                        post_processor.clone(),
-                    );
-                }
-            }
-        });
-
-        // Add to canvas directly for keyboard events (canvas is now focusable)
-        canvas.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref())?;
-        closure.forget();
+    #[test]
+    fn test_reset() {
+        let mut term = Terminal::new();
+        term.process_bytes(b"\x1b[1;31mHello\x1b[s");
+        term.process_bytes(&b"\r\n".repeat(30));
+        term.reset();
+        assert_eq!(term.screen.cursor_pos(), (0, 0));
+        assert_eq!(term.screen.get_cell(0, 0).unwrap().ch, b' ');
+        assert!(!term.scrollback.is_active());
+        term.process_bytes(b"X");
+        assert_eq!(term.screen.get_cell(0, 0).unwrap().fg, 7);
     }
 
-    // Set up click event listener for exiting scrollback and focusing canvas
-    {
-        let terminal = terminal.clone();
-        let renderer = renderer.clone();
-        let offscreen_canvas = offscreen_canvas.clone();
-        let post_processor = post_processor.clone();
-        let canvas_clone = canvas.clone();
-
-        let closure = Closure::<dyn Fn(MouseEvent)>::new(move |event: MouseEvent| {
-            // Focus the canvas on click so it receives keyboard events
-            let _ = canvas_clone.focus();
-
-            let mut term = terminal.borrow_mut();
-            let was_animating = term.scrollback.is_animating_exit();
-            if term.handle_click() {
-                event.prevent_default();
-                event.stop_propagation();
-
-                // Re-render with scrollback
-                let _ = renderer.render_with_scrollback(&term.screen, &term.scrollback);
-                let _ = post_processor.process(&offscreen_canvas);
-
-                // Start animation only if it just started (wasn't already running)
-                if !was_animating && term.scrollback.is_animating_exit() {
-                    drop(term); // Release borrow before starting animation
-                    start_exit_animation(
-                        terminal.clone(),
-                        renderer.clone(),
-                        offscreen_canvas.clone(),
-                        post_processor.clone(),
-                    );
-                }
-            }
-        });
-
-        canvas.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;
-        closure.forget();
+    #[test]
+    fn test_wide_screen_scrolls_at_last_column() {
+        let mut term = Terminal::with_size(132, 25, 100);
+        term.process_bytes(b"\x1b[25;1H");
+        term.process_bytes(&[b'A'; 131]);
+        assert_eq!(term.scrollback.history_len(), 0);
+        term.process_bytes(b"B");
+        assert_eq!(term.scrollback.history_len(), 1);
+        assert_eq!(term.screen.get_cell(131, 23).unwrap().ch, b'B');
     }
 
-    // Set up mousedown event listener to prevent bubbling (especially for middle-click scroll)
-    {
-        let closure = Closure::<dyn Fn(MouseEvent)>::new(move |event: MouseEvent| {
-            // Prevent default for middle mouse button (scroll wheel click)
-            // This stops the auto-scroll behavior in browsers
-            if event.button() == 1 {
-                event.prevent_default();
-                event.stop_propagation();
-            }
-        });
+    #[test]
+    fn test_load_screen() {
+        let mut image = Screen::with_size(80, 30);
+        image.set_ice_colors(false);
+        for y in 0..30 {
+            image.set_cell(0, y, crate::screen::Cell { ch: b'0' + y as u8, ..Default::default() });
+        }
 
-        canvas.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref())?;
-        closure.forget();
+        let mut term = Terminal::new();
+        term.process_bytes(b"old");
+        term.load_screen(&image);
+        assert!(!term.screen.ice_colors());
+        assert_eq!(term.scrollback.history_len(), 5);
+        assert_eq!(term.screen.get_cell(0, 0).unwrap().ch, b'5');
+        assert_eq!(term.screen.get_cell(0, 24).unwrap().ch, b'0' + 29);
+
+        term.scrollback.enter_viewer_mode();
+        assert_eq!(term.scrollback.get_display_line(0, &term.screen).unwrap()[0], b'0');
+
+        // Short images fit on the screen
+        term.load_screen(&Screen::with_size(80, 3));
+        assert_eq!(term.scrollback.history_len(), 0);
+        assert_eq!(term.screen.get_cell(0, 0).unwrap().ch, b' ');
     }
 
-    Ok(())
-}
-
-/// Start the exit animation loop using requestAnimationFrame.
-///
-/// The animation loop will automatically stop if:
-/// - Animation completes (scrolled to bottom)
-/// - Animation is cancelled (user scrolled up or pressed Alt+K)
-/// - Scrollback mode is exited
-fn start_exit_animation(
-    terminal: Rc<RefCell<Terminal>>,
-    renderer: Rc<Renderer>,
-    offscreen_canvas: Rc<HtmlCanvasElement>,
-    post_processor: Rc<PostProcessor>,
-) {
-    let window = match web_sys::window() {
-        Some(w) => w,
-        None => return,
-    };
-
-    // Create the animation frame callback
-    let f: Rc<RefCell<Option<Closure<dyn FnMut()>>>> = Rc::new(RefCell::new(None));
-    let g = f.clone();
-
-    let terminal_clone = terminal.clone();
-    let renderer_clone = renderer.clone();
-    let offscreen_clone = offscreen_canvas.clone();
-    let post_clone = post_processor.clone();
-
-    *g.borrow_mut() = Some(Closure::new(move || {
-        let mut term = terminal_clone.borrow_mut();
-
-        // Check if animation was cancelled by user input (scroll wheel, Alt+K, etc.)
-        // This happens when is_animating_exit() is false but scrollback is still active
-        if !term.scrollback.is_animating_exit() {
-            // Animation was cancelled or completed, stop the loop
-            let _ = f.borrow_mut().take();
-            return;
-        }
-
-        // Advance animation
-        let still_animating = term.scrollback.animate_exit_frame();
+    #[test]
+    fn test_reset_keeps_ice_colors_mode() {
+        let mut term = Terminal::new();
+        term.screen.set_ice_colors(false);
+        term.reset();
+        assert!(!term.screen.ice_colors());
+    }
 
-        // Re-render
-        let _ = renderer_clone.render_with_scrollback(&term.screen, &term.scrollback);
-        let _ = post_clone.process(&offscreen_clone);
+    #[test]
+    fn test_key_down_sends_to_sink() {
+        let term = RefCell::new(Terminal::new());
+        let sent = capture_output(&mut term.borrow_mut());
+        let plain = Modifiers::default();
+        assert_eq!(Terminal::key_down(&term, "ArrowUp", plain), KeyInput::Sent);
+        assert_eq!(Terminal::key_down(&term, "x", plain), KeyInput::Sent);
+        assert_eq!(Terminal::key_down(&term, "Shift", plain), KeyInput::Ignored);
+        assert_eq!(*sent.borrow(), b"\x1b[Ax".to_vec());
+    }
 
-        // Continue animation if needed
-        if still_animating && term.scrollback.is_animating_exit() {
-            drop(term); // Release borrow
-            if let Some(window) = web_sys::window() {
-                let _ = window.request_animation_frame(
-                    f.borrow().as_ref().unwrap().as_ref().unchecked_ref()
-                );
-            }
-        } else {
-            // Animation complete, clean up
-            let _ = f.borrow_mut().take();
-        }
-    }));
+    #[test]
+    fn test_key_down_without_sink() {
+        let term = RefCell::new(Terminal::new());
+        assert!(term.borrow().output_sink().is_none());
+        assert_eq!(Terminal::key_down(&term, "x", Modifiers::default()), KeyInput::Ignored);
+        assert_eq!(Terminal::key_down(&term, "Tab", Modifiers::default()), KeyInput::Ignored);
+    }
 
-    // Start the animation loop
-    let _ = window.request_animation_frame(
-        g.borrow().as_ref().unwrap().as_ref().unchecked_ref()
-    );
-}
+    #[test]
+    fn test_key_down_local_echo() {
+        let term = Rc::new(RefCell::new(Terminal::new()));
+        let echo = term.clone();
+        term.borrow_mut().set_output_sink(Rc::new(move |bytes: &[u8]| {
+            echo.borrow_mut().process_bytes(bytes)
+        }));
+        Terminal::key_down(&term, "A", Modifiers::default());
+        assert_eq!(term.borrow().screen.get_cell(0, 0).unwrap().ch, b'A');
+    }
 
-/// Toggle fullscreen mode for the canvas element.
-///
-/// When entering fullscreen:
-/// - The canvas fills the screen height (or width if aspect ratio requires)
-/// - Black background fills any remaining space
-/// - Canvas maintains its native aspect ratio (~1.37:1 for 1920x1400)
-///
-/// Uses the Fullscreen API with fallbacks for different browsers.
-fn toggle_fullscreen(canvas: &HtmlCanvasElement) {
-    let document = match web_sys::window().and_then(|w| w.document()) {
-        Some(d) => d,
-        None => return,
-    };
-
-    // Check if we're currently in fullscreen
-    let fullscreen_element = document.fullscreen_element();
-
-    if fullscreen_element.is_some() {
-        // Exit fullscreen
-        document.exit_fullscreen();
-    } else {
-        // Enter fullscreen - request on the canvas element
-        // The canvas will be centered with black background automatically
-        let _ = canvas.request_fullscreen();
+    #[test]
+    fn test_scrollback_keys_not_sent() {
+        let term = RefCell::new(Terminal::new());
+        let sent = capture_output(&mut term.borrow_mut());
+        term.borrow_mut().process_bytes(&b"line\r\n".repeat(30));
+        let alt = Modifiers { alt: true, ..Default::default() };
+        assert_eq!(Terminal::key_down(&term, "k", alt), KeyInput::Local);
+        assert_eq!(Terminal::key_down(&term, "ArrowUp", Modifiers::default()), KeyInput::Local);
+        assert_eq!(Terminal::key_down(&term, "x", Modifiers::default()), KeyInput::Local);
+        assert!(sent.borrow().is_empty());
     }
 }
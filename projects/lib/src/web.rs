@@ -0,0 +1,699 @@
+//! JavaScript API: rendering art files, auto-initialized terminals, and
+//! fonts (the `web` feature).
+
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::art::{self, Format};
+use crate::connection::{Connection, ConnectionOptions};
+use crate::display::RendererKind;
+use crate::dom;
+use crate::export;
+use crate::font;
+use crate::html;
+use crate::parser::AnsiParser;
+use crate::postprocess::CrtEffects;
+use crate::sauce::{self, LetterSpacing};
+use crate::screen::Screen;
+use crate::webterm::{font_or_default, palette_or_default, TerminalConfig, WebTerm};
+
+/// Options for rendering ANSI content.
+#[wasm_bindgen]
+pub struct RenderOptions {
+    /// CSS selector for the container element
+    pub(crate) selector: String,
+    /// Baud rate for rendering simulation (e.g., 2400, 9600). None for instant.
+    pub(crate) bps: Option<u32>,
+    /// Color palette: a preset name ("VGA" by default) or 16 hex colors
+    pub(crate) palette: Option<String>,
+    /// Keep exact 256-color and 24-bit colors instead of the nearest palette entry
+    pub(crate) true_color: Option<bool>,
+    /// Scrollback buffer size (default: 5000)
+    pub(crate) scrollback_lines: Option<u32>,
+    /// iCE colors (bright backgrounds) instead of blinking text
+    pub(crate) ice_colors: Option<bool>,
+    /// Screen width in characters (default: 80, or the SAUCE width)
+    pub(crate) columns: Option<u32>,
+    /// Screen height in characters (default: 25)
+    pub(crate) rows: Option<u32>,
+    /// Text renderer: "canvas" (default) or "webgl"
+    pub(crate) renderer: Option<String>,
+    /// CRT post-processing effects (default: light blur)
+    pub(crate) effects: CrtEffects,
+    /// Registered font name (default: "IBM EGA", or the SAUCE font)
+    pub(crate) font: Option<String>,
+    /// Cell width for 8-pixel fonts: 8 or 9 (default: 8, or the SAUCE letter spacing)
+    pub(crate) letter_spacing: Option<u32>,
+    /// Draw the whole file as a document as tall as the art (default: false)
+    pub(crate) document: Option<bool>,
+}
+
+#[wasm_bindgen]
+impl RenderOptions {
+    /// Create new render options with required selector.
+    #[wasm_bindgen(constructor)]
+    pub fn new(selector: String) -> Self {
+        RenderOptions {
+            selector,
+            bps: None,
+            palette: None,
+            true_color: None,
+            scrollback_lines: None,
+            ice_colors: None,
+            columns: None,
+            rows: None,
+            renderer: None,
+            effects: CrtEffects::default(),
+            font: None,
+            letter_spacing: None,
+            document: None,
+        }
+    }
+
+    /// Set baud rate for rendering simulation.
+    #[wasm_bindgen(js_name = setBps)]
+    pub fn set_bps(mut self, bps: u32) -> Self {
+        self.bps = Some(bps);
+        self
+    }
+
+    /// Set color palette: "VGA", "CGA", "Amiga", "C64", "Solarized", or a
+    /// list of 16 hex colors ("#000000,#aa0000,...") in ANSI color order.
+    #[wasm_bindgen(js_name = setPalette)]
+    pub fn set_palette(mut self, palette: String) -> Self {
+        self.palette = Some(palette);
+        self
+    }
+
+    /// Set true color ("modern") mode: 256-color and 24-bit SGR colors are
+    /// drawn exactly instead of as the nearest palette color.
+    #[wasm_bindgen(js_name = setTrueColor)]
+    pub fn set_true_color(mut self, enabled: bool) -> Self {
+        self.true_color = Some(enabled);
+        self
+    }
+
+    /// Set scrollback buffer size.
+    #[wasm_bindgen(js_name = setScrollbackLines)]
+    pub fn set_scrollback_lines(mut self, lines: u32) -> Self {
+        self.scrollback_lines = Some(lines);
+        self
+    }
+
+    /// Set iCE color mode: the blink attribute selects a bright background
+    /// instead of blinking text. Overrides the SAUCE iCE colors flag.
+    #[wasm_bindgen(js_name = setIceColors)]
+    pub fn set_ice_colors(mut self, enabled: bool) -> Self {
+        self.ice_colors = Some(enabled);
+        self
+    }
+
+    /// Set the screen width in characters (e.g., 80, 132, 160).
+    /// Overrides the SAUCE width.
+    #[wasm_bindgen(js_name = setColumns)]
+    pub fn set_columns(mut self, columns: u32) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Set the screen height in characters (e.g., 25, 43, 50).
+    #[wasm_bindgen(js_name = setRows)]
+    pub fn set_rows(mut self, rows: u32) -> Self {
+        self.rows = Some(rows);
+        self
+    }
+
+    /// Set the text renderer: "canvas" (2D canvas, default) or "webgl"
+    /// (glyphs drawn on the GPU from a small cell texture).
+    #[wasm_bindgen(js_name = setRenderer)]
+    pub fn set_renderer(mut self, renderer: String) -> Self {
+        self.renderer = Some(renderer);
+        self
+    }
+
+    /// Start from a CRT effects preset: "none" (crisp pixels), "default"
+    /// (light blur), or "crt" (curvature, scanlines, glow, and more).
+    /// Unknown names are ignored. Individual effects set afterwards apply on top.
+    #[wasm_bindgen(js_name = setEffects)]
+    pub fn set_effects(mut self, preset: String) -> Self {
+        if let Some(effects) = CrtEffects::from_preset(&preset) {
+            self.effects = effects;
+        }
+        self
+    }
+
+    /// Set the font by registered name (see `registerFont`), e.g. "IBM VGA".
+    /// Overrides the SAUCE font name.
+    #[wasm_bindgen(js_name = setFont)]
+    pub fn set_font(mut self, name: String) -> Self {
+        self.font = Some(name);
+        self
+    }
+
+    /// Set the letter spacing: 9 for VGA-style 9-pixel cells (line-drawing
+    /// characters 0xC0-0xDF extend into the 9th column), or 8. Overrides the
+    /// SAUCE letter spacing flag.
+    #[wasm_bindgen(js_name = setLetterSpacing)]
+    pub fn set_letter_spacing(mut self, pixels: u32) -> Self {
+        self.letter_spacing = Some(pixels);
+        self
+    }
+
+    /// Set document mode: `renderAnsi` draws the whole file into a screen
+    /// that grows to the height of the art, like an ANSI viewer, instead of a
+    /// scrolling terminal. The result is shown from the top and scrolled with
+    /// the scrollback keys. The baud rate is ignored.
+    #[wasm_bindgen(js_name = setDocument)]
+    pub fn set_document(mut self, enabled: bool) -> Self {
+        self.document = Some(enabled);
+        self
+    }
+
+    /// Set the blur spread in pixels (0.0-4.0, default 1.0; 0 disables blur).
+    #[wasm_bindgen(js_name = setBlur)]
+    pub fn set_blur(mut self, amount: f32) -> Self {
+        self.effects.blur = amount;
+        self
+    }
+
+    /// Set scanline darkening (0.0-1.0).
+    #[wasm_bindgen(js_name = setScanlines)]
+    pub fn set_scanlines(mut self, amount: f32) -> Self {
+        self.effects.scanlines = amount;
+        self
+    }
+
+    /// Set phosphor glow (bloom) strength (0.0-1.0).
+    #[wasm_bindgen(js_name = setGlow)]
+    pub fn set_glow(mut self, amount: f32) -> Self {
+        self.effects.glow = amount;
+        self
+    }
+
+    /// Set barrel curvature (0.0-1.0).
+    #[wasm_bindgen(js_name = setCurvature)]
+    pub fn set_curvature(mut self, amount: f32) -> Self {
+        self.effects.curvature = amount;
+        self
+    }
+
+    /// Set vignette darkening toward the edges (0.0-1.0).
+    #[wasm_bindgen(js_name = setVignette)]
+    pub fn set_vignette(mut self, amount: f32) -> Self {
+        self.effects.vignette = amount;
+        self
+    }
+
+    /// Set RGB shadow mask strength (0.0-1.0).
+    #[wasm_bindgen(js_name = setShadowMask)]
+    pub fn set_shadow_mask(mut self, amount: f32) -> Self {
+        self.effects.shadow_mask = amount;
+        self
+    }
+}
+
+/// Initialize WebTerm terminals on the page.
+///
+/// Scans the DOM for elements with `data-term-url` attribute and initializes
+/// terminal instances for each one.
+///
+/// Supported data attributes:
+/// - `data-term-url`: WebSocket URL (required)
+/// - `data-term-palette`: Color palette (a preset name or 16 hex colors, default: "VGA")
+/// - `data-term-true-color`: Set to "true" to draw 256-color and 24-bit colors exactly
+/// - `data-term-scrollback-lines`: Scrollback buffer size (default: 5000)
+/// - `data-term-preconnect-screen`: CP437 ANSI text shown before connecting
+/// - `data-term-connect-button`: Image URL for a connect button overlay
+/// - `data-term-telnet`: Set to "false" to disable telnet negotiation
+/// - `data-term-ice-colors`: Set to "true" for iCE colors instead of blinking text
+/// - `data-term-columns` / `data-term-rows`: Screen size (default: 80x25)
+/// - `data-term-renderer`: Text renderer ("canvas" or "webgl", default: "canvas")
+/// - `data-term-font`: Registered font name (default: "IBM EGA")
+/// - `data-term-letter-spacing`: Set to "9" for 9-pixel VGA cells (default: 8)
+/// - `data-term-effects`: CRT effects preset ("none", "default", or "crt")
+/// - `data-term-blur`, `data-term-scanlines`, `data-term-glow`, `data-term-curvature`,
+///   `data-term-vignette`, `data-term-shadow-mask`: Individual CRT effects
+///
+/// Without WebGL, terminals fall back to plain 2D canvas rendering. The path
+/// used is stored in the container's `data-term-render-path` attribute.
+///
+/// Each terminal shows a pre-connect screen and opens a binary WebSocket to
+/// `data-term-url` when the user presses Enter or clicks the terminal.
+#[wasm_bindgen(js_name = initWebTerm)]
+pub fn init_web_term() {
+    // Set panic hook for better error messages in the browser console
+    #[cfg(feature = "console_error_panic_hook")]
+    console_error_panic_hook::set_once();
+
+    // Log initialization
+    web_sys::console::log_1(&"WebTerm: Initializing terminals...".into());
+
+    // Scan DOM for elements with data-term-url
+    match dom::query_selector_all("[data-term-url]") {
+        Ok(elements) => {
+            web_sys::console::log_1(&format!("WebTerm: Found {} terminal(s)", elements.len()).into());
+
+            for element in elements {
+                if let Err(e) = init_terminal(&element) {
+                    web_sys::console::error_1(&format!("Failed to initialize terminal: {:?}", e).into());
+                }
+            }
+        }
+        Err(e) => {
+            web_sys::console::error_1(&format!("Failed to query terminals: {:?}", e).into());
+        }
+    }
+
+    web_sys::console::log_1(&"WebTerm: Initialization complete".into());
+}
+
+/// Initialize a single terminal instance.
+fn init_terminal(container: &web_sys::Element) -> Result<(), JsValue> {
+    let term_url = dom::get_data_attribute(container, "term-url")
+        .ok_or_else(|| JsValue::from_str("Missing data-term-url"))?;
+
+    let defaults = TerminalConfig::default();
+    let number_attribute = |name: &str, default: usize| {
+        dom::get_data_attribute(container, name)
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(default)
+    };
+
+    // Get palette configuration (default: VGA)
+    let palette_str = dom::get_data_attribute(container, "term-palette")
+        .unwrap_or_else(|| "VGA".to_string());
+
+    let config = TerminalConfig {
+        palette: palette_or_default(&palette_str),
+        true_color: dom::get_data_attribute(container, "term-true-color")
+            .map(|s| s == "true")
+            .unwrap_or(defaults.true_color),
+        // Get scrollback lines configuration (default: 5000)
+        scrollback_lines: number_attribute("term-scrollback-lines", defaults.scrollback_lines),
+        // BBSes expect real blinking text unless iCE colors are requested
+        ice_colors: dom::get_data_attribute(container, "term-ice-colors")
+            .map(|s| s == "true")
+            .unwrap_or(false),
+        columns: number_attribute("term-columns", defaults.columns),
+        rows: number_attribute("term-rows", defaults.rows),
+        renderer: dom::get_data_attribute(container, "term-renderer")
+            .map(|s| RendererKind::from_str(&s))
+            .unwrap_or(defaults.renderer),
+        effects: effects_attributes(container),
+        font: dom::get_data_attribute(container, "term-font")
+            .map(|name| font_or_default(&name))
+            .unwrap_or(defaults.font),
+        nine_pixel_cells: number_attribute("term-letter-spacing", 8) == 9,
+    };
+
+    web_sys::console::log_1(&format!(
+        "WebTerm: Initializing terminal for {} (palette: {}, scrollback: {} lines, {}x{}, iCE colors: {}, renderer: {:?}, font: {})",
+        term_url, palette_str, config.scrollback_lines, config.columns, config.rows, config.ice_colors, config.renderer,
+        config.font.name()
+    ).into());
+
+    let options = ConnectionOptions {
+        url: term_url,
+        telnet: dom::get_data_attribute(container, "term-telnet")
+            .map(|s| s != "false")
+            .unwrap_or(true),
+        preconnect_screen: dom::get_data_attribute(container, "term-preconnect-screen"),
+        connect_button: dom::get_data_attribute(container, "term-connect-button"),
+    };
+
+    // Create the terminal (canvas, renderer, post-processor, and event listeners)
+    let web_term = WebTerm::attach(container, &config)?;
+    let handles = web_term.handles()?;
+    web_sys::console::log_1(&format!("WebTerm: Rendering path: {}", handles.render_path).into());
+
+    // Show the pre-connect screen and wait for Enter or a click to connect
+    Connection::start(options, handles.terminal, handles.frame, &handles.display_canvas)?;
+
+    // Terminals created by initWebTerm live for the lifetime of the page
+    std::mem::forget(web_term);
+
+    Ok(())
+}
+
+/// CRT effects from the `data-term-effects` preset and per-effect attributes.
+fn effects_attributes(container: &web_sys::Element) -> CrtEffects {
+    let mut effects = dom::get_data_attribute(container, "term-effects")
+        .and_then(|s| CrtEffects::from_preset(&s))
+        .unwrap_or_default();
+    for (name, value) in [
+        ("term-blur", &mut effects.blur),
+        ("term-scanlines", &mut effects.scanlines),
+        ("term-glow", &mut effects.glow),
+        ("term-curvature", &mut effects.curvature),
+        ("term-vignette", &mut effects.vignette),
+        ("term-shadow-mask", &mut effects.shadow_mask),
+    ] {
+        if let Some(amount) = dom::get_data_attribute(container, name).and_then(|s| s.parse::<f32>().ok()) {
+            *value = amount;
+        }
+    }
+    effects
+}
+
+/// Render CP437 ANSI content to a container element.
+///
+/// # Arguments
+/// * `content` - CP437 ANSI content as bytes
+/// * `options` - Render options (selector, bps, palette, true_color, scrollback_lines, ice_colors,
+///   columns, rows, renderer, effects, font, letter_spacing)
+///
+/// iCE colors, the screen width, the font, and the letter spacing follow
+/// `options`, then SAUCE, then the defaults (iCE colors on, 80 columns,
+/// "IBM EGA", 8-pixel cells). The rendering path ("webgl",
+/// "canvas", or "2d" without WebGL) is stored in the container's
+/// `data-term-render-path` attribute.
+///
+/// # Example (JavaScript)
+/// ```javascript
+/// const options = new RenderOptions("#terminal")
+///     .setBps(9600)
+///     .setPalette("CGA")
+///     .setScrollbackLines(10000);
+/// renderAnsi(content, options);
+/// ```
+#[wasm_bindgen(js_name = renderAnsi)]
+pub fn render_ansi(content: &[u8], options: RenderOptions) {
+    if options.document.unwrap_or(false) {
+        let shown = decode_art(None, content, &options)
+            .and_then(|(screen, config)| show_screen(&options.selector, &screen, config, "ANSI document"));
+        if let Err(e) = shown {
+            web_sys::console::error_1(&format!("Failed to render ANSI: {:?}", e).into());
+        }
+        return;
+    }
+
+    let config = art_config(content, &options);
+
+    web_sys::console::log_1(&format!(
+        "WebTerm: Rendering ANSI to {} (bps: {:?}, palette: {}, scrollback: {} lines, {}x{}, font: {})",
+        options.selector,
+        options.bps,
+        options.palette.as_deref().unwrap_or("VGA"),
+        config.scrollback_lines,
+        config.columns,
+        config.rows,
+        config.font.name()
+    ).into());
+
+    // Clone data for the async closure
+    let selector = options.selector.clone();
+    let content = content.to_vec();
+    let bps = options.bps;
+
+    spawn_local(async move {
+        match render_ansi_async(&selector, &content, bps, &config).await {
+            Ok(_) => web_sys::console::log_1(&"WebTerm: ANSI rendering complete".into()),
+            Err(e) => web_sys::console::error_1(&format!("Failed to render ANSI: {:?}", e).into()),
+        }
+    });
+}
+
+/// Render an XBin (.XB) file to a container element.
+///
+/// The screen width, iCE colors, palette, and font come from the file, unless
+/// `options` sets the palette, font, or iCE colors. The image is shown from
+/// the top and can be scrolled; `bps` is ignored.
+///
+/// # Example (JavaScript)
+/// ```javascript
+/// const bytes = new Uint8Array(await (await fetch('/art/logo.xb')).arrayBuffer());
+/// renderXBin(bytes, new RenderOptions("#viewer"));
+/// ```
+#[wasm_bindgen(js_name = renderXBin)]
+pub fn render_xbin(content: &[u8], options: RenderOptions) -> Result<(), JsValue> {
+    render_image(Format::XBin, content, &options)
+}
+
+/// Render an art file to a container element, detecting its format.
+///
+/// BIN, XBin, ADF, and IDF files are recognized by the file name's extension
+/// (if given), their signature, or the SAUCE DataType, and shown like
+/// `renderXBin`. Anything else is treated as ANSI and shown with `renderAnsi`.
+///
+/// # Example (JavaScript)
+/// ```javascript
+/// renderArt(bytes, new RenderOptions("#viewer"), "LOGO.ADF");
+/// ```
+#[wasm_bindgen(js_name = renderArt)]
+pub fn render_art(content: &[u8], options: RenderOptions, name: Option<String>) -> Result<(), JsValue> {
+    match art::detect(content, name.as_deref()) {
+        Some(format) => render_image(format, content, &options),
+        None => {
+            render_ansi(content, options);
+            Ok(())
+        }
+    }
+}
+
+/// Export a whole art file as a PNG image.
+///
+/// The file is decoded as with `renderArt` (ANSI is drawn as a document, see
+/// `RenderOptions.setDocument`) and drawn at the font's own resolution, one
+/// image pixel per font pixel (640 pixels wide for 80 columns of an 8-pixel
+/// font). The palette, font, letter spacing, and iCE color settings of
+/// `options` apply; the selector is ignored. Blinking text is drawn visible.
+///
+/// # Example (JavaScript)
+/// ```javascript
+/// const png = exportPng(bytes, new RenderOptions(""), "LOGO.ANS");
+/// const url = URL.createObjectURL(new Blob([png], { type: "image/png" }));
+/// ```
+#[wasm_bindgen(js_name = exportPng)]
+pub fn export_png(content: &[u8], options: RenderOptions, name: Option<String>) -> Result<Vec<u8>, JsValue> {
+    let format = art::detect(content, name.as_deref());
+    let (screen, config) = decode_art(format, content, &options)?;
+    export::to_png(&screen, None, config.palette, config.cell_font(), 1).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Export a whole art file as HTML text.
+///
+/// The file is decoded as with `exportPng` and returned as a `<pre>` block of
+/// Unicode text with inline colors from the palette, so it stays selectable
+/// and searchable. Blinking runs get the `blink` class.
+///
+/// # Example (JavaScript)
+/// ```javascript
+/// post.innerHTML = exportHtml(bytes, new RenderOptions(""), "LOGO.ANS");
+/// ```
+#[wasm_bindgen(js_name = exportHtml)]
+pub fn export_html(content: &[u8], options: RenderOptions, name: Option<String>) -> Result<String, JsValue> {
+    let format = art::detect(content, name.as_deref());
+    let (screen, config) = decode_art(format, content, &options)?;
+    Ok(html::to_html(&screen, None, config.palette))
+}
+
+/// Settings for rendering an art file: `options`, then SAUCE, then defaults.
+///
+/// A SAUCE font name is used only if a font is registered under it (the IBM
+/// EGA/VGA fonts are built in); others, such as "Amiga Topaz 1+" before it is
+/// registered, fall back to the built-in EGA 8x14 font.
+fn art_config(content: &[u8], options: &RenderOptions) -> TerminalConfig {
+    let mut config = TerminalConfig::from_options(options);
+
+    if let Some(sauce) = sauce::parse(content) {
+        web_sys::console::log_1(&format!(
+            "WebTerm: SAUCE \"{}\" by {} / {} ({}x{}, flags: {:#04x}, font: {})",
+            sauce.title,
+            sauce.author,
+            sauce.group,
+            sauce.tinfo1,
+            sauce.tinfo2,
+            sauce.flags,
+            sauce.font_name
+        ).into());
+
+        if options.ice_colors.is_none() {
+            config.ice_colors = sauce.ice_colors();
+        }
+        if let (None, Some(width)) = (options.columns, sauce.width()) {
+            config.columns = width as usize;
+        }
+        if options.font.is_none() && !sauce.font_name.is_empty() {
+            config.font = font_or_default(&sauce.font_name);
+        }
+        if options.letter_spacing.is_none() {
+            config.nine_pixel_cells = sauce.letter_spacing() == LetterSpacing::NinePixel;
+        }
+    }
+    config
+}
+
+/// Decode a binary art file and show it in a new terminal.
+fn render_image(format: Format, content: &[u8], options: &RenderOptions) -> Result<(), JsValue> {
+    let (screen, config) = decode_binary_art(format, content, options)?;
+    show_screen(&options.selector, &screen, config, &format!("{:?}", format))
+}
+
+/// Decode a whole art file into a screen as tall as the art, with the
+/// settings to show it: a binary format, or ANSI drawn as a document (`None`).
+fn decode_art(format: Option<Format>, content: &[u8], options: &RenderOptions) -> Result<(Screen, TerminalConfig), JsValue> {
+    match format {
+        Some(format) => decode_binary_art(format, content, options),
+        None => {
+            let config = art_config(content, options);
+            let mut parser = AnsiParser::new();
+            parser.set_palette(config.palette);
+            parser.set_true_color(config.true_color);
+            parser.set_stop_at_eof(true);
+            let screen = parser.draw_document(content, config.columns, config.ice_colors);
+            Ok((screen, config))
+        }
+    }
+}
+
+/// Decode a binary art file. The screen width, iCE colors, palette, and font
+/// come from the file, unless `options` sets the palette, font, or iCE colors.
+fn decode_binary_art(format: Format, content: &[u8], options: &RenderOptions) -> Result<(Screen, TerminalConfig), JsValue> {
+    let mut image = art::decode(format, content).map_err(|e| JsValue::from_str(&e))?;
+    let mut config = art_config(content, options);
+    config.columns = image.width;
+    config.ice_colors = options.ice_colors.unwrap_or(image.ice_colors);
+    if let (None, Some(palette)) = (&options.palette, image.palette) {
+        config.palette = palette;
+    }
+    if let (None, Some(font)) = (&options.font, image.font.take()) {
+        config.font = Rc::new(font);
+    }
+    Ok((image.to_screen(config.ice_colors), config))
+}
+
+/// Show a complete screen (a decoded image or document) in a new terminal,
+/// from the top, scrollable. Scrollback is enlarged to hold all of it.
+fn show_screen(selector: &str, screen: &Screen, mut config: TerminalConfig, kind: &str) -> Result<(), JsValue> {
+    let (columns, rows) = screen.dimensions();
+    config.scrollback_lines = config.scrollback_lines.max(rows);
+
+    let container = dom::query_selector(selector)?
+        .ok_or_else(|| JsValue::from_str("Container not found"))?;
+
+    let web_term = WebTerm::attach(&container, &config)?;
+    let handles = web_term.handles()?;
+    web_sys::console::log_1(&format!(
+        "WebTerm: Rendering {} {}x{} to {} (path: {})",
+        kind,
+        columns,
+        rows,
+        selector,
+        handles.render_path
+    ).into());
+
+    // Rendered content lives for the lifetime of the page
+    std::mem::forget(web_term);
+
+    {
+        let mut term = handles.terminal.borrow_mut();
+        term.load_screen(screen);
+        term.scrollback.enter_viewer_mode();
+    }
+    let _ = handles.display_canvas.focus();
+    handles.frame.request();
+    Ok(())
+}
+
+async fn render_ansi_async(
+    selector: &str,
+    content: &[u8],
+    bps: Option<u32>,
+    config: &TerminalConfig,
+) -> Result<(), JsValue> {
+    // Find container element
+    let container = dom::query_selector(selector)?
+        .ok_or_else(|| JsValue::from_str("Container not found"))?;
+
+    // Create the terminal (canvas, renderer, post-processor, and event listeners)
+    let web_term = WebTerm::attach(&container, config)?;
+    let handles = web_term.handles()?;
+    let terminal = handles.terminal;
+    web_sys::console::log_1(&format!("WebTerm: Rendering path: {}", handles.render_path).into());
+
+    // Rendered content lives for the lifetime of the page
+    std::mem::forget(web_term);
+
+    // File content ends at ^Z; SAUCE metadata follows
+    terminal.borrow_mut().parser.set_stop_at_eof(true);
+
+    // Focus the canvas so it can receive keyboard events for scrollback
+    let _ = handles.display_canvas.focus();
+
+    match bps {
+        Some(bps) if bps > 0 => {
+            // BPS simulation: render in chunks with delays
+            let bytes_per_second = bps as f64 / 8.0;
+
+            // Render approximately 30 frames per second for smooth animation
+            let target_fps = 30.0;
+            let bytes_per_frame = (bytes_per_second / target_fps).max(1.0) as usize;
+            let frame_delay_ms = (1000.0 / target_fps) as i32;
+
+            let mut offset = 0;
+            while offset < content.len() {
+                let chunk_end = (offset + bytes_per_frame).min(content.len());
+
+                // Process this chunk
+                {
+                    let mut term = terminal.borrow_mut();
+                    term.process_bytes(&content[offset..chunk_end]);
+                }
+
+                // Render the current view (scrollback position or live screen)
+                handles.frame.request();
+
+                offset = chunk_end;
+
+                // Wait before next frame (unless we're done)
+                if offset < content.len() {
+                    sleep_ms(frame_delay_ms).await;
+                }
+            }
+        }
+        _ => {
+            // No BPS - render immediately and enter viewer mode at top
+            {
+                let mut term = terminal.borrow_mut();
+                term.process_bytes(content);
+            }
+            // Enter viewer mode: show content from the top with no indicators
+            {
+                let mut term = terminal.borrow_mut();
+                term.scrollback.enter_viewer_mode();
+            }
+            handles.frame.request();
+        }
+    }
+
+    Ok(())
+}
+
+/// Register a font for `RenderOptions.setFont()`, `data-term-font`, and SAUCE
+/// font names.
+///
+/// Accepts PSF1/PSF2 files and raw 8-pixel wide .F08/.F14/.F16 dumps (256
+/// glyphs, height from the file size). Registering an existing name replaces
+/// that font.
+///
+/// # Example (JavaScript)
+/// ```javascript
+/// const bytes = new Uint8Array(await (await fetch('/fonts/topaz.f16')).arrayBuffer());
+/// registerFont("Amiga Topaz 1+", bytes);
+/// ```
+#[wasm_bindgen(js_name = registerFont)]
+pub fn register_font(name: &str, data: &[u8]) -> Result<(), JsValue> {
+    let font = font::Font::parse(name, data).map_err(|e| JsValue::from_str(&e))?;
+    font::register(font);
+    Ok(())
+}
+
+/// Sleep for the specified number of milliseconds using JavaScript setTimeout
+async fn sleep_ms(ms: i32) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let window = web_sys::window().unwrap();
+        window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms).unwrap();
+    });
+    wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
+}
@@ -0,0 +1,342 @@
+//! JavaScript-facing terminal object.
+//!
+//! Wraps `Terminal` and its `Display` pipeline in a handle so host pages
+//! can stream data in with `write()` and receive keystrokes via `onData()`,
+//! independent of the transport used.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::{Element, HtmlCanvasElement};
+
+use crate::display::{Display, RendererKind};
+use crate::dom;
+use crate::events::{setup_scrollback_events, BlinkTimer, EventListeners, FrameScheduler};
+use crate::export;
+use crate::font::{self, Font};
+use crate::html;
+use crate::palette::Palette;
+use crate::postprocess::CrtEffects;
+use crate::screen::{DEFAULT_COLUMNS, DEFAULT_ROWS};
+use crate::scrollback::DEFAULT_MAX_LINES;
+use crate::terminal::Terminal;
+use crate::RenderOptions;
+
+/// A terminal instance attached to a container element.
+///
+/// # Example (JavaScript)
+/// ```javascript
+/// const term = new WebTerm(new RenderOptions("#terminal").setPalette("CGA"));
+/// term.onData((bytes) => socket.send(bytes));
+/// socket.onmessage = (e) => term.write(new Uint8Array(e.data));
+/// ```
+#[wasm_bindgen]
+pub struct WebTerm {
+    /// None once `dispose()` has been called
+    inner: Option<WebTermInner>,
+}
+
+/// Settings for creating a terminal.
+pub struct TerminalConfig {
+    pub palette: Palette,
+    /// Keep exact SGR 38/48 colors instead of the nearest palette entry
+    pub true_color: bool,
+    pub scrollback_lines: usize,
+    /// iCE colors (bright backgrounds) instead of blinking text
+    pub ice_colors: bool,
+    pub columns: usize,
+    pub rows: usize,
+    /// Text renderer (2D canvas or WebGL)
+    pub renderer: RendererKind,
+    pub effects: CrtEffects,
+    /// Font (cell and canvas size follow its glyph size)
+    pub font: Rc<Font>,
+    /// 9-pixel cells for 8-pixel fonts (VGA letter spacing)
+    pub nine_pixel_cells: bool,
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        TerminalConfig {
+            palette: Palette::Vga,
+            true_color: false,
+            scrollback_lines: DEFAULT_MAX_LINES,
+            ice_colors: true,
+            columns: DEFAULT_COLUMNS,
+            rows: DEFAULT_ROWS,
+            renderer: RendererKind::Canvas,
+            effects: CrtEffects::default(),
+            font: font::default_font(),
+            nine_pixel_cells: false,
+        }
+    }
+}
+
+impl TerminalConfig {
+    /// Settings from `RenderOptions`, with defaults for anything unset.
+    pub fn from_options(options: &RenderOptions) -> Self {
+        let defaults = TerminalConfig::default();
+        TerminalConfig {
+            palette: options.palette.as_deref().map(palette_or_default).unwrap_or(defaults.palette),
+            true_color: options.true_color.unwrap_or(defaults.true_color),
+            scrollback_lines: options
+                .scrollback_lines
+                .map(|n| n as usize)
+                .unwrap_or(defaults.scrollback_lines),
+            ice_colors: options.ice_colors.unwrap_or(defaults.ice_colors),
+            columns: options.columns.map(|n| n as usize).unwrap_or(defaults.columns),
+            rows: options.rows.map(|n| n as usize).unwrap_or(defaults.rows),
+            renderer: options
+                .renderer
+                .as_deref()
+                .map(RendererKind::from_str)
+                .unwrap_or(defaults.renderer),
+            effects: options.effects,
+            font: options.font.as_deref().map(font_or_default).unwrap_or(defaults.font),
+            nine_pixel_cells: options.letter_spacing == Some(9),
+        }
+    }
+
+    /// The font with the configured letter spacing applied.
+    pub fn cell_font(&self) -> Rc<Font> {
+        if self.nine_pixel_cells && self.font.cell_width() == 8 {
+            Rc::new(self.font.as_ref().clone().with_ninth_column())
+        } else {
+            self.font.clone()
+        }
+    }
+}
+
+/// Find a registered font by name, falling back to the default font.
+///
+/// The fallback is the built-in EGA 8x14 font, so an unregistered font such as
+/// "Amiga Topaz 1+" is drawn with 14-pixel cells instead.
+pub fn font_or_default(name: &str) -> Rc<Font> {
+    font::find(name).unwrap_or_else(|| {
+        web_sys::console::warn_1(&format!(
+            "WebTerm: Font \"{}\" is not registered, using {}",
+            name,
+            font::DEFAULT_FONT_NAME
+        ).into());
+        font::default_font()
+    })
+}
+
+/// Parse a palette name or hex color list, falling back to VGA.
+pub fn palette_or_default(palette: &str) -> Palette {
+    Palette::parse(palette).unwrap_or_else(|| {
+        web_sys::console::warn_1(&format!(
+            "WebTerm: Unknown palette \"{}\" (expected a preset name or 16 hex colors), using VGA",
+            palette
+        ).into());
+        Palette::Vga
+    })
+}
+
+/// Shared handles to a `WebTerm`'s internals (see `WebTerm::handles`).
+pub struct TerminalHandles {
+    pub terminal: Rc<RefCell<Terminal>>,
+    /// Schedules a render on the next animation frame
+    pub frame: Rc<FrameScheduler>,
+    pub display_canvas: HtmlCanvasElement,
+    /// Rendering path name (see `Display::path`)
+    pub render_path: &'static str,
+}
+
+struct WebTermInner {
+    terminal: Rc<RefCell<Terminal>>,
+    display: Rc<Display>,
+    display_canvas: HtmlCanvasElement,
+    listeners: EventListeners,
+    frame: Rc<FrameScheduler>,
+    blink: BlinkTimer,
+    /// Cell font, for image export
+    font: Rc<Font>,
+}
+
+#[wasm_bindgen]
+impl WebTerm {
+    /// Create a terminal in the container matched by `options.selector`.
+    ///
+    /// Uses the palette, scrollback size, screen size, iCE color mode,
+    /// renderer, CRT effects, font, and letter spacing from `options` (iCE
+    /// colors are on by default); `bps` is ignored.
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: RenderOptions) -> Result<WebTerm, JsValue> {
+        let container = dom::query_selector(&options.selector)?
+            .ok_or_else(|| JsValue::from_str("Container not found"))?;
+
+        Self::attach(&container, &TerminalConfig::from_options(&options))
+    }
+
+    /// Write CP437 ANSI bytes to the terminal.
+    pub fn write(&self, data: &[u8]) -> Result<(), JsValue> {
+        let inner = self.inner()?;
+        inner.terminal.borrow_mut().process_bytes(data);
+        inner.frame.request();
+        Ok(())
+    }
+
+    /// Register a callback that receives keyboard input as a `Uint8Array`.
+    #[wasm_bindgen(js_name = onData)]
+    pub fn on_data(&self, callback: js_sys::Function) -> Result<(), JsValue> {
+        self.inner()?
+            .terminal
+            .borrow_mut()
+            .set_output_sink(Rc::new(callback));
+        Ok(())
+    }
+
+    /// Clear the screen and scrollback history.
+    pub fn clear(&self) -> Result<(), JsValue> {
+        let inner = self.inner()?;
+        inner.terminal.borrow_mut().clear();
+        inner.frame.request();
+        Ok(())
+    }
+
+    /// Reset the terminal (screen, colors, cursor, and scrollback).
+    pub fn reset(&self) -> Result<(), JsValue> {
+        let inner = self.inner()?;
+        inner.terminal.borrow_mut().reset();
+        inner.frame.request();
+        Ok(())
+    }
+
+    /// Switch between iCE colors (bright backgrounds) and real blinking text.
+    #[wasm_bindgen(js_name = setIceColors)]
+    pub fn set_ice_colors(&self, enabled: bool) -> Result<(), JsValue> {
+        let inner = self.inner()?;
+        inner.terminal.borrow_mut().screen.set_ice_colors(enabled);
+        inner.frame.request();
+        Ok(())
+    }
+
+    /// Change the color palette: a preset ("VGA", "CGA", "Amiga", "C64",
+    /// "Solarized") or a list of 16 hex colors.
+    #[wasm_bindgen(js_name = setPalette)]
+    pub fn set_palette(&self, palette: &str) -> Result<(), JsValue> {
+        let inner = self.inner()?;
+        let palette = palette_or_default(palette);
+        inner.terminal.borrow_mut().parser.set_palette(palette);
+        inner.display.set_palette(palette);
+        inner.frame.request();
+        Ok(())
+    }
+
+    /// Export the screen as a PNG image, below the scrollback history if
+    /// `include_history` is set.
+    ///
+    /// The image is drawn at the font's own resolution (640x350 for 80x25
+    /// with the EGA font), without CRT effects, and blinking text is visible.
+    #[wasm_bindgen(js_name = exportPng)]
+    pub fn export_png(&self, include_history: bool) -> Result<Vec<u8>, JsValue> {
+        let inner = self.inner()?;
+        let term = inner.terminal.borrow();
+        let history = include_history.then_some(&term.scrollback);
+        export::to_png(&term.screen, history, term.parser.palette(), inner.font.clone(), 1)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Export the screen as HTML text, below the scrollback history if
+    /// `include_history` is set.
+    ///
+    /// Returns a `<pre>` block of Unicode text with inline colors from the
+    /// active palette.
+    #[wasm_bindgen(js_name = exportHtml)]
+    pub fn export_html(&self, include_history: bool) -> Result<String, JsValue> {
+        let inner = self.inner()?;
+        let term = inner.terminal.borrow();
+        let history = include_history.then_some(&term.scrollback);
+        Ok(html::to_html(&term.screen, history, term.parser.palette()))
+    }
+
+    /// The rendering path in use: "webgl", "canvas", or "2d" (WebGL unavailable).
+    #[wasm_bindgen(getter, js_name = renderPath)]
+    pub fn render_path(&self) -> Result<String, JsValue> {
+        Ok(self.inner()?.display.path().to_string())
+    }
+
+    /// Detach the terminal from the page and release its resources.
+    ///
+    /// Any further calls on this object will throw.
+    pub fn dispose(&mut self) {
+        if let Some(mut inner) = self.inner.take() {
+            inner.blink.stop();
+            inner.frame.cancel();
+            inner.listeners.remove();
+            inner.display_canvas.remove();
+        }
+    }
+}
+
+impl WebTerm {
+    /// Create a terminal inside an existing container element.
+    pub fn attach(container: &Element, config: &TerminalConfig) -> Result<WebTerm, JsValue> {
+        let terminal = Rc::new(RefCell::new(Terminal::with_size(
+            config.columns,
+            config.rows,
+            config.scrollback_lines,
+        )));
+        {
+            let mut term = terminal.borrow_mut();
+            term.screen.set_ice_colors(config.ice_colors);
+            term.parser.set_palette(config.palette);
+            term.parser.set_true_color(config.true_color);
+        }
+
+        // Create display canvas sized to the screen (WebGL post-processing,
+        // or plain 2D without WebGL)
+        let (columns, rows) = terminal.borrow().screen.dimensions();
+        let (display_canvas, display) = Display::create(columns, rows, config)?;
+        container.append_child(&display_canvas)?;
+        container.set_attribute("data-term-render-path", display.path())?;
+        let display = Rc::new(display);
+
+        let listeners = setup_scrollback_events(&display_canvas, terminal.clone(), display.clone())?;
+
+        // Coalesce writes into one render per animation frame
+        let frame = {
+            let terminal = terminal.clone();
+            let display = display.clone();
+            FrameScheduler::new(move || {
+                let term = terminal.borrow();
+                let _ = display.render(&term.screen, &term.scrollback);
+            })
+        };
+
+        let frame = Rc::new(frame);
+        let blink = BlinkTimer::start(terminal.clone(), display.clone(), frame.clone())?;
+
+        let web_term = WebTerm {
+            inner: Some(WebTermInner {
+                terminal,
+                display,
+                display_canvas,
+                listeners,
+                frame,
+                blink,
+                font: config.cell_font(),
+            }),
+        };
+        web_term.inner()?.frame.request();
+        Ok(web_term)
+    }
+
+    /// Shared handles for driving the terminal from Rust.
+    pub fn handles(&self) -> Result<TerminalHandles, JsValue> {
+        let inner = self.inner()?;
+        Ok(TerminalHandles {
+            terminal: inner.terminal.clone(),
+            frame: inner.frame.clone(),
+            display_canvas: inner.display_canvas.clone(),
+            render_path: inner.display.path(),
+        })
+    }
+
+    fn inner(&self) -> Result<&WebTermInner, JsValue> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| JsValue::from_str("WebTerm has been disposed"))
+    }
+}
@@ -0,0 +1,235 @@
+//! XBin (.XB) art file decoding.
+//!
+//! An XBin file is an 11-byte header ("XBIN", ^Z, width, height, font height,
+//! flags), then an optional 16-color palette, an optional 8-pixel wide font,
+//! and the image as character/attribute pairs, optionally RLE-compressed.
+//! SAUCE metadata may follow the image.
+
+use crate::art::Image;
+use crate::font::{Font, GLYPH_COUNT};
+use crate::palette::{Palette, PALETTE_SIZE};
+use crate::screen::{MAX_COLUMNS, MAX_DOCUMENT_ROWS};
+
+/// File signature: "XBIN" followed by ^Z.
+const MAGIC: &[u8] = b"XBIN\x1a";
+
+/// Header size in bytes.
+const HEADER_SIZE: usize = 11;
+
+/// Flag: a palette follows the header.
+const FLAG_PALETTE: u8 = 0x01;
+/// Flag: a font follows the header (and palette).
+const FLAG_FONT: u8 = 0x02;
+/// Flag: the image data is RLE-compressed.
+const FLAG_COMPRESSED: u8 = 0x04;
+/// Flag: iCE colors (attribute bit 7 selects a bright background).
+const FLAG_NON_BLINK: u8 = 0x08;
+/// Flag: the font has 512 characters.
+const FLAG_512_CHARS: u8 = 0x10;
+
+/// Font height assumed when the header gives 0.
+const DEFAULT_FONT_HEIGHT: usize = 16;
+
+/// Whether the data starts with the XBin signature.
+pub fn is_xbin(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Decode an XBin file.
+///
+/// Image data cut short leaves the remaining cells blank. Images wider than
+/// `MAX_COLUMNS` are rejected, and only the first `MAX_DOCUMENT_ROWS` rows
+/// are read. 512-character files are rejected: foreground bit 3 selects the
+/// font half there, and cells only hold 256 characters.
+pub fn decode(data: &[u8]) -> Result<Image, String> {
+    if !is_xbin(data) || data.len() < HEADER_SIZE {
+        return Err("Not an XBin file".to_string());
+    }
+    let width = u16::from_le_bytes([data[5], data[6]]) as usize;
+    let height = u16::from_le_bytes([data[7], data[8]]) as usize;
+    let font_height = match data[9] as usize {
+        0 => DEFAULT_FONT_HEIGHT,
+        n => n,
+    };
+    let flags = data[10];
+    if width == 0 || height == 0 {
+        return Err("XBin image is empty".to_string());
+    }
+    if width > MAX_COLUMNS {
+        return Err(format!("XBin image is too wide ({} columns)", width));
+    }
+    if flags & FLAG_512_CHARS != 0 {
+        return Err("512-character XBin files are not supported".to_string());
+    }
+    let height = height.min(MAX_DOCUMENT_ROWS);
+
+    let mut rest = &data[HEADER_SIZE..];
+    let palette = if flags & FLAG_PALETTE != 0 {
+        let dac = take(&mut rest, PALETTE_SIZE * 3).ok_or("XBin palette is truncated")?;
+        Some(Palette::from_dac(dac.try_into().expect("palette size")))
+    } else {
+        None
+    };
+
+    let font = if flags & FLAG_FONT != 0 {
+        let font_data = take(&mut rest, GLYPH_COUNT * font_height).ok_or("XBin font is truncated")?;
+        Some(Font::from_raw("XBin", font_data)?)
+    } else {
+        None
+    };
+
+    let data = if flags & FLAG_COMPRESSED != 0 {
+        decompress(rest, width * height)
+    } else {
+        rest[..rest.len().min(width * height * 2)].to_vec()
+    };
+
+    Ok(Image {
+        width,
+        height,
+        data,
+        ice_colors: flags & FLAG_NON_BLINK != 0,
+        palette,
+        font,
+    })
+}
+
+/// Expand RLE-compressed image data into up to `cells` character/attribute
+/// pairs.
+///
+/// Each run starts with a byte whose top two bits give the type and low six
+/// bits the length minus one:
+/// - 0: `length` character/attribute pairs
+/// - 1: one character, then `length` attributes
+/// - 2: one attribute, then `length` characters
+/// - 3: one character/attribute pair, repeated `length` times
+fn decompress(mut data: &[u8], cells: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    while out.len() < cells * 2 {
+        let Some(run) = take(&mut data, 1) else { break };
+        let length = (run[0] & 0x3F) as usize + 1;
+        let expanded = match run[0] >> 6 {
+            0 => take(&mut data, length * 2).map(<[u8]>::to_vec),
+            1 => take(&mut data, 1 + length)
+                .map(|bytes| bytes[1..].iter().flat_map(|&attr| [bytes[0], attr]).collect()),
+            2 => take(&mut data, 1 + length)
+                .map(|bytes| bytes[1..].iter().flat_map(|&ch| [ch, bytes[0]]).collect()),
+            _ => take(&mut data, 2).map(|pair| pair.repeat(length)),
+        };
+        match expanded {
+            Some(bytes) => out.extend(bytes),
+            None => break,
+        }
+    }
+    out.truncate(cells * 2);
+    out
+}
+
+/// Split `len` bytes off the front of `data`.
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if data.len() < len {
+        return None;
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Some(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ansi_color_to_rgb_tuple;
+
+    /// 4x2, no palette or font, uncompressed, blink mode.
+    const PLAIN: &[u8] = include_bytes!("../fixtures/plain.xb");
+    /// 12x3 with palette, 8x8 font, compression, and iCE colors, plus SAUCE.
+    const COMPRESSED: &[u8] = include_bytes!("../fixtures/compressed.xb");
+
+    #[test]
+    fn test_plain() {
+        let xbin = decode(PLAIN).unwrap();
+        let screen = xbin.to_screen(xbin.ice_colors);
+        assert_eq!((xbin.width, xbin.height), (4, 2));
+        assert!(xbin.palette.is_none());
+        assert!(xbin.font.is_none());
+        assert!(!xbin.ice_colors);
+        assert_eq!(screen.row_text(0), "ABCD");
+        assert_eq!(screen.row_text(1), "EFGH");
+
+        // 0x1F: white on blue
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!((cell.fg, cell.bg, cell.blink), (15, 4, false));
+        // 0x8C: blinking light red on black
+        let cell = screen.get_cell(0, 1).unwrap();
+        assert_eq!((cell.fg, cell.bg, cell.blink), (9, 0, true));
+    }
+
+    #[test]
+    fn test_compressed_with_palette_and_font() {
+        let xbin = decode(COMPRESSED).unwrap();
+        let screen = xbin.to_screen(xbin.ice_colors);
+        assert_eq!((xbin.width, xbin.height), (12, 3));
+        assert!(xbin.ice_colors);
+        assert_eq!(screen.row_text(0), "XBIN fixture");
+        assert_eq!(screen.row_text(1), "\u{DB}\u{DB}\u{DB}\u{DB}\u{B1}\u{B1}\u{B1}\u{B1}abcd");
+        assert_eq!(screen.row_text(2), "------------");
+
+        // Character run: one attribute per cell
+        let fgs: Vec<u8> = (0..4).map(|x| screen.get_cell(x, 1).unwrap().fg).collect();
+        assert_eq!(fgs, [4, 2, 6, 1]);
+        // Attribute run: yellow on a bright blue background from bit 7
+        let cell = screen.get_cell(8, 1).unwrap();
+        assert_eq!((cell.fg, cell.bg, cell.blink), (11, 12, false));
+
+        // DOS color 1 is defined as orange, so ANSI color 4 is orange
+        let palette = xbin.palette.unwrap();
+        assert_eq!(ansi_color_to_rgb_tuple(4, palette), (0xFF, 0x82, 0x00));
+
+        let font = xbin.font.unwrap();
+        assert_eq!((font.width(), font.height()), (8, 8));
+        assert!(font.is_set(b'A', 0, 0));
+        assert!(!font.is_set(b'B', 0, 0));
+    }
+
+    #[test]
+    fn test_truncated_image_is_blank() {
+        let xbin = decode(&PLAIN[..HEADER_SIZE + 5]).unwrap();
+        let screen = xbin.to_screen(xbin.ice_colors);
+        assert_eq!(screen.row_text(0), "AB  ");
+        assert_eq!(screen.row_text(1), "    ");
+
+        // Header, palette, font, row 0, and the first run of row 1
+        let xbin = decode(&COMPRESSED[..HEADER_SIZE + 48 + 2048 + 26]).unwrap();
+        let screen = xbin.to_screen(xbin.ice_colors);
+        assert_eq!(screen.row_text(0), "XBIN fixture");
+        assert_eq!(screen.row_text(1), "\u{DB}\u{DB}\u{DB}\u{DB}        ");
+        assert_eq!(screen.row_text(2), "            ");
+    }
+
+    #[test]
+    fn test_oversized_header() {
+        // 65535x65535, compressed, with a single run
+        let data = b"XBIN\x1a\xff\xff\xff\xff\x10\x04\xc0A\x07";
+        assert!(decode(data).is_err());
+
+        // 4x65535: rows past the document limit are dropped
+        let data = b"XBIN\x1a\x04\x00\xff\xff\x10\x04\xc0A\x07";
+        let xbin = decode(data).unwrap();
+        assert_eq!((xbin.width, xbin.height), (4, MAX_DOCUMENT_ROWS));
+        assert_eq!(xbin.data, b"A\x07");
+    }
+
+    #[test]
+    fn test_512_characters_rejected() {
+        let mut data = PLAIN.to_vec();
+        data[10] |= FLAG_512_CHARS;
+        assert!(decode(&data).is_err());
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        assert!(decode(b"\x1b[0mANSI").is_err());
+        assert!(decode(b"XBIN\x1a\x00\x00\x19\x00\x10\x00").is_err());
+        assert!(decode(&COMPRESSED[..HEADER_SIZE + 20]).is_err());
+    }
+}
@@ -1,10 +1,14 @@
 use axum::{
-    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
     response::{Html, IntoResponse},
-    routing::{get, post},
+    routing::get,
     Router,
 };
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tower_http::{services::ServeDir, trace::TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -21,12 +25,23 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    // Targets the bridge may connect to (none unless configured)
+    let allowed_hosts = websocket::AllowedHosts::from_env();
+    if allowed_hosts.is_empty() {
+        tracing::warn!(
+            "{} is not set, so every connection will be refused (e.g. {}=bbs.example.com:23)",
+            websocket::ALLOWED_HOSTS_VAR,
+            websocket::ALLOWED_HOSTS_VAR
+        );
+    }
+
     // Build the application router
     let app = Router::new()
         .route("/", get(index_handler))
         .route("/ws", get(ws_handler))
         .nest_service("/static", ServeDir::new("static"))
-        .layer(TraceLayer::new_for_http());
+        .layer(TraceLayer::new_for_http())
+        .with_state(Arc::new(allowed_hosts));
 
     // Run the server
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
@@ -43,18 +58,47 @@ async fn index_handler() -> Html<&'static str> {
 }
 
 /// WebSocket upgrade handler
-async fn ws_handler(ws: WebSocketUpgrade) -> impl IntoResponse {
-    ws.on_upgrade(handle_socket)
+///
+/// The target may be given as `?host=...&port=...`; otherwise it is read
+/// from the first message sent by the client.
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<websocket::ConnectParams>,
+    State(allowed_hosts): State<Arc<websocket::AllowedHosts>>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_socket(socket, params, allowed_hosts))
 }
 
 /// Handle WebSocket connection
-async fn handle_socket(socket: WebSocket) {
+async fn handle_socket(
+    mut socket: WebSocket,
+    params: websocket::ConnectParams,
+    allowed_hosts: Arc<websocket::AllowedHosts>,
+) {
     tracing::info!("New WebSocket connection");
 
-    // TODO: Parse connection parameters from first message
-    // TODO: Connect to TCP server
-    // TODO: Bridge WebSocket <-> TCP
+    // Query string takes priority, then fall back to the first message
+    let target = match params.target() {
+        Some(target) => Some(target),
+        None => websocket::read_connect_message(&mut socket).await,
+    };
 
-    // For now, just echo messages back
-    websocket::echo_handler(socket).await;
+    match target {
+        Some((host, port)) if allowed_hosts.allows(&host, port) => {
+            websocket::tcp_bridge_handler(socket, host, port).await
+        }
+        Some((host, port)) => {
+            tracing::warn!(
+                "Refusing connection to {}:{} (not in {})",
+                host,
+                port,
+                websocket::ALLOWED_HOSTS_VAR
+            );
+            let _ = socket.send(Message::Close(None)).await;
+        }
+        None => {
+            tracing::warn!("No connection target supplied, closing WebSocket");
+            let _ = socket.send(Message::Close(None)).await;
+        }
+    }
 }
@@ -1,41 +1,344 @@
 use axum::extract::ws::{Message, WebSocket};
 use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
 
-/// Echo handler for testing (will be replaced with TCP bridge)
-pub async fn echo_handler(socket: WebSocket) {
-    let (mut sender, mut receiver) = socket.split();
+/// Default telnet port used when the client does not specify one.
+pub const DEFAULT_PORT: u16 = 23;
 
-    while let Some(msg) = receiver.next().await {
+/// Size of the TCP read buffer (one WebSocket frame per read).
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// How long to wait for the TCP connection before giving up.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Environment variable listing the targets the bridge may connect to.
+pub const ALLOWED_HOSTS_VAR: &str = "ALLOWED_HOSTS";
+
+/// Connection target supplied by the client.
+///
+/// Accepted either as query string parameters (`/ws?host=bbs.example.com&port=23`)
+/// or as the first WebSocket message.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ConnectParams {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+}
+
+impl ConnectParams {
+    /// Resolve to a (host, port) pair if a host was given.
+    ///
+    /// Brackets around an IPv6 address (`[::1]`) are removed.
+    pub fn target(&self) -> Option<(String, u16)> {
+        let host = self.host.as_deref().map(str::trim)?;
+        let host = host
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .unwrap_or(host);
+        if host.is_empty() {
+            return None;
+        }
+        Some((host.to_string(), self.port.unwrap_or(DEFAULT_PORT)))
+    }
+}
+
+/// Targets the bridge is allowed to connect to.
+///
+/// Without an allow list the bridge would be an open TCP proxy, so an empty
+/// list denies every target. Entries are `host` (any port) or `host:port`;
+/// `*` allows any target.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AllowedHosts {
+    any: bool,
+    entries: Vec<(String, Option<u16>)>,
+}
+
+impl AllowedHosts {
+    /// Parse a comma-separated list such as `bbs.example.com:23, [::1]:2323`.
+    ///
+    /// Entries that can't be parsed are skipped with a warning.
+    pub fn parse(list: &str) -> Self {
+        let mut allowed = AllowedHosts::default();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            if entry == "*" {
+                allowed.any = true;
+            } else if let Some((host, port)) = split_host_port(entry) {
+                allowed.entries.push((host.to_lowercase(), port));
+            } else {
+                tracing::warn!("Ignoring invalid allowed host {:?}", entry);
+            }
+        }
+        allowed
+    }
+
+    /// Read the list from the `ALLOWED_HOSTS` environment variable.
+    pub fn from_env() -> Self {
+        Self::parse(&std::env::var(ALLOWED_HOSTS_VAR).unwrap_or_default())
+    }
+
+    /// Whether no target is allowed.
+    pub fn is_empty(&self) -> bool {
+        !self.any && self.entries.is_empty()
+    }
+
+    /// Whether the bridge may connect to `host:port` (host names are compared
+    /// case-insensitively).
+    pub fn allows(&self, host: &str, port: u16) -> bool {
+        self.any
+            || self.entries.iter().any(|(allowed, allowed_port)| {
+                allowed.eq_ignore_ascii_case(host) && allowed_port.is_none_or(|p| p == port)
+            })
+    }
+}
+
+/// Split `host`, `host:port`, `[v6]`, or `[v6]:port`, removing the brackets.
+///
+/// A bare IPv6 address (`::1`) is rejected, since its last group can't be told
+/// apart from a port.
+fn split_host_port(text: &str) -> Option<(String, Option<u16>)> {
+    let (host, port) = if let Some(rest) = text.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']')?;
+        let port = match rest {
+            "" => None,
+            _ => Some(rest.strip_prefix(':')?),
+        };
+        (host, port)
+    } else {
+        match text.split_once(':') {
+            Some((_, port)) if port.contains(':') => return None,
+            Some((host, port)) => (host, Some(port)),
+            None => (text, None),
+        }
+    };
+    let port = match port {
+        Some(port) => Some(port.trim().parse::<u16>().ok()?),
+        None => None,
+    };
+    let host = host.trim();
+    (!host.is_empty()).then(|| (host.to_string(), port))
+}
+
+/// Parse connection parameters from the first WebSocket message.
+///
+/// Supported forms:
+/// - JSON: `{"host": "bbs.example.com", "port": 23}`
+/// - Plain text: `bbs.example.com:23` or `bbs.example.com` (default port 23)
+/// - IPv6 addresses in brackets: `[::1]:23` or `[::1]`
+pub fn parse_connect_message(text: &str) -> Option<(String, u16)> {
+    let text = text.trim();
+
+    if text.starts_with('{') {
+        return serde_json::from_str::<ConnectParams>(text)
+            .ok()
+            .and_then(|params| params.target());
+    }
+
+    let (host, port) = split_host_port(text)?;
+    ConnectParams {
+        host: Some(host),
+        port,
+    }
+    .target()
+}
+
+/// Wait for the first message and parse connection parameters from it.
+///
+/// Returns None if the client closes or sends something unparseable.
+pub async fn read_connect_message(socket: &mut WebSocket) -> Option<(String, u16)> {
+    while let Some(msg) = socket.recv().await {
         match msg {
-            Ok(Message::Text(text)) => {
-                tracing::debug!("Received text: {}", text);
-                if sender.send(Message::Text(text)).await.is_err() {
+            Ok(Message::Text(text)) => return parse_connect_message(&text),
+            Ok(Message::Binary(data)) => {
+                return parse_connect_message(&String::from_utf8_lossy(&data));
+            }
+            Ok(Message::Close(_)) => return None,
+            Err(e) => {
+                tracing::error!("WebSocket error: {}", e);
+                return None;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Bridge a WebSocket connection to a TCP server.
+///
+/// Bytes received from the TCP server are forwarded as binary frames, and
+/// binary (or text) frames from the client are written to the TCP stream.
+/// When either side closes, the other side is shut down.
+pub async fn tcp_bridge_handler(mut socket: WebSocket, host: String, port: u16) {
+    tracing::info!("Connecting to {}:{}", host, port);
+
+    let stream = match timeout(CONNECT_TIMEOUT, TcpStream::connect((host.as_str(), port))).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            tracing::error!("Failed to connect to {}:{}: {}", host, port, e);
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+        Err(_) => {
+            tracing::error!(
+                "Timed out connecting to {}:{} after {:?}",
+                host,
+                port,
+                CONNECT_TIMEOUT
+            );
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+    };
+    let _ = stream.set_nodelay(true);
+
+    tracing::info!("Connected to {}:{}", host, port);
+
+    let (mut tcp_reader, mut tcp_writer) = stream.into_split();
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+
+    // TCP -> WebSocket
+    let mut tcp_to_ws = tokio::spawn(async move {
+        let mut buf = vec![0u8; READ_BUFFER_SIZE];
+        loop {
+            match tcp_reader.read(&mut buf).await {
+                Ok(0) => {
+                    tracing::info!("TCP connection closed by server");
                     break;
                 }
-            }
-            Ok(Message::Binary(data)) => {
-                tracing::debug!("Received {} bytes", data.len());
-                if sender.send(Message::Binary(data)).await.is_err() {
+                Ok(n) => {
+                    if ws_sender
+                        .send(Message::Binary(buf[..n].to_vec()))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("TCP read error: {}", e);
                     break;
                 }
             }
-            Ok(Message::Close(_)) => {
-                tracing::info!("WebSocket closed by client");
-                break;
-            }
-            Err(e) => {
-                tracing::error!("WebSocket error: {}", e);
+        }
+        let _ = ws_sender.send(Message::Close(None)).await;
+    });
+
+    // WebSocket -> TCP
+    let mut ws_to_tcp = tokio::spawn(async move {
+        while let Some(msg) = ws_receiver.next().await {
+            let data = match msg {
+                Ok(Message::Binary(data)) => data,
+                Ok(Message::Text(text)) => text.into_bytes(),
+                Ok(Message::Close(_)) => {
+                    tracing::info!("WebSocket closed by client");
+                    break;
+                }
+                Err(e) => {
+                    tracing::error!("WebSocket error: {}", e);
+                    break;
+                }
+                _ => continue,
+            };
+            if tcp_writer.write_all(&data).await.is_err() {
                 break;
             }
-            _ => {}
         }
+        let _ = tcp_writer.shutdown().await;
+    });
+
+    // Whichever direction finishes first tears down the other
+    tokio::select! {
+        _ = &mut tcp_to_ws => ws_to_tcp.abort(),
+        _ = &mut ws_to_tcp => tcp_to_ws.abort(),
     }
 
-    tracing::info!("WebSocket connection closed");
+    tracing::info!("Bridge to {}:{} closed", host, port);
 }
 
-// TODO: Implement TCP bridge
-// pub async fn tcp_bridge_handler(socket: WebSocket, host: String, port: u16) {
-//     // Connect to TCP server
-//     // Bridge messages between WebSocket and TCP
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_host_and_port() {
+        assert_eq!(
+            parse_connect_message("bbs.example.com:2323"),
+            Some(("bbs.example.com".to_string(), 2323))
+        );
+    }
+
+    #[test]
+    fn test_parse_host_default_port() {
+        assert_eq!(
+            parse_connect_message("  bbs.example.com\n"),
+            Some(("bbs.example.com".to_string(), DEFAULT_PORT))
+        );
+    }
+
+    #[test]
+    fn test_parse_json() {
+        assert_eq!(
+            parse_connect_message(r#"{"host": "bbs.example.com", "port": 23}"#),
+            Some(("bbs.example.com".to_string(), 23))
+        );
+        assert_eq!(
+            parse_connect_message(r#"{"host": "bbs.example.com"}"#),
+            Some(("bbs.example.com".to_string(), DEFAULT_PORT))
+        );
+    }
+
+    #[test]
+    fn test_parse_ipv6() {
+        assert_eq!(
+            parse_connect_message("[::1]:2323"),
+            Some(("::1".to_string(), 2323))
+        );
+        assert_eq!(
+            parse_connect_message("[2001:db8::1]"),
+            Some(("2001:db8::1".to_string(), DEFAULT_PORT))
+        );
+        assert_eq!(
+            parse_connect_message(r#"{"host": "[::1]", "port": 23}"#),
+            Some(("::1".to_string(), 23))
+        );
+        // Bare IPv6 addresses are ambiguous
+        assert_eq!(parse_connect_message("::1"), None);
+        assert_eq!(parse_connect_message("2001:db8::1:23"), None);
+        assert_eq!(parse_connect_message("[::1]23"), None);
+        assert_eq!(parse_connect_message("[::1"), None);
+        assert_eq!(parse_connect_message("[]:23"), None);
+    }
+
+    #[test]
+    fn test_allowed_hosts() {
+        let allowed =
+            AllowedHosts::parse("bbs.example.com:23, Other.example.com, [::1]:2323, bad:port");
+        assert!(allowed.allows("bbs.example.com", 23));
+        assert!(allowed.allows("BBS.example.com", 23));
+        assert!(!allowed.allows("bbs.example.com", 22));
+        assert!(allowed.allows("other.example.com", 6400));
+        assert!(allowed.allows("::1", 2323));
+        assert!(!allowed.allows("::1", 23));
+        assert!(!allowed.allows("localhost", 23));
+        assert!(!allowed.allows("bad", 23));
+    }
+
+    #[test]
+    fn test_allowed_hosts_default_deny() {
+        let allowed = AllowedHosts::parse("");
+        assert!(allowed.is_empty());
+        assert!(!allowed.allows("bbs.example.com", 23));
+
+        let allowed = AllowedHosts::parse("*");
+        assert!(!allowed.is_empty());
+        assert!(allowed.allows("anything.example.com", 22));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse_connect_message(""), None);
+        assert_eq!(parse_connect_message("bbs.example.com:notaport"), None);
+        assert_eq!(parse_connect_message(r#"{"port": 23}"#), None);
+    }
+}